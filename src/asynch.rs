@@ -0,0 +1,711 @@
+//! Async driver for the Notecard, using `embedded-hal-async`.
+//!
+//! The driver yields to the executor while waiting for responses, instead of blocking for the
+//! duration of e.g. a sync or GPS fix. Requests are made with the request types of the API
+//! modules, and the response is deserialized into the matching response type:
+//!
+//! ```ignore
+//! let mut note = AsyncNotecard::<_>::new(i2c);
+//! note.initialize(&mut delay).await?;
+//!
+//! let r: hub::res::SyncStatus = note
+//!     .request_raw(&mut delay, b"{\"req\":\"hub.sync.status\"}\n")
+//!     .await?;
+//! ```
+
+#[allow(unused_imports)]
+use crate::fmt::{debug, error, info, trace, warn, wire};
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use embedded_hal_async::delay::DelayNs;
+use embedded_hal_async::i2c::I2c;
+use heapless::Vec;
+use serde::{de::DeserializeOwned, Serialize};
+
+use super::buffer::Buffer;
+use super::observer::request_name;
+use super::{
+    deserialize_response, FutureResponse, NoteError, NotecardConfig, NotecardTransport,
+    DEFAULT_BUF_SIZE,
+};
+
+/// Largest chunk that can be read or written over I2C at once (excluding the header).
+const I2C_CHUNK_MAX: usize = 126;
+
+/// An async bus to the Notecard, see [`NotecardTransport`](crate::NotecardTransport).
+#[allow(async_fn_in_trait)]
+pub trait AsyncNotecardTransport {
+    /// Check if the Notecard is connected and responding.
+    async fn ping(&mut self) -> bool;
+
+    /// Send a chunk of a request.
+    async fn write(&mut self, data: &[u8]) -> Result<(), NoteError>;
+
+    /// Number of response bytes ready to be read, `0` if no response is ready yet.
+    async fn available(&mut self) -> Result<usize, NoteError>;
+
+    /// Read response bytes into `buf`, which is not larger than the number of bytes available.
+    /// Returns the number of bytes read and the number of bytes still available.
+    async fn read(&mut self, buf: &mut [u8]) -> Result<(usize, usize), NoteError>;
+}
+
+/// The serial-over-I2C protocol using an async I2C bus.
+pub struct AsyncI2cTransport<IOM: I2c> {
+    i2c: IOM,
+    addr: u8,
+}
+
+impl<IOM: I2c> AsyncI2cTransport<IOM> {
+    pub fn new(i2c: IOM, addr: u8) -> AsyncI2cTransport<IOM> {
+        AsyncI2cTransport { i2c, addr }
+    }
+
+    /// Release the I2C device.
+    pub fn free(self) -> IOM {
+        self.i2c
+    }
+}
+
+impl<IOM: I2c> AsyncNotecardTransport for AsyncI2cTransport<IOM> {
+    async fn ping(&mut self) -> bool {
+        self.i2c.write(self.addr, &[]).await.is_ok()
+    }
+
+    async fn write(&mut self, data: &[u8]) -> Result<(), NoteError> {
+        // Each chunk is prefixed with its length.
+        let mut buf = Vec::<u8, { I2C_CHUNK_MAX + 2 }>::new();
+        buf.push(data.len() as u8)
+            .map_err(|_| NoteError::BufOverflow)?;
+        buf.extend_from_slice(data)
+            .map_err(|_| NoteError::BufOverflow)?;
+
+        self.i2c
+            .write(self.addr, &buf)
+            .await
+            .map_err(|_| NoteError::I2cWriteError)
+    }
+
+    async fn available(&mut self) -> Result<usize, NoteError> {
+        // Ask for reading, but with zero bytes allocated.
+        self.i2c
+            .write(self.addr, &[0, 0])
+            .await
+            .map_err(|_| NoteError::I2cWriteError)?;
+
+        let mut buf = [0u8; 2];
+
+        self.i2c
+            .read(self.addr, &mut buf)
+            .await
+            .map_err(|_| NoteError::I2cReadError)?;
+
+        let available = buf[0] as usize;
+        let sent = buf[1] as usize;
+
+        if sent > 0 {
+            error!(
+                "data query: bytes sent when querying available bytes: {}",
+                sent
+            );
+            Err(NoteError::RemainingData)
+        } else {
+            Ok(available)
+        }
+    }
+
+    async fn read(&mut self, buf: &mut [u8]) -> Result<(usize, usize), NoteError> {
+        let mut bytes = [0u8; I2C_CHUNK_MAX + 2];
+        let sz = I2C_CHUNK_MAX.min(buf.len());
+
+        // Ask for reading `sz` bytes
+        self.i2c
+            .write(self.addr, &[0, sz as u8])
+            .await
+            .map_err(|_| NoteError::I2cWriteError)?;
+
+        self.i2c
+            .read(self.addr, &mut bytes[..sz + 2])
+            .await
+            .map_err(|_| NoteError::I2cReadError)?;
+
+        buf[..sz].copy_from_slice(&bytes[2..sz + 2]);
+
+        Ok((sz, bytes[0] as usize))
+    }
+}
+
+/// The async driver for the Notecard. Must be initialized before making any requests.
+///
+/// Unlike [`Notecard`](crate::Notecard) a request is made and its response read in one call, so
+/// the driver can not be left waiting for a response unless the future is dropped or writing the
+/// request fails. A partially written request is then terminated, and the response discarded
+/// before the next request, waiting up to the response timeout for it.
+pub struct AsyncNotecard<IOM: AsyncNotecardTransport, const BUF_SIZE: usize = DEFAULT_BUF_SIZE> {
+    transport: IOM,
+
+    /// A request has been sent, but the response has not been read.
+    pending: bool,
+
+    /// Writing a request was started, but not finished.
+    partial: bool,
+
+    buf: Buffer<BUF_SIZE>,
+
+    response_timeout: u32,
+    poll_interval: u16,
+    chunk_length: usize,
+    segment_length: usize,
+    chunk_delay: u16,
+    segment_delay: u16,
+}
+
+impl<I2C: I2c, const BUF_SIZE: usize> AsyncNotecard<AsyncI2cTransport<I2C>, BUF_SIZE> {
+    /// Create a driver for a Notecard connected over I2C.
+    pub fn new(i2c: I2C) -> AsyncNotecard<AsyncI2cTransport<I2C>, BUF_SIZE> {
+        Self::new_with_config(i2c, NotecardConfig::default())
+    }
+
+    pub fn new_with_config(
+        i2c: I2C,
+        c: NotecardConfig,
+    ) -> AsyncNotecard<AsyncI2cTransport<I2C>, BUF_SIZE> {
+        let transport = AsyncI2cTransport::new(i2c, c.i2c_addr);
+        AsyncNotecard::new_with_transport(transport, c)
+    }
+}
+
+impl<IOM: AsyncNotecardTransport, const BUF_SIZE: usize> AsyncNotecard<IOM, BUF_SIZE> {
+    /// Create a driver for a Notecard connected through `transport`.
+    pub fn new_with_transport(transport: IOM, c: NotecardConfig) -> AsyncNotecard<IOM, BUF_SIZE> {
+        let (chunk_length, segment_length) = c.chunking();
+
+        AsyncNotecard {
+            transport,
+            pending: false,
+            partial: false,
+            buf: Buffer::new(),
+            response_timeout: c.response_timeout,
+            poll_interval: c.poll_interval.max(1),
+            chunk_length,
+            segment_length,
+            chunk_delay: c.chunk_delay,
+            segment_delay: c.segment_delay,
+        }
+    }
+
+    /// Release the transport.
+    pub fn free(self) -> IOM {
+        self.transport
+    }
+
+    /// Timeout while waiting for a response (ms).
+    pub fn response_timeout(&self) -> u32 {
+        self.response_timeout
+    }
+
+    /// Set the timeout while waiting for a response (ms).
+    pub fn set_response_timeout(&mut self, timeout: u32) {
+        self.response_timeout = timeout;
+    }
+
+    /// Interval between polls while waiting for a response (ms).
+    pub fn poll_interval(&self) -> u16 {
+        self.poll_interval
+    }
+
+    /// Set the interval between polls while waiting for a response (ms). At least 1 ms.
+    pub fn set_poll_interval(&mut self, interval: u16) {
+        self.poll_interval = interval.max(1);
+    }
+
+    /// Initialize the notecard driver by performing handshake with notecard.
+    pub async fn initialize(&mut self, delay: &mut impl DelayNs) -> Result<(), NoteError> {
+        info!("note: initializing.");
+        self.buf.clear();
+        self.pending = false;
+        self.partial = false;
+
+        if self.transport.available().await? > 0 {
+            warn!("note: initialize: remaining data in queue, consuming..");
+            self.read_response(delay).await?;
+            self.buf.clear();
+        }
+
+        Ok(())
+    }
+
+    /// Check if notecarrier is connected and responding.
+    pub async fn ping(&mut self) -> bool {
+        self.transport.ping().await
+    }
+
+    /// Discard the response to a request whose future was dropped before the response was read,
+    /// waiting up to the response timeout for the response to arrive. A request that was not
+    /// written in full is terminated first, the Notecard responds to it with an error.
+    async fn handshake(&mut self, delay: &mut impl DelayNs) -> Result<(), NoteError> {
+        if self.partial {
+            warn!("note: terminating partially written request.");
+
+            self.transport.write(b"\n").await?;
+            delay.delay_ms(self.segment_delay as u32).await;
+
+            self.partial = false;
+            self.pending = true;
+        }
+
+        if self.pending {
+            warn!("note: discarding response to abandoned request.");
+
+            match self.read_response(delay).await {
+                // Read in full, but discarded anyway.
+                Ok(()) | Err(NoteError::ResponseTooLarge { .. }) => (),
+                // Nothing to discard.
+                Err(NoteError::TimeOut { .. }) => warn!("note: no response to abandoned request."),
+                Err(e) => return Err(e),
+            }
+
+            self.buf.clear();
+            self.pending = false;
+        }
+
+        Ok(())
+    }
+
+    /// Make a request and wait for the response.
+    pub async fn request<T: Serialize, R: DeserializeOwned>(
+        &mut self,
+        delay: &mut impl DelayNs,
+        cmd: T,
+    ) -> Result<R, NoteError> {
+        self.handshake(delay).await?;
+
+        self.buf.clear();
+        self.buf
+            .append_with(|buf| serde_json_core::to_slice(&cmd, buf))
+            .map_err(|e| NoteError::from_ser(e, BUF_SIZE))?;
+
+        // Add new-line, this separator tells the Notecard that the request is done.
+        let sz = self.buf.len();
+        self.buf
+            .push(b'\n')
+            .map_err(|_| NoteError::RequestTooLarge(sz + 1))?;
+
+        self.transact(delay).await
+    }
+
+    /// Make a raw request and wait for the response. The byte slice must end with `\n`.
+    pub async fn request_raw<R: DeserializeOwned>(
+        &mut self,
+        delay: &mut impl DelayNs,
+        cmd: &[u8],
+    ) -> Result<R, NoteError> {
+        self.handshake(delay).await?;
+
+        self.buf.clear();
+        self.buf
+            .extend_from_slice(cmd)
+            .map_err(|_| NoteError::RequestTooLarge(cmd.len()))?;
+
+        self.transact(delay).await
+    }
+
+    /// Send the request in the buffer and wait for the response.
+    async fn transact<R: DeserializeOwned>(
+        &mut self,
+        delay: &mut impl DelayNs,
+    ) -> Result<R, NoteError> {
+        if self.buf.last() != Some(&b'\n') {
+            return Err(NoteError::InvalidRequest);
+        }
+
+        wire!(">", &self.buf);
+
+        let mut name = heapless::String::<32>::new();
+        for c in request_name(&self.buf).chars() {
+            if name.push(c).is_err() {
+                break;
+            }
+        }
+
+        self.partial = true;
+
+        for segment in self.buf.chunks(self.segment_length) {
+            for c in segment.chunks(self.chunk_length) {
+                self.transport.write(c).await?;
+                delay.delay_ms(self.chunk_delay as u32).await;
+            }
+            delay.delay_ms(self.segment_delay as u32).await;
+        }
+
+        self.partial = false;
+        self.pending = true;
+
+        self.read_response(delay)
+            .await
+            .map_err(|e| e.with_req(&name))?;
+        self.pending = false;
+
+        deserialize_response(&self.buf).map_err(|e| e.with_req(&name))
+    }
+
+    /// Wait for a response and read it into the buffer.
+    async fn read_response(&mut self, delay: &mut impl DelayNs) -> Result<(), NoteError> {
+        let mut waited = 0;
+
+        let mut avail = loop {
+            let avail = self.transport.available().await?;
+            if avail > 0 {
+                break avail;
+            }
+
+            if waited >= self.response_timeout {
+                error!("response timed out (>= {}).", self.response_timeout);
+                return Err(NoteError::timeout(""));
+            }
+
+            delay.delay_ms(self.poll_interval as u32).await;
+            waited += self.poll_interval as u32;
+        };
+
+        debug!("response ready: {} bytes..", avail);
+        self.buf.clear();
+
+        // The whole response is read even if it does not fit, so that the next request starts
+        // from a clean transport.
+        let mut needed = 0;
+
+        while avail > 0 {
+            let mut bytes = [0u8; 128];
+            let sz = bytes.len().min(avail);
+
+            let (n, next) = self.transport.read(&mut bytes[..sz]).await?;
+            let chunk = crate::read_chunk(&bytes[..sz], n)?;
+            needed += n;
+            if needed <= self.buf.capacity() {
+                self.buf.extend_from_slice(chunk).ok();
+            }
+
+            avail = next;
+        }
+
+        if needed > self.buf.capacity() {
+            error!("response does not fit in buffer ({} bytes).", needed);
+            self.buf.clear();
+            return Err(NoteError::ResponseTooLarge {
+                req: heapless::String::new(),
+                needed,
+                capacity: self.buf.capacity(),
+            });
+        }
+
+        wire!("<", &self.buf);
+
+        Ok(())
+    }
+}
+
+/// A response of the blocking [`Notecard`](crate::Notecard) driver can be awaited, e.g. in an
+/// Embassy task. The executor is woken again immediately while the response is not ready, and
+/// there is no timeout. Use [`FutureResponse::wait_async`] to poll at an interval, or
+/// [`FutureResponse::wait_on`] to poll when e.g. the ATTN pin changes.
+///
+/// ```ignore
+/// let time = note.card().time(&mut delay)?.await?;
+/// ```
+impl<T: DeserializeOwned, IOM: NotecardTransport, const BS: usize> Future
+    for FutureResponse<'_, T, IOM, BS>
+{
+    type Output = Result<T, NoteError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match FutureResponse::poll(self.get_mut()) {
+            Ok(r) => Poll::Ready(Ok(r)),
+            Err(nb::Error::Other(e)) => Poll::Ready(Err(e)),
+            Err(nb::Error::WouldBlock) => {
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl<T: DeserializeOwned, IOM: NotecardTransport, const BS: usize> FutureResponse<'_, T, IOM, BS> {
+    /// Wait for the response, yielding to the executor with `delay` between polls.
+    pub async fn wait_async(mut self, delay: &mut impl DelayNs) -> Result<T, NoteError> {
+        let mut waited = 0;
+
+        while waited < self.note.response_timeout {
+            match FutureResponse::poll(&mut self) {
+                Ok(r) => return Ok(r),
+                Err(nb::Error::WouldBlock) => (),
+                Err(nb::Error::Other(e)) => return Err(e),
+            }
+
+            delay.delay_ms(self.note.poll_interval as u32).await;
+            waited += self.note.poll_interval as u32;
+        }
+
+        self.note.stats.timeouts = self.note.stats.timeouts.wrapping_add(1);
+
+        error!("response timed out (>= {}).", self.note.response_timeout);
+        Err(NoteError::timeout(&self.note.name))
+    }
+
+    /// Wait for the response, polling every time the future returned by `tick` completes. E.g. a
+    /// timer, or an edge on the ATTN pin. There is no timeout, use e.g. `select` with a timer.
+    ///
+    /// ```ignore
+    /// let status = note
+    ///     .hub()
+    ///     .sync_status(&mut delay)?
+    ///     .wait_on(|| attn.wait_for_rising_edge())
+    ///     .await?;
+    /// ```
+    pub async fn wait_on<F: Future>(mut self, mut tick: impl FnMut() -> F) -> Result<T, NoteError> {
+        loop {
+            match FutureResponse::poll(&mut self) {
+                Ok(r) => return Ok(r),
+                Err(nb::Error::WouldBlock) => (),
+                Err(nb::Error::Other(e)) => return Err(e),
+            }
+
+            tick().await;
+        }
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use super::*;
+    use crate::mock::{MockTransport, NoDelay};
+    use crate::{Notecard, NotecardTransport};
+
+    /// Run a future to completion, polling it until it is ready.
+    fn block_on<F: Future>(f: F) -> F::Output {
+        let mut cx = Context::from_waker(core::task::Waker::noop());
+        let mut f = core::pin::pin!(f);
+
+        loop {
+            if let Poll::Ready(r) = f.as_mut().poll(&mut cx) {
+                return r;
+            }
+        }
+    }
+
+    /// A delay that yields to the executor once.
+    struct Yield;
+
+    impl DelayNs for Yield {
+        async fn delay_ns(&mut self, _ns: u32) {
+            let mut yielded = false;
+            core::future::poll_fn(|_| {
+                if core::mem::replace(&mut yielded, true) {
+                    Poll::Ready(())
+                } else {
+                    Poll::Pending
+                }
+            })
+            .await
+        }
+    }
+
+    /// Holds back the response for a number of queries for available bytes.
+    struct Slow(MockTransport, usize);
+
+    impl AsyncNotecardTransport for Slow {
+        async fn ping(&mut self) -> bool {
+            AsyncNotecardTransport::ping(&mut self.0).await
+        }
+
+        async fn write(&mut self, data: &[u8]) -> Result<(), NoteError> {
+            AsyncNotecardTransport::write(&mut self.0, data).await
+        }
+
+        async fn available(&mut self) -> Result<usize, NoteError> {
+            if self.1 > 0 {
+                self.1 -= 1;
+                return Ok(0);
+            }
+
+            AsyncNotecardTransport::available(&mut self.0).await
+        }
+
+        async fn read(&mut self, buf: &mut [u8]) -> Result<(usize, usize), NoteError> {
+            AsyncNotecardTransport::read(&mut self.0, buf).await
+        }
+    }
+
+    #[test]
+    fn request_response() {
+        let mut note: AsyncNotecard<_, 1024> = block_on(
+            MockTransport::new()
+                .expect_req("hub.sync", "{}")
+                .expect_req("hub.sync.status", r#"{"status":"idle"}"#)
+                .async_notecard(),
+        );
+
+        let _: crate::hub::res::Empty =
+            block_on(note.request(&mut NoDelay, crate::hub::req::HubSync::new())).unwrap();
+
+        let s: crate::hub::res::SyncStatus =
+            block_on(note.request_raw(&mut NoDelay, b"{\"req\":\"hub.sync.status\"}\n")).unwrap();
+        assert_eq!(s.status.as_deref(), Some("idle"));
+
+        note.free().done();
+    }
+
+    #[test]
+    fn request_timeout() {
+        // The Notecard does not respond to commands.
+        let mut note: AsyncNotecard<_, 1024> = block_on(
+            MockTransport::new()
+                .expect(r#"{"cmd":"card.time"}"#, "")
+                .async_notecard(),
+        );
+        note.set_response_timeout(100);
+
+        let r: Result<crate::card::res::Time, _> =
+            block_on(note.request_raw(&mut NoDelay, b"{\"cmd\":\"card.time\"}\n"));
+        assert!(matches!(r, Err(NoteError::TimeOut { .. })));
+
+        note.free().done();
+    }
+
+    const TIME: &[u8] = b"{\"req\":\"card.time\"}\n";
+
+    #[test]
+    fn drain_dropped_request() {
+        let mock = MockTransport::new()
+            .expect_req("card.time", r#"{"time":1599769214}"#)
+            .expect_req("card.time", r#"{"time":1599769215}"#);
+
+        // The response to the dropped request arrives after the next request is made.
+        let mut note: AsyncNotecard<_, 1024> =
+            AsyncNotecard::new_with_transport(Slow(mock, 0), crate::tests::config());
+        block_on(note.initialize(&mut NoDelay)).unwrap();
+
+        {
+            let mut cx = Context::from_waker(core::task::Waker::noop());
+            let mut d = Yield;
+            let f = note.request_raw::<crate::card::res::Time>(&mut d, TIME);
+            let mut f = core::pin::pin!(f);
+
+            // The request is written, and the driver yields after the first chunk.
+            assert!(f.as_mut().poll(&mut cx).is_pending());
+        }
+        note.transport.1 = 3;
+
+        let t: crate::card::res::Time = block_on(note.request_raw(&mut Yield, TIME)).unwrap();
+        assert_eq!(t.time, Some(1599769215));
+
+        note.free().0.done();
+    }
+
+    /// Fails the first write.
+    struct FailWrite(MockTransport, bool);
+
+    impl AsyncNotecardTransport for FailWrite {
+        async fn ping(&mut self) -> bool {
+            AsyncNotecardTransport::ping(&mut self.0).await
+        }
+
+        async fn write(&mut self, data: &[u8]) -> Result<(), NoteError> {
+            if core::mem::replace(&mut self.1, false) {
+                return Err(NoteError::I2cWriteError);
+            }
+
+            AsyncNotecardTransport::write(&mut self.0, data).await
+        }
+
+        async fn available(&mut self) -> Result<usize, NoteError> {
+            AsyncNotecardTransport::available(&mut self.0).await
+        }
+
+        async fn read(&mut self, buf: &mut [u8]) -> Result<(usize, usize), NoteError> {
+            AsyncNotecardTransport::read(&mut self.0, buf).await
+        }
+    }
+
+    #[test]
+    fn failed_write() {
+        let mock = MockTransport::new().expect_req("card.time", r#"{"time":1599769214}"#);
+
+        let mut note: AsyncNotecard<_, 1024> =
+            AsyncNotecard::new_with_transport(FailWrite(mock, true), crate::tests::config());
+        note.set_response_timeout(100);
+        block_on(note.initialize(&mut NoDelay)).unwrap();
+
+        let r: Result<crate::card::res::Time, _> = block_on(note.request_raw(&mut NoDelay, TIME));
+        assert!(matches!(r, Err(NoteError::I2cWriteError)));
+
+        // The partial request is terminated, and there is no response to it.
+        let t: crate::card::res::Time = block_on(note.request_raw(&mut NoDelay, TIME)).unwrap();
+        assert_eq!(t.time, Some(1599769214));
+
+        note.free().0.done();
+    }
+
+    #[test]
+    fn await_response() {
+        let mut note: Notecard<_, 1024> = MockTransport::new()
+            .expect_req("card.time", r#"{"time":1599769214}"#)
+            .expect_req("card.time", r#"{"time":1599769215}"#)
+            .expect_req("card.time", r#"{"time":1599769216}"#)
+            .notecard();
+
+        let t = block_on(note.card().time(&mut NoDelay).unwrap()).unwrap();
+        assert_eq!(t.time, Some(1599769214));
+
+        let t = block_on(
+            note.card()
+                .time(&mut NoDelay)
+                .unwrap()
+                .wait_async(&mut NoDelay),
+        );
+        assert_eq!(t.unwrap().time, Some(1599769215));
+
+        let t = block_on(note.card().time(&mut NoDelay).unwrap().wait_on(|| async {}));
+        assert_eq!(t.unwrap().time, Some(1599769216));
+    }
+
+    /// Never has a response ready.
+    struct Silent(MockTransport);
+
+    impl NotecardTransport for Silent {
+        fn ping(&mut self) -> bool {
+            true
+        }
+
+        fn write(&mut self, data: &[u8]) -> Result<(), NoteError> {
+            NotecardTransport::write(&mut self.0, data)
+        }
+
+        fn available(&mut self) -> Result<usize, NoteError> {
+            Ok(0)
+        }
+
+        fn read(&mut self, _buf: &mut [u8]) -> Result<(usize, usize), NoteError> {
+            Ok((0, 0))
+        }
+    }
+
+    #[test]
+    fn wait_async_timeout() {
+        let mock = MockTransport::new().expect_req("card.time", r#"{"time":1599769214}"#);
+        let mut note: Notecard<_, 1024> =
+            Notecard::new_with_transport(Silent(mock), crate::tests::config())
+                .initialize(&mut NoDelay)
+                .unwrap();
+        note.set_response_timeout(100);
+
+        let r = block_on(
+            note.card()
+                .time(&mut NoDelay)
+                .unwrap()
+                .wait_async(&mut NoDelay),
+        );
+        assert!(matches!(r, Err(NoteError::TimeOut { .. })));
+        assert_eq!(note.stats().timeouts, 1);
+    }
+}
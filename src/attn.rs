@@ -0,0 +1,212 @@
+//! Helper for waiting on the ATTN pin of the Notecard using a host GPIO.
+//!
+//! <https://dev.blues.io/guides-and-tutorials/notecard-guides/attention-pin-guide/>
+
+#[allow(unused_imports)]
+use crate::fmt::{debug, error, info, trace, warn};
+use embedded_hal::blocking::delay::DelayMs;
+use embedded_hal::digital::v2::InputPin;
+
+use super::card::res;
+use super::{NoteError, Notecard, NotecardTransport};
+
+/// Delay between checking the ATTN pin (ms).
+const ATTN_POLL_DELAY: u16 = 50;
+
+/// Monitors the ATTN pin of the Notecard. The pin is held low when armed, and goes high once one of
+/// the armed events fire.
+pub struct AttnMonitor<P: InputPin> {
+    pin: P,
+}
+
+impl<P: InputPin> AttnMonitor<P> {
+    pub fn new(pin: P) -> AttnMonitor<P> {
+        AttnMonitor { pin }
+    }
+
+    /// Release the pin.
+    pub fn free(self) -> P {
+        self.pin
+    }
+
+    /// Arm the ATTN pin. `mode` is an optional comma-separated list of additional modes (e.g.
+    /// `files,motion`), and `files` the notefiles to watch when using the `files` mode.
+    pub fn arm<IOM: NotecardTransport, const BS: usize>(
+        &mut self,
+        note: &mut Notecard<IOM, BS>,
+        delay: &mut impl DelayMs<u16>,
+        mode: Option<&str>,
+        files: Option<&[&str]>,
+    ) -> Result<(), NoteError> {
+        let mut m = heapless::String::<64>::from("arm");
+        if let Some(mode) = mode {
+            m.push(',')
+                .and_then(|_| m.push_str(mode))
+                .map_err(|_| NoteError::ArgumentTooLong {
+                    len: "arm,".len() + mode.len(),
+                    capacity: m.capacity(),
+                })?;
+        }
+
+        note.card()
+            .attn(delay, Some(&m), files, None, None, false)?
+            .wait(delay)?;
+
+        Ok(())
+    }
+
+    /// Disarm the ATTN pin.
+    pub fn disarm<IOM: NotecardTransport, const BS: usize>(
+        &mut self,
+        note: &mut Notecard<IOM, BS>,
+        delay: &mut impl DelayMs<u16>,
+    ) -> Result<(), NoteError> {
+        note.card()
+            .attn(delay, Some("disarm"), None, None, None, false)?
+            .wait(delay)?;
+
+        Ok(())
+    }
+
+    /// Check whether the ATTN pin has fired.
+    pub fn is_fired(&self) -> Result<bool, NoteError> {
+        self.pin.is_high().map_err(|_| NoteError::PinError)
+    }
+
+    /// Check the ATTN pin once, and if it has fired fetch the reason from the Notecard.
+    pub fn poll<IOM: NotecardTransport, const BS: usize>(
+        &mut self,
+        note: &mut Notecard<IOM, BS>,
+        delay: &mut impl DelayMs<u16>,
+    ) -> Result<Option<res::Attn>, NoteError> {
+        if self.is_fired()? {
+            debug!("attn: pin fired, querying reason.");
+            note.card()
+                .attn(delay, None, None, None, None, false)?
+                .wait(delay)
+                .map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Block until the ATTN pin fires and return the reason. Times out after `timeout` ms if
+    /// specified, otherwise waits forever.
+    pub fn wait_for_event<IOM: NotecardTransport, const BS: usize>(
+        &mut self,
+        note: &mut Notecard<IOM, BS>,
+        delay: &mut impl DelayMs<u16>,
+        timeout: Option<u32>,
+    ) -> Result<res::Attn, NoteError> {
+        let mut waited = 0;
+
+        while timeout.is_none_or(|t| waited < t) {
+            if let Some(r) = self.poll(note, delay)? {
+                return Ok(r);
+            }
+
+            delay.delay_ms(ATTN_POLL_DELAY);
+            waited = waited.saturating_add(ATTN_POLL_DELAY as u32);
+        }
+
+        error!("attn: timed out waiting for ATTN pin (>= {}).", waited);
+        Err(NoteError::timeout("card.attn"))
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use super::*;
+    use crate::mock::{MockTransport, NoDelay};
+    use core::cell::Cell;
+    use core::convert::Infallible;
+
+    /// An ATTN pin that fires after it has been checked a number of times.
+    struct Pin {
+        checks: Cell<u32>,
+        fires_after: Option<u32>,
+    }
+
+    impl Pin {
+        fn new(fires_after: Option<u32>) -> Pin {
+            Pin {
+                checks: Cell::new(0),
+                fires_after,
+            }
+        }
+    }
+
+    impl InputPin for Pin {
+        type Error = Infallible;
+
+        fn is_high(&self) -> Result<bool, Infallible> {
+            self.checks.set(self.checks.get() + 1);
+            Ok(self.fires_after.is_some_and(|n| self.checks.get() > n))
+        }
+
+        fn is_low(&self) -> Result<bool, Infallible> {
+            self.is_high().map(|h| !h)
+        }
+    }
+
+    #[test]
+    fn arm_and_disarm() {
+        let mut note: Notecard<_, 1024> = MockTransport::new()
+            .expect(
+                r#"{"req":"card.attn","mode":"arm,files","files":["data.qi"]}"#,
+                "{}",
+            )
+            .expect(r#"{"req":"card.attn","mode":"disarm"}"#, "{}")
+            .notecard();
+        let mut attn = AttnMonitor::new(Pin::new(None));
+
+        attn.arm(&mut note, &mut NoDelay, Some("files"), Some(&["data.qi"]))
+            .unwrap();
+        attn.disarm(&mut note, &mut NoDelay).unwrap();
+
+        let long = "x".repeat(64);
+        assert!(matches!(
+            attn.arm(&mut note, &mut NoDelay, Some(&long), None),
+            Err(NoteError::ArgumentTooLong {
+                len: 68,
+                capacity: 64
+            })
+        ));
+
+        note.transport().done();
+    }
+
+    #[test]
+    fn wait_for_event() {
+        let mut note: Notecard<_, 1024> = MockTransport::new()
+            .expect(
+                r#"{"req":"card.attn"}"#,
+                r#"{"files":["data.qi","modified"],"set":true}"#,
+            )
+            .notecard();
+        let mut attn = AttnMonitor::new(Pin::new(Some(3)));
+
+        assert!(attn.poll(&mut note, &mut NoDelay).unwrap().is_none());
+
+        let r = attn.wait_for_event(&mut note, &mut NoDelay, None).unwrap();
+        assert_eq!(r.set, Some(true));
+        assert_eq!(r.files.unwrap()[0], "data.qi");
+        assert_eq!(attn.free().checks.get(), 4);
+
+        note.transport().done();
+    }
+
+    #[test]
+    fn wait_for_event_timeout() {
+        let mut note: Notecard<_, 1024> = MockTransport::new().notecard();
+        let mut attn = AttnMonitor::new(Pin::new(None));
+
+        match attn.wait_for_event(&mut note, &mut NoDelay, Some(120)) {
+            Err(NoteError::TimeOut { req }) => assert_eq!(req, "card.attn"),
+            r => panic!("unexpected: {:?}", r),
+        }
+        assert_eq!(attn.free().checks.get(), 3);
+
+        note.transport().done();
+    }
+}
@@ -0,0 +1,364 @@
+//! Transfer of large binary data through the binary storage area of the Notecard
+//! (`card.binary.put`), which can then be sent with `note.add`.
+//!
+//! ```ignore
+//! let mut buf = [0u8; 1024];
+//! let mut transfer = BinaryTransfer::new(&mut buf).retries(3);
+//!
+//! transfer.note_add(&mut note, &mut delay, &mut &capture[..], Some("capture.qo"), true, |sent, total| {
+//!     info!("binary: {} / {}", sent, total);
+//! })?;
+//! ```
+
+#[allow(unused_imports)]
+use crate::fmt::{debug, error, info, trace, warn};
+use embedded_hal::blocking::delay::DelayMs;
+use md5::{Digest, Md5};
+
+use super::card::{digest_hex, res};
+use super::{NoteError, Notecard, NotecardTransport};
+
+/// Source of the binary data to transfer.
+pub trait BinarySource {
+    /// Total size of the data.
+    fn size(&self) -> usize;
+
+    /// Read data starting at `offset` into `buf`, returning the number of bytes read.
+    fn read(&mut self, offset: usize, buf: &mut [u8]) -> Result<usize, NoteError>;
+}
+
+impl BinarySource for &[u8] {
+    fn size(&self) -> usize {
+        self.len()
+    }
+
+    fn read(&mut self, offset: usize, buf: &mut [u8]) -> Result<usize, NoteError> {
+        let data = self.get(offset..).ok_or(NoteError::BufOverflow)?;
+        let sz = data.len().min(buf.len());
+        buf[..sz].copy_from_slice(&data[..sz]);
+        Ok(sz)
+    }
+}
+
+/// Chunks binary data into the binary storage area of the Notecard, verifying the MD5 of the
+/// stored data when done.
+pub struct BinaryTransfer<'b> {
+    buf: &'b mut [u8],
+    retries: u8,
+}
+
+impl<'b> BinaryTransfer<'b> {
+    /// Create a new transfer using `buf` for staging chunks. The chunk size is the size of the
+    /// buffer, and the COBS encoded chunk must fit in the buffer of the [`Notecard`]. The transfer
+    /// fails with [`NoteError::InvalidArgument`] if `buf` is empty.
+    pub fn new(buf: &'b mut [u8]) -> BinaryTransfer<'b> {
+        BinaryTransfer { buf, retries: 0 }
+    }
+
+    /// Number of times to retry a chunk that the Notecard rejected.
+    pub fn retries(self, retries: u8) -> BinaryTransfer<'b> {
+        BinaryTransfer { retries, ..self }
+    }
+
+    /// Clear the binary storage area and store the data from `source` in it. `progress` is called
+    /// with the number of bytes stored so far and the total size after each chunk. An empty
+    /// `source` leaves the storage area empty, and the status of the cleared area is returned.
+    pub fn store<IOM: NotecardTransport, const BS: usize>(
+        &mut self,
+        note: &mut Notecard<IOM, BS>,
+        delay: &mut impl DelayMs<u16>,
+        source: &mut impl BinarySource,
+        mut progress: impl FnMut(usize, usize),
+    ) -> Result<res::Binary, NoteError> {
+        if self.buf.is_empty() {
+            return Err(NoteError::InvalidArgument);
+        }
+
+        let total = source.size();
+        let mut md5 = Md5::new();
+        let mut offset = 0;
+
+        let cleared = note.card().binary(delay, true)?.wait(delay)?;
+        if cleared.max.is_some_and(|max| total > max as usize) {
+            error!("binary: {} bytes does not fit in storage area (max: {:?})", total, cleared.max);
+            return Err(NoteError::BufOverflow);
+        }
+
+        if total == 0 {
+            return Ok(cleared);
+        }
+
+        while offset < total {
+            let sz = source.read(offset, self.buf)?;
+            if sz == 0 {
+                error!("binary: source ended after {} of {} bytes.", offset, total);
+                return Err(NoteError::SourceEnded {
+                    read: offset,
+                    size: total,
+                });
+            }
+            let chunk = &self.buf[..sz];
+
+            let mut tries = 0;
+            loop {
+                match note
+                    .card()
+                    .binary_put(delay, chunk, Some(offset as u32))
+                    .and_then(|r| r.wait(delay))
+                {
+                    Ok(_) => break,
+                    Err(e) if tries < self.retries => {
+                        warn!("binary: chunk at {} failed: {:?}, retrying..", offset, e);
+                        tries += 1;
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+
+            md5.update(chunk);
+            offset += sz;
+            progress(offset, total);
+        }
+
+        let status = note.card().binary(delay, false)?.wait(delay)?;
+
+        let expected = digest_hex(&md5.finalize());
+
+        if status.length != Some(total as u32) || status.status.as_ref() != Some(&expected) {
+            error!("binary: stored data does not match ({:?} bytes).", status.length);
+            return Err(NoteError::InvalidPayload);
+        }
+
+        Ok(status)
+    }
+
+    /// Store the data from `source` (see [`BinaryTransfer::store`]) and add it as the payload of
+    /// a note in `file`.
+    pub fn note_add<IOM: NotecardTransport, const BS: usize>(
+        &mut self,
+        note: &mut Notecard<IOM, BS>,
+        delay: &mut impl DelayMs<u16>,
+        source: &mut impl BinarySource,
+        file: Option<&str>,
+        sync: bool,
+        progress: impl FnMut(usize, usize),
+    ) -> Result<crate::note::res::Add, NoteError> {
+        self.store(note, delay, source, progress)?;
+        note.note().add_binary(delay, file, sync)?.wait(delay)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slice_source() {
+        let data = [1u8, 2, 3, 4, 5];
+        let mut src = &data[..];
+        let mut buf = [0u8; 2];
+
+        assert_eq!(src.size(), 5);
+        assert_eq!(src.read(0, &mut buf).unwrap(), 2);
+        assert_eq!(buf, [1, 2]);
+        assert_eq!(src.read(4, &mut buf).unwrap(), 1);
+        assert_eq!(buf[0], 5);
+        assert_eq!(src.read(5, &mut buf).unwrap(), 0);
+    }
+
+    #[test]
+    fn md5_matches_card() {
+        let mut md5 = Md5::new();
+        md5.update(b"hello ");
+        md5.update(b"notecard");
+
+        assert_eq!(
+            digest_hex(&md5.finalize()),
+            crate::card::md5_hex(b"hello notecard")
+        );
+    }
+
+    #[cfg(feature = "mock")]
+    mod transfer {
+        use super::*;
+        use crate::mock::{MockTransport, NoDelay};
+        use std::vec::Vec;
+
+        const DATA: &[u8] = b"0123456789";
+
+        fn stored(data: &[u8]) -> std::string::String {
+            format!(
+                r#"{{"length":{},"status":"{}","max":1000}}"#,
+                data.len(),
+                crate::card::md5_hex(data)
+            )
+        }
+
+        #[test]
+        fn store() {
+            let mut note: Notecard<_, 1024> = MockTransport::new()
+                .expect(r#"{"req":"card.binary","delete":true}"#, r#"{"max":1000}"#)
+                .expect_req("card.binary.put", "{}")
+                .expect_req("card.binary.put", r#"{"err":"md5 mismatch {bad-bin}"}"#)
+                .expect_req("card.binary.put", "{}")
+                .expect_req("card.binary.put", "{}")
+                .expect(r#"{"req":"card.binary"}"#, &stored(DATA))
+                .notecard();
+            let mut buf = [0u8; 4];
+            let mut progress = Vec::new();
+
+            let status = BinaryTransfer::new(&mut buf)
+                .retries(1)
+                .store(&mut note, &mut NoDelay, &mut &DATA[..], |s, t| {
+                    progress.push((s, t))
+                })
+                .unwrap();
+
+            assert_eq!(status.length, Some(10));
+            assert_eq!(progress, [(4, 10), (8, 10), (10, 10)]);
+            note.transport().done();
+        }
+
+        #[test]
+        fn store_fails() {
+            let mut buf = [0u8; 4];
+
+            // Does not fit.
+            let mut note: Notecard<_, 1024> = MockTransport::new()
+                .expect_req("card.binary", r#"{"max":8}"#)
+                .notecard();
+            assert!(matches!(
+                BinaryTransfer::new(&mut buf).store(
+                    &mut note,
+                    &mut NoDelay,
+                    &mut &DATA[..],
+                    |_, _| ()
+                ),
+                Err(NoteError::BufOverflow)
+            ));
+            note.transport().done();
+
+            // Rejected chunk without retries.
+            let mut note: Notecard<_, 1024> = MockTransport::new()
+                .expect_req("card.binary", r#"{"max":1000}"#)
+                .expect_req("card.binary.put", r#"{"err":"md5 mismatch {bad-bin}"}"#)
+                .notecard();
+            assert!(matches!(
+                BinaryTransfer::new(&mut buf).store(
+                    &mut note,
+                    &mut NoDelay,
+                    &mut &DATA[..],
+                    |_, _| ()
+                ),
+                Err(NoteError::NotecardErr(_))
+            ));
+            note.transport().done();
+
+            // Stored data does not match.
+            let mut note: Notecard<_, 1024> = MockTransport::new()
+                .expect_req("card.binary", r#"{"max":1000}"#)
+                .expect_req("card.binary.put", "{}")
+                .expect_req("card.binary.put", "{}")
+                .expect_req("card.binary.put", "{}")
+                .expect_req("card.binary", &stored(b"0123456780"))
+                .notecard();
+            assert!(matches!(
+                BinaryTransfer::new(&mut buf).store(
+                    &mut note,
+                    &mut NoDelay,
+                    &mut &DATA[..],
+                    |_, _| ()
+                ),
+                Err(NoteError::InvalidPayload)
+            ));
+            note.transport().done();
+        }
+
+        /// Reports more data than it has.
+        struct Short(&'static [u8]);
+
+        impl BinarySource for Short {
+            fn size(&self) -> usize {
+                self.0.len() + 4
+            }
+
+            fn read(&mut self, offset: usize, buf: &mut [u8]) -> Result<usize, NoteError> {
+                self.0.read(offset, buf)
+            }
+        }
+
+        #[test]
+        fn store_short_source() {
+            let mut note: Notecard<_, 1024> = MockTransport::new()
+                .expect_req("card.binary", r#"{"max":1000}"#)
+                .expect_req("card.binary.put", "{}")
+                .expect_req("card.binary.put", "{}")
+                .expect_req("card.binary.put", "{}")
+                .notecard();
+            let mut buf = [0u8; 4];
+
+            assert!(matches!(
+                BinaryTransfer::new(&mut buf).store(
+                    &mut note,
+                    &mut NoDelay,
+                    &mut Short(DATA),
+                    |_, _| ()
+                ),
+                Err(NoteError::SourceEnded { read: 10, size: 14 })
+            ));
+            note.transport().done();
+        }
+
+        #[test]
+        fn store_empty() {
+            let mut note: Notecard<_, 1024> = MockTransport::new()
+                .expect_req("card.binary", r#"{"max":1000}"#)
+                .notecard();
+
+            // Nothing is sent with an empty buffer.
+            assert!(matches!(
+                BinaryTransfer::new(&mut []).store(
+                    &mut note,
+                    &mut NoDelay,
+                    &mut &DATA[..],
+                    |_, _| ()
+                ),
+                Err(NoteError::InvalidArgument)
+            ));
+
+            let mut buf = [0u8; 4];
+            let status = BinaryTransfer::new(&mut buf)
+                .store(&mut note, &mut NoDelay, &mut &[][..], |_, _| ())
+                .unwrap();
+            assert_eq!(status.max, Some(1000));
+            assert_eq!(status.length, None);
+            note.transport().done();
+        }
+
+        #[test]
+        fn note_add() {
+            let mut note: Notecard<_, 1024> = MockTransport::new()
+                .expect_req("card.binary", r#"{"max":1000}"#)
+                .expect_req("card.binary.put", "{}")
+                .expect_req("card.binary", &stored(DATA))
+                .expect(
+                    r#"{"req":"note.add","file":"capture.qo","sync":true,"binary":true}"#,
+                    r#"{"total":1}"#,
+                )
+                .notecard();
+            let mut buf = [0u8; 16];
+
+            BinaryTransfer::new(&mut buf)
+                .note_add(
+                    &mut note,
+                    &mut NoDelay,
+                    &mut &DATA[..],
+                    Some("capture.qo"),
+                    true,
+                    |_, _| (),
+                )
+                .unwrap();
+            note.transport().done();
+        }
+    }
+}
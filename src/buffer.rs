@@ -0,0 +1,161 @@
+//! The request and response buffer of the driver.
+
+use core::ops::{Deref, DerefMut};
+
+enum Storage<const N: usize> {
+    /// Part of the driver.
+    Owned([u8; N]),
+
+    /// Provided by the caller, see [`Notecard::new_with_buffer`](crate::Notecard::new_with_buffer).
+    Borrowed(&'static mut [u8]),
+}
+
+/// A vector of bytes stored either in the driver or in a buffer provided by the caller. Has the
+/// same methods as [`heapless::Vec`] where they are used by the driver.
+///
+/// The storage is initialized once, so that the bytes after the content can be written in place
+/// with [`Buffer::append_with`].
+pub(crate) struct Buffer<const N: usize> {
+    data: Storage<N>,
+    len: usize,
+}
+
+impl<const N: usize> Buffer<N> {
+    pub fn new() -> Buffer<N> {
+        Buffer {
+            data: Storage::Owned([0; N]),
+            len: 0,
+        }
+    }
+
+    pub fn borrowed(data: &'static mut [u8]) -> Buffer<N> {
+        Buffer {
+            data: Storage::Borrowed(data),
+            len: 0,
+        }
+    }
+
+    pub fn from_slice(s: &[u8]) -> Result<Buffer<N>, ()> {
+        let mut buf = Buffer::new();
+        buf.extend_from_slice(s)?;
+        Ok(buf)
+    }
+
+    fn storage(&self) -> &[u8] {
+        match &self.data {
+            Storage::Owned(data) => data,
+            Storage::Borrowed(data) => data,
+        }
+    }
+
+    fn storage_mut(&mut self) -> &mut [u8] {
+        match &mut self.data {
+            Storage::Owned(data) => data,
+            Storage::Borrowed(data) => data,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.storage().len()
+    }
+
+    pub fn clear(&mut self) {
+        self.truncate(0)
+    }
+
+    pub fn truncate(&mut self, new_len: usize) {
+        self.len = new_len.min(self.len);
+    }
+
+    pub fn push(&mut self, b: u8) -> Result<(), u8> {
+        self.extend_from_slice(&[b]).map_err(|_| b)
+    }
+
+    pub fn extend_from_slice(&mut self, s: &[u8]) -> Result<(), ()> {
+        self.append_with(|spare| {
+            spare.get_mut(..s.len()).ok_or(())?.copy_from_slice(s);
+            Ok(s.len())
+        })
+    }
+
+    /// Let `f` write directly into the free space after the content, and append the number of
+    /// bytes it returns. Nothing is appended if `f` fails.
+    pub fn append_with<E>(
+        &mut self,
+        f: impl FnOnce(&mut [u8]) -> Result<usize, E>,
+    ) -> Result<(), E> {
+        let len = self.len;
+        let spare = &mut self.storage_mut()[len..];
+        let n = f(spare)?.min(spare.len());
+        self.len += n;
+
+        Ok(())
+    }
+}
+
+impl<const N: usize> Deref for Buffer<N> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.storage()[..self.len]
+    }
+}
+
+impl<const N: usize> DerefMut for Buffer<N> {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        let len = self.len;
+        &mut self.storage_mut()[..len]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn borrowed() {
+        let data = std::boxed::Box::leak(std::vec![0xffu8; 8].into_boxed_slice());
+        let mut buf = Buffer::<0>::borrowed(data);
+
+        assert_eq!(buf.capacity(), 8);
+        assert!(buf.is_empty());
+
+        buf.extend_from_slice(b"abc").unwrap();
+        buf.push(b'd').unwrap();
+        assert_eq!(&buf[..], b"abcd");
+
+        buf.extend_from_slice(&[0, 0]).unwrap();
+        assert_eq!(&buf[..], b"abcd\0\0");
+        assert!(buf.extend_from_slice(b"xyz").is_err());
+        assert_eq!(buf.len(), 6);
+
+        buf.truncate(2);
+        assert_eq!(&buf[..], b"ab");
+
+        buf.clear();
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn append_with() {
+        let mut buf = Buffer::<8>::new();
+        buf.extend_from_slice(b"ab").unwrap();
+
+        buf.append_with(|spare| {
+            assert_eq!(spare.len(), 6);
+            spare[..3].copy_from_slice(b"cde");
+            Ok::<_, ()>(3)
+        })
+        .unwrap();
+        assert_eq!(&buf[..], b"abcde");
+
+        // Nothing is appended on failure, even if `f` wrote to the free space.
+        assert!(buf
+            .append_with(|spare| {
+                spare[0] = b'x';
+                Err(())
+            })
+            .is_err());
+        assert_eq!(&buf[..], b"abcde");
+    }
+}
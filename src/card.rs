@@ -1,18 +1,24 @@
 //! https://dev.blues.io/reference/notecard-api/card-requests/
 
 #[allow(unused_imports)]
-use defmt::{debug, error, info, trace, warn};
-use embedded_hal::blocking::i2c::{Read, SevenBitAddress, Write};
+use crate::fmt::{debug, error, info, trace, warn};
 use embedded_hal::blocking::delay::DelayMs;
 use serde::{Deserialize, Serialize};
 
-use super::{FutureResponse, NoteError, Notecard};
+use super::notefile::{self, NotefileName};
+use super::{cobs, payload, FutureResponse, NoteError, Notecard, NotecardTransport};
 
-pub struct Card<'a, IOM: Write<SevenBitAddress> + Read<SevenBitAddress>, const BS: usize> {
+/// Maximum size of the host payload that can be stored with [`Card::attn_sleep`].
+pub const ATTN_PAYLOAD_MAX: usize = 512;
+
+/// Maximum number of random bytes that can be requested with [`Card::random`].
+pub const RANDOM_PAYLOAD_MAX: usize = 256;
+
+pub struct Card<'a, IOM: NotecardTransport, const BS: usize> {
     note: &'a mut Notecard<IOM, BS>,
 }
 
-impl<'a, IOM: Write<SevenBitAddress> + Read<SevenBitAddress>, const BS: usize> Card<'a, IOM, BS> {
+impl<'a, IOM: NotecardTransport, const BS: usize> Card<'a, IOM, BS> {
     pub fn from(note: &mut Notecard<IOM, BS>) -> Card<'_, IOM, BS> {
         Card { note }
     }
@@ -43,33 +49,62 @@ impl<'a, IOM: Write<SevenBitAddress> + Read<SevenBitAddress>, const BS: usize> C
         Ok(FutureResponse::from(self.note))
     }
 
+    /// Sets location-related configuration settings with a [`LocationModeBuilder`], see
+    /// [`Card::location_mode`].
+    ///
+    /// ```ignore
+    /// note.card()
+    ///     .location_mode_builder()
+    ///     .mode(LocationMode::Periodic)
+    ///     .seconds(3600)
+    ///     .send(&mut delay)?
+    ///     .wait(&mut delay)?;
+    /// ```
+    pub fn location_mode_builder(self) -> LocationModeBuilder<'a, IOM, BS> {
+        LocationModeBuilder {
+            note: self.note,
+            req: req::LocationMode::new(),
+        }
+    }
+
     /// Sets location-related configuration settings. Retrieves the current location mode when passed with no argument.
+    #[allow(clippy::too_many_arguments)]
     pub fn location_mode(
         self,
         delay: &mut impl DelayMs<u16>,
-        mode: Option<&str>,
+        mode: Option<LocationMode>,
         seconds: Option<u32>,
         vseconds: Option<&str>,
         delete: Option<bool>,
         max: Option<u32>,
-        lat: Option<f32>,
-        lon: Option<f32>,
+        lat: Option<f64>,
+        lon: Option<f64>,
         minutes: Option<u32>,
     ) -> Result<FutureResponse<'a, res::LocationMode, IOM, BS>, NoteError> {
+        if mode == Some(LocationMode::Unknown) {
+            return Err(NoteError::InvalidArgument);
+        }
+
         self.note.request(delay, req::LocationMode {
-            req: "card.location.mode",
-            mode: mode.map(heapless::String::from),
+            mode,
             seconds,
-            vseconds: vseconds.map(heapless::String::from),
+            vseconds: vseconds.map(crate::to_string).transpose()?,
             delete,
             max,
             lat,
             lon,
             minutes,
+            ..req::LocationMode::new()
         })?;
         Ok(FutureResponse::from(self.note))
     }
 
+    /// Store location data in a Notefile at the `periodic` interval, or using `heartbeat`. When
+    /// `start` is false tracking is stopped and the remaining arguments are ignored. `sync`
+    /// syncs to Notehub every time a new entry is added. The tracking notefile `file` must be an
+    /// outbound queue (`.qo` or `.qos`), and `payload` is a base64 encoded payload added to every
+    /// entry.
+    #[allow(clippy::too_many_arguments)]
     pub fn location_track(
         self,
         delay: &mut impl DelayMs<u16>,
@@ -78,16 +113,38 @@ impl<'a, IOM: Write<SevenBitAddress> + Read<SevenBitAddress>, const BS: usize> C
         sync: bool,
         hours: Option<i32>,
         file: Option<&str>,
+        payload: Option<&str>,
     ) -> Result<FutureResponse<'a, res::LocationTrack, IOM, BS>, NoteError> {
-        self.note.request(delay, req::LocationTrack {
-            req: "card.location.track",
-            start: start.then(|| true),
-            stop: (!start).then(|| true),
-            heartbeat: heartbeat.then(|| true),
-            sync: sync.then(|| true),
-            hours,
-            file: file.map(heapless::String::from),
-        })?;
+        let file = file
+            .map(|f| {
+                if !NotefileName::new(f)?.kind().is_outbound() {
+                    error!("location.track: {} is not an outbound queue.", f);
+                    return Err(NoteError::InvalidArgument);
+                }
+
+                crate::to_string(f)
+            })
+            .transpose()?;
+
+        self.note.request(
+            delay,
+            if start {
+                req::LocationTrack {
+                    start: Some(true),
+                    heartbeat: heartbeat.then_some(true),
+                    sync: sync.then_some(true),
+                    hours,
+                    file,
+                    payload,
+                    ..req::LocationTrack::new()
+                }
+            } else {
+                req::LocationTrack {
+                    stop: Some(true),
+                    ..req::LocationTrack::new()
+                }
+            },
+        )?;
 
         Ok(FutureResponse::from(self.note))
     }
@@ -97,19 +154,640 @@ impl<'a, IOM: Write<SevenBitAddress> + Read<SevenBitAddress>, const BS: usize> C
         Ok(FutureResponse::from(self.note))
     }
 
+    /// Configure hardware notification from the Notecard to the host via the ATTN pin. `mode` is a
+    /// comma-separated list of modes, e.g. `arm,files`.
+    pub fn attn(
+        self,
+        delay: &mut impl DelayMs<u16>,
+        mode: Option<&str>,
+        files: Option<&[&str]>,
+        seconds: Option<u32>,
+        payload: Option<&str>,
+        start: bool,
+    ) -> Result<FutureResponse<'a, res::Attn, IOM, BS>, NoteError> {
+        for f in files.unwrap_or_default() {
+            NotefileName::new(f)?;
+        }
+
+        self.note.request(
+            delay,
+            req::Attn {
+                mode,
+                files,
+                seconds,
+                payload,
+                start: start.then_some(true),
+                ..req::Attn::new()
+            },
+        )?;
+        Ok(FutureResponse::from(self.note))
+    }
+
+    /// Put the host to sleep by pulling the ATTN pin low for `seconds`. The `payload` is stored on
+    /// the Notecard while the host is powered off, and can be retrieved with [`Card::attn_start`]
+    /// after waking up. The payload may not be larger than [`ATTN_PAYLOAD_MAX`].
+    pub fn attn_sleep(
+        self,
+        delay: &mut impl DelayMs<u16>,
+        seconds: u32,
+        payload: Option<&[u8]>,
+    ) -> Result<FutureResponse<'a, res::Attn, IOM, BS>, NoteError> {
+        if payload.map_or(0, <[u8]>::len) > ATTN_PAYLOAD_MAX {
+            return Err(NoteError::BufOverflow);
+        }
+
+        let mut b64 = [0u8; payload::encoded_len(ATTN_PAYLOAD_MAX)];
+        let payload = payload
+            .map(|p| payload::encode(p, &mut b64))
+            .transpose()?;
+
+        self.attn(delay, Some("sleep"), None, Some(seconds), payload, false)
+    }
+
+    /// Retrieve the payload stored with [`Card::attn_sleep`] after the host has woken up. Use
+    /// [`res::Attn::decode_payload`] to get the original bytes.
+    pub fn attn_start(self, delay: &mut impl DelayMs<u16>) -> Result<FutureResponse<'a, res::Attn, IOM, BS>, NoteError> {
+        self.note.request_raw(delay, b"{\"req\":\"card.attn\",\"start\":true}\n")?;
+        Ok(FutureResponse::from(self.note))
+    }
+
     /// Returns firmware version information for the Notecard.
     pub fn version(self, delay: &mut impl DelayMs<u16>) -> Result<FutureResponse<'a, res::Version, IOM, BS>, NoteError> {
         self.note.request_raw(delay, b"{\"req\":\"card.version\"}\n")?;
         Ok(FutureResponse::from(self.note))
     }
+
+    /// Configure the AUX pins of the Notecard as GPIOs. `usage` sets the mode of AUX1-4, the
+    /// response reports the state of each pin. If `sync` is set changes on input pins are recorded
+    /// to `file` (default `_button.qo`).
+    pub fn aux_gpio(
+        self,
+        delay: &mut impl DelayMs<u16>,
+        usage: Option<[req::AuxGpioUsage; 4]>,
+        seconds: Option<u32>,
+        sync: bool,
+        file: Option<&str>,
+    ) -> Result<FutureResponse<'a, res::Aux, IOM, BS>, NoteError> {
+        self.note.request(
+            delay,
+            req::Aux {
+                mode: Some(req::AuxMode::Gpio),
+                usage,
+                seconds,
+                sync: sync.then_some(true),
+                file: notefile::check(file)?,
+                ..req::Aux::new()
+            },
+        )?;
+        Ok(FutureResponse::from(self.note))
+    }
+
+    /// Set the mode of the AUX pins. In `monitor` mode the AUX pins drive the Notecarrier LEDs,
+    /// and in `neo-monitor` mode a strip of `count` NeoPixels, where the host may control the pixel
+    /// at `offset` (1-based). Use `off` to disable the AUX pins. See [`Card::aux_gpio`] for `gpio`
+    /// mode.
+    pub fn aux(
+        self,
+        delay: &mut impl DelayMs<u16>,
+        mode: req::AuxMode,
+        count: Option<u32>,
+        offset: Option<u32>,
+    ) -> Result<FutureResponse<'a, res::Aux, IOM, BS>, NoteError> {
+        self.note.request(
+            delay,
+            req::Aux {
+                mode: Some(mode),
+                count,
+                offset,
+                ..req::Aux::new()
+            },
+        )?;
+        Ok(FutureResponse::from(self.note))
+    }
+
+    /// Configure the AUX serial port. `mode` is one of `req`, `gps`, `notify`, or `notify` with
+    /// additional sources, e.g. `notify,accel`. `rate` sets the baud rate, `max` the maximum number
+    /// of bytes sent per notification, and `ms` the delay between notifications.
+    pub fn aux_serial(
+        self,
+        delay: &mut impl DelayMs<u16>,
+        mode: Option<&str>,
+        duration: Option<u32>,
+        rate: Option<u32>,
+        max: Option<u32>,
+        ms: Option<u32>,
+    ) -> Result<FutureResponse<'a, res::AuxSerial, IOM, BS>, NoteError> {
+        self.note.request(
+            delay,
+            req::AuxSerial {
+                mode,
+                duration,
+                rate,
+                max,
+                ms,
+                ..req::AuxSerial::new()
+            },
+        )?;
+        Ok(FutureResponse::from(self.note))
+    }
+
+    /// Provides the current supply voltage, and with `hours` set, the minimum, maximum and average
+    /// voltage over that period together with daily, weekly and monthly trends. `offset` moves the
+    /// analysis window back by the given number of hours, and `vmin`/`vmax` filter out readings.
+    ///
+    /// `mode` sets the battery type or custom thresholds used to determine the voltage level
+    /// (`usb`, `high`, `normal`, `low` or `dead`) that `voutbound`/`vinbound` in `hub.set` use.
+    pub fn voltage(
+        self,
+        delay: &mut impl DelayMs<u16>,
+        hours: Option<u32>,
+        offset: Option<u32>,
+        vmax: Option<f32>,
+        vmin: Option<f32>,
+        mode: Option<req::VoltageMode>,
+    ) -> Result<FutureResponse<'a, res::Voltage, IOM, BS>, NoteError> {
+        self.note.request(
+            delay,
+            req::Voltage {
+                hours,
+                offset,
+                vmax,
+                vmin,
+                mode,
+                ..req::Voltage::new()
+            },
+        )?;
+        Ok(FutureResponse::from(self.note))
+    }
+
+    /// Get the current temperature from the Notecard's onboard sensor (°C). With `minutes` set, the
+    /// temperature is also sampled periodically and added to the `_temp.qo` notefile.
+    pub fn temp(
+        self,
+        delay: &mut impl DelayMs<u16>,
+        minutes: Option<u32>,
+    ) -> Result<FutureResponse<'a, res::Temp, IOM, BS>, NoteError> {
+        self.note.request(
+            delay,
+            req::Temp {
+                minutes,
+                ..req::Temp::new()
+            },
+        )?;
+        Ok(FutureResponse::from(self.note))
+    }
+
+    /// Configure outboard DFU of the host MCU. `name` selects the host type, `on`/`off` enable or
+    /// disable outboard DFU, and `seconds` disables it for the given period.
+    pub fn dfu(
+        self,
+        delay: &mut impl DelayMs<u16>,
+        name: Option<req::DfuName>,
+        on: bool,
+        off: bool,
+        seconds: Option<u32>,
+    ) -> Result<FutureResponse<'a, res::Dfu, IOM, BS>, NoteError> {
+        self.note.request(
+            delay,
+            req::Dfu {
+                name,
+                on: on.then_some(true),
+                off: off.then_some(true),
+                seconds,
+                ..req::Dfu::new()
+            },
+        )?;
+        Ok(FutureResponse::from(self.note))
+    }
+
+    /// Project how long the data plan (`megabytes`, default 1024) will last based on the usage over
+    /// the last `days` or `hours`.
+    pub fn usage_test(
+        self,
+        delay: &mut impl DelayMs<u16>,
+        days: Option<u32>,
+        hours: Option<u32>,
+        megabytes: Option<u32>,
+    ) -> Result<FutureResponse<'a, res::UsageTest, IOM, BS>, NoteError> {
+        self.note.request(
+            delay,
+            req::UsageTest {
+                days,
+                hours,
+                megabytes,
+                ..req::UsageTest::new()
+            },
+        )?;
+        Ok(FutureResponse::from(self.note))
+    }
+
+    /// Change the I2C address of the Notecard, or the USB and I2C behavior with `mode` (e.g.
+    /// `-usb` to disable the USB port). An `i2c` address of `-1` restores the default address
+    /// ([`crate::DEFAULT_I2C_ADDR`]). The response is waited for, and when the address is changed
+    /// the driver switches to the new address once the Notecard has acknowledged it.
+    pub fn io(
+        self,
+        delay: &mut impl DelayMs<u16>,
+        i2c: Option<i16>,
+        mode: Option<&str>,
+    ) -> Result<res::Empty, NoteError> {
+        let addr = match i2c {
+            None => None,
+            Some(-1) => Some(crate::DEFAULT_I2C_ADDR),
+            Some(addr @ 0..=0x7f) => Some(addr as u8),
+            Some(addr) => {
+                error!("card.io: invalid i2c address: {}", addr);
+                return Err(NoteError::InvalidArgument);
+            }
+        };
+
+        self.note.request(
+            delay,
+            req::Io {
+                i2c,
+                mode,
+                ..req::Io::new()
+            },
+        )?;
+
+        let r = FutureResponse::<res::Empty, IOM, BS>::from(&mut *self.note).wait(delay)?;
+
+        if let Some(addr) = addr {
+            info!("card.io: changing i2c address to: {}", addr);
+            self.note.transport.set_address(addr);
+        }
+
+        Ok(r)
+    }
+
+    /// Turn an LED driven by the Notecard (in `led` or `neo` AUX mode) on or off.
+    pub fn led(
+        self,
+        delay: &mut impl DelayMs<u16>,
+        mode: req::LedColor,
+        on: bool,
+    ) -> Result<FutureResponse<'a, res::Empty, IOM, BS>, NoteError> {
+        self.note.request(
+            delay,
+            req::Led {
+                on: on.then_some(true),
+                off: (!on).then_some(true),
+                ..req::Led::new(mode)
+            },
+        )?;
+        Ok(FutureResponse::from(self.note))
+    }
+
+    /// Set up the WiFi credentials of a WiFi Notecard. With `start` the Notecard starts a SoftAP
+    /// with the given `name` and `org` shown on the access point page, so credentials can be
+    /// entered from a browser.
+    pub fn wifi(
+        self,
+        delay: &mut impl DelayMs<u16>,
+        ssid: Option<&str>,
+        password: Option<&str>,
+        name: Option<&str>,
+        org: Option<&str>,
+        start: bool,
+    ) -> Result<FutureResponse<'a, res::Wifi, IOM, BS>, NoteError> {
+        self.note.request(
+            delay,
+            req::Wifi {
+                ssid,
+                password,
+                name,
+                org,
+                start: start.then_some(true),
+                ..req::Wifi::new()
+            },
+        )?;
+        Ok(FutureResponse::from(self.note))
+    }
+
+    /// Configure (or with `method` set to `None`, query) which radio transport the Notecard uses.
+    /// `allow` permits the use of NTN (satellite) transport.
+    pub fn transport(
+        self,
+        delay: &mut impl DelayMs<u16>,
+        method: Option<req::TransportMethod>,
+        allow: Option<bool>,
+    ) -> Result<FutureResponse<'a, res::Transport, IOM, BS>, NoteError> {
+        if method == Some(req::TransportMethod::Unknown) {
+            return Err(NoteError::InvalidArgument);
+        }
+
+        self.note.request(
+            delay,
+            req::Transport {
+                method,
+                allow,
+                ..req::Transport::new()
+            },
+        )?;
+        Ok(FutureResponse::from(self.note))
+    }
+
+    /// Query the binary storage area of the Notecard, or clear it with `delete`. The response
+    /// reports the length of the stored data, its COBS encoded length, the MD5 (`status`), and the
+    /// maximum size of the storage area. A transfer error (e.g. `{bad-bin}`) is returned as
+    /// [`NoteError::NotecardErr`].
+    pub fn binary(
+        self,
+        delay: &mut impl DelayMs<u16>,
+        delete: bool,
+    ) -> Result<FutureResponse<'a, res::Binary, IOM, BS>, NoteError> {
+        self.note.request(
+            delay,
+            req::Binary {
+                delete: delete.then_some(true),
+                ..req::Binary::new()
+            },
+        )?;
+        Ok(FutureResponse::from(self.note))
+    }
+
+    /// Store `data` in the binary storage area of the Notecard at `offset` (appended when `None`).
+    /// The data is COBS encoded and sent directly after the request, together with its MD5 so
+    /// that the Notecard can verify it. Use [`Card::binary`] to query the result.
+    pub fn binary_put(
+        self,
+        delay: &mut impl DelayMs<u16>,
+        data: &[u8],
+        offset: Option<u32>,
+    ) -> Result<FutureResponse<'a, res::Empty, IOM, BS>, NoteError> {
+        let status = md5_hex(data);
+
+        self.note.request_binary(
+            delay,
+            req::BinaryPut {
+                offset,
+                ..req::BinaryPut::new(cobs::encoded_len(data) as u32, &status)
+            },
+            data,
+        )?;
+        Ok(FutureResponse::from(self.note))
+    }
+
+    /// Get a random number from the hardware random number generator of the Notecard, in the range
+    /// `0..count`. With `payload` set, `count` random bytes (at most [`RANDOM_PAYLOAD_MAX`]) are
+    /// returned instead, use [`res::Random::decode_payload`] to get them.
+    pub fn random(
+        self,
+        delay: &mut impl DelayMs<u16>,
+        count: Option<u32>,
+        payload: bool,
+    ) -> Result<FutureResponse<'a, res::Random, IOM, BS>, NoteError> {
+        if payload && count.is_some_and(|c| c as usize > RANDOM_PAYLOAD_MAX) {
+            return Err(NoteError::BufOverflow);
+        }
+
+        self.note.request(
+            delay,
+            req::Random {
+                count,
+                mode: payload.then_some("payload"),
+                ..req::Random::new()
+            },
+        )?;
+        Ok(FutureResponse::from(self.note))
+    }
+
+    /// Get the illumination (lux) measured by an attached illumination sensor.
+    pub fn illumination(self, delay: &mut impl DelayMs<u16>) -> Result<FutureResponse<'a, res::Illumination, IOM, BS>, NoteError> {
+        self.note.request_raw(delay, b"{\"req\":\"card.illumination\"}\n")?;
+        Ok(FutureResponse::from(self.note))
+    }
+
+    /// Poll `card.time` until the Notecard knows the time and its time zone (see
+    /// [`res::Time::is_valid`]), for up to `timeout` ms. The Notecard gets the time when it first
+    /// connects to Notehub, and responds with an error until then. That error and transient
+    /// errors are retried, any other error is returned.
+    pub fn wait_for_time(
+        self,
+        delay: &mut impl DelayMs<u16>,
+        timeout: u32,
+    ) -> Result<res::Time, NoteError> {
+        const RETRY_DELAY: u16 = 1000;
+
+        let mut waited = 0;
+        loop {
+            match Card::from(&mut *self.note).time(delay)?.wait(delay) {
+                Ok(t) if t.is_valid() => return Ok(t),
+                Ok(_) => debug!("card: time: zone not known yet."),
+                Err(NoteError::NotecardErr(e))
+                    if e.message().contains("time is not yet set")
+                        || e.message().contains("{no-time}")
+                        || e.is_transient() =>
+                {
+                    debug!("card: time: not set: {}", e.message())
+                }
+                Err(e) => return Err(e),
+            }
+
+            if waited >= timeout {
+                error!("card: time: not set after {} ms.", timeout);
+                return Err(NoteError::timeout("card.time"));
+            }
+
+            delay.delay_ms(RETRY_DELAY);
+            waited += RETRY_DELAY as u32;
+        }
+    }
+
+    /// Restart the Notecard and wait for it to come back. The Notecard is unresponsive for a few
+    /// seconds after a restart, so the driver waits `boot` ms before re-initializing the transport
+    /// (retrying until `response_timeout`). With `connect` set, this then waits up to `connect` ms
+    /// for `hub.status` to report that the Notecard is connected to Notehub.
+    pub fn restart_reconnect(
+        self,
+        delay: &mut impl DelayMs<u16>,
+        boot: u32,
+        connect: Option<u32>,
+    ) -> Result<(), NoteError> {
+        const RETRY_DELAY: u16 = 500;
+
+        info!("card: restarting notecard..");
+
+        // The Notecard may restart before responding.
+        if let Err(e) = self.note.request_raw(delay, b"{\"req\":\"card.restart\"}\n").and_then(|_| {
+            FutureResponse::<res::Empty, IOM, BS>::from(&mut *self.note).wait(delay)
+        }) {
+            debug!("card: restart: no response: {:?}", e);
+        }
+
+        let mut waited = 0;
+        while waited < boot {
+            let d = (boot - waited).min(u16::MAX as u32) as u16;
+            delay.delay_ms(d);
+            waited += d as u32;
+        }
+
+        let mut waited = 0;
+        loop {
+            match self.note.sync_transport(delay) {
+                Ok(()) => break,
+                Err(e) if waited < self.note.response_timeout => {
+                    debug!("card: restart: notecard not ready: {:?}", e);
+                    delay.delay_ms(RETRY_DELAY);
+                    waited += RETRY_DELAY as u32;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        if let Some(connect) = connect {
+            info!("card: restarted, waiting for notehub connection..");
+
+            let mut waited = 0;
+            while waited < connect {
+                if self.note.hub().status(delay)?.wait(delay)?.connected {
+                    return Ok(());
+                }
+
+                delay.delay_ms(RETRY_DELAY);
+                waited += RETRY_DELAY as u32;
+            }
+
+            error!("card: restart: not connected after {} ms.", connect);
+            return Err(NoteError::timeout("card.restart"));
+        }
+
+        Ok(())
+    }
+}
+
+/// Mode of the GPS/GNSS module, set with `card.location.mode`.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "defmt-format", derive(defmt::Format))]
+#[serde(rename_all = "lowercase")]
+pub enum LocationMode {
+    Off,
+    Periodic,
+    Continuous,
+    Fixed,
+
+    /// A mode reported by the Notecard that is not known to the driver. Not accepted in requests.
+    #[serde(other)]
+    Unknown,
+}
+
+/// Builder for `card.location.mode`, created with [`Card::location_mode_builder`]. Arguments that
+/// are not set are left out of the request.
+pub struct LocationModeBuilder<'a, IOM: NotecardTransport, const BS: usize> {
+    note: &'a mut Notecard<IOM, BS>,
+    req: req::LocationMode<'static>,
+}
+
+impl<'a, IOM: NotecardTransport, const BS: usize> LocationModeBuilder<'a, IOM, BS> {
+    pub fn mode(mut self, mode: LocationMode) -> Self {
+        self.req.mode = Some(mode);
+        self
+    }
+
+    /// Sampling interval in `periodic` mode (seconds).
+    pub fn seconds(mut self, seconds: u32) -> Self {
+        self.req.seconds = Some(seconds);
+        self
+    }
+
+    /// Sampling interval depending on the voltage (`vseconds`), e.g. `usb:3600;normal:7200`.
+    pub fn vseconds(mut self, vseconds: &str) -> Result<Self, NoteError> {
+        self.req.vseconds = Some(crate::to_string(vseconds)?);
+        Ok(self)
+    }
+
+    /// Use a fixed location instead of the GPS/GNSS module.
+    pub fn fixed(self, lat: f64, lon: f64) -> Self {
+        self.mode(LocationMode::Fixed).position(lat, lon)
+    }
+
+    /// The fixed location, or the center of the geofence.
+    pub fn position(mut self, lat: f64, lon: f64) -> Self {
+        self.req.lat = Some(lat);
+        self.req.lon = Some(lon);
+        self
+    }
+
+    /// The fixed location, or the center of the geofence, in millionths of a degree.
+    pub fn position_e6(self, lat: i32, lon: i32) -> Self {
+        self.position(from_microdegrees(lat), from_microdegrees(lon))
+    }
+
+    /// Delete the last known location.
+    pub fn delete(mut self) -> Self {
+        self.req.delete = Some(true);
+        self
+    }
+
+    /// Radius of a geofence around `lat`/`lon` (meters), enables geofence location tracking.
+    pub fn max(mut self, max: u32) -> Self {
+        self.req.max = Some(max);
+        self
+    }
+
+    /// Time the Notecard must be outside the geofence before the location is tracked (minutes).
+    pub fn minutes(mut self, minutes: u32) -> Self {
+        self.req.minutes = Some(minutes);
+        self
+    }
+
+    pub fn send(
+        self,
+        delay: &mut impl DelayMs<u16>,
+    ) -> Result<FutureResponse<'a, res::LocationMode, IOM, BS>, NoteError> {
+        if self.req.mode == Some(LocationMode::Unknown) {
+            return Err(NoteError::InvalidArgument);
+        }
+
+        self.note.request(delay, self.req)?;
+        Ok(FutureResponse::from(self.note))
+    }
+}
+
+/// MD5 of `data` as hex string.
+pub(crate) fn md5_hex(data: &[u8]) -> heapless::String<32> {
+    use md5::{Digest, Md5};
+
+    digest_hex(&Md5::digest(data))
+}
+
+/// Format a 16 byte digest as hex string.
+pub(crate) fn digest_hex(digest: &[u8]) -> heapless::String<32> {
+    use core::fmt::Write;
+
+    let mut s = heapless::String::new();
+    for b in digest.iter().take(16) {
+        write!(s, "{:02x}", b).ok();
+    }
+    s
+}
+
+/// A latitude or longitude in millionths of a degree (about 0.1 m), rounded to the nearest.
+pub fn microdegrees(degrees: f64) -> i32 {
+    let v = degrees * 1e6;
+    (if v < 0.0 { v - 0.5 } else { v + 0.5 }) as i32
+}
+
+/// A latitude or longitude in millionths of a degree in degrees.
+pub fn from_microdegrees(microdegrees: i32) -> f64 {
+    microdegrees as f64 / 1e6
+}
+
+/// The `{...}` tokens in a status string without braces, e.g. `gps-active` and `gps-signal` in
+/// `GPS inactive {gps-active} {gps-signal}`.
+pub(crate) fn tokens(s: &str) -> impl Iterator<Item = &str> {
+    s.split('{')
+        .skip(1)
+        .filter_map(|t| t.split_once('}').map(|(t, _)| t))
 }
 
 pub mod req {
     use super::*;
 
-    #[derive(Deserialize, Serialize, defmt::Format, Default)]
-    pub struct LocationTrack {
-        pub req: &'static str,
+    #[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "defmt-format", derive(defmt::Format))]
+    pub struct LocationTrack<'a> {
+        pub req: &'a str,
 
         #[serde(skip_serializing_if = "Option::is_none")]
         pub start: Option<bool>,
@@ -127,148 +805,1227 @@ pub mod req {
         pub hours: Option<i32>,
 
         #[serde(skip_serializing_if = "Option::is_none")]
-        pub file: Option<heapless::String<20>>,
+        pub file: Option<heapless::String<{ crate::NOTEFILE_NAME_LEN }>>,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub payload: Option<&'a str>,
     }
 
-    #[derive(Deserialize, Serialize, defmt::Format, Default)]
-    pub struct LocationMode {
-        pub req: &'static str,
+    impl<'a> LocationTrack<'a> {
+        /// A `card.location.track` request with all optional fields unset.
+        pub fn new() -> LocationTrack<'a> {
+            LocationTrack {
+                req: "card.location.track",
+                ..Default::default()
+            }
+        }
+    }
+
+    #[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "defmt-format", derive(defmt::Format))]
+    pub struct LocationMode<'a> {
+        pub req: &'a str,
 
         #[serde(skip_serializing_if = "Option::is_none")]
-        pub mode: Option<heapless::String<20>>,
+        pub mode: Option<crate::card::LocationMode>,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub seconds: Option<u32>,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub vseconds: Option<heapless::String<20>>,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub delete: Option<bool>,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub max: Option<u32>,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub lat: Option<f64>,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub lon: Option<f64>,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub minutes: Option<u32>,
+    }
+
+    impl<'a> LocationMode<'a> {
+        /// A `card.location.mode` request with all optional fields unset.
+        pub fn new() -> LocationMode<'a> {
+            LocationMode {
+                req: "card.location.mode",
+                ..Default::default()
+            }
+        }
+    }
+
+    #[derive(Serialize, Default, Debug, Clone, PartialEq)]
+    pub struct Attn<'a> {
+        pub req: &'a str,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub mode: Option<&'a str>,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub files: Option<&'a [&'a str]>,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub seconds: Option<u32>,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub payload: Option<&'a str>,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub start: Option<bool>,
+    }
+
+    impl<'a> Attn<'a> {
+        /// A `card.attn` request with all optional fields unset.
+        pub fn new() -> Attn<'a> {
+            Attn {
+                req: "card.attn",
+                ..Default::default()
+            }
+        }
+    }
+
+    #[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "defmt-format", derive(defmt::Format))]
+    #[serde(rename_all = "kebab-case")]
+    pub enum AuxGpioUsage {
+        Off,
+        High,
+        Low,
+        Input,
+        InputPullup,
+        InputPulldown,
+        Count,
+        CountPullup,
+        CountPulldown,
+    }
+
+    #[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "defmt-format", derive(defmt::Format))]
+    #[serde(rename_all = "kebab-case")]
+    pub enum AuxMode {
+        Off,
+        Gpio,
+        Led,
+        Neo,
+        Monitor,
+        NeoMonitor,
+    }
+
+    #[derive(Serialize, Default, Debug, Clone, PartialEq)]
+    pub struct Aux<'a> {
+        pub req: &'a str,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub mode: Option<AuxMode>,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub usage: Option<[AuxGpioUsage; 4]>,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub seconds: Option<u32>,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub sync: Option<bool>,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub file: Option<&'a str>,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub count: Option<u32>,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub offset: Option<u32>,
+    }
+
+    impl<'a> Aux<'a> {
+        /// A `card.aux` request with all optional fields unset.
+        pub fn new() -> Aux<'a> {
+            Aux {
+                req: "card.aux",
+                ..Default::default()
+            }
+        }
+    }
+
+    #[derive(Serialize, Default, Debug, Clone, PartialEq)]
+    pub struct AuxSerial<'a> {
+        pub req: &'a str,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub mode: Option<&'a str>,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub duration: Option<u32>,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub rate: Option<u32>,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub max: Option<u32>,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub ms: Option<u32>,
+    }
+
+    impl<'a> AuxSerial<'a> {
+        /// A `card.aux.serial` request with all optional fields unset.
+        pub fn new() -> AuxSerial<'a> {
+            AuxSerial {
+                req: "card.aux.serial",
+                ..Default::default()
+            }
+        }
+    }
+
+    /// Voltage thresholds for a battery type, or custom thresholds built with
+    /// [`VoltageThresholds`].
+    #[derive(Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "defmt-format", derive(defmt::Format))]
+    pub enum VoltageMode<'a> {
+        Default,
+        Lipo,
+        L91,
+        Alkaline,
+        Tad,
+        Lic,
+        Custom(&'a str),
+    }
+
+    impl Serialize for VoltageMode<'_> {
+        fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            use VoltageMode::*;
+
+            serializer.serialize_str(match self {
+                Default => "default",
+                Lipo => "lipo",
+                L91 => "l91",
+                Alkaline => "alkaline",
+                Tad => "tad",
+                Lic => "lic",
+                Custom(s) => s,
+            })
+        }
+    }
+
+    /// Builder for custom voltage thresholds, e.g. `usb:4.6;high:4.0;normal:3.5;low:3.0;dead:0`.
+    /// Each threshold is the minimum voltage for that level.
+    #[derive(Default, Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "defmt-format", derive(defmt::Format))]
+    pub struct VoltageThresholds {
+        thresholds: heapless::String<80>,
+    }
+
+    impl VoltageThresholds {
+        pub fn new() -> VoltageThresholds {
+            VoltageThresholds::default()
+        }
+
+        /// Add a threshold for the level `name`.
+        pub fn threshold(mut self, name: &str, volts: f32) -> Result<VoltageThresholds, NoteError> {
+            use core::fmt::Write;
+
+            if !self.thresholds.is_empty() {
+                self.thresholds.push(';').map_err(|_| NoteError::BufOverflow)?;
+            }
+            write!(self.thresholds, "{}:{}", name, volts).map_err(|_| NoteError::BufOverflow)?;
+
+            Ok(self)
+        }
+
+        pub fn usb(self, volts: f32) -> Result<VoltageThresholds, NoteError> {
+            self.threshold("usb", volts)
+        }
+
+        pub fn high(self, volts: f32) -> Result<VoltageThresholds, NoteError> {
+            self.threshold("high", volts)
+        }
+
+        pub fn normal(self, volts: f32) -> Result<VoltageThresholds, NoteError> {
+            self.threshold("normal", volts)
+        }
+
+        pub fn low(self, volts: f32) -> Result<VoltageThresholds, NoteError> {
+            self.threshold("low", volts)
+        }
+
+        pub fn dead(self, volts: f32) -> Result<VoltageThresholds, NoteError> {
+            self.threshold("dead", volts)
+        }
+
+        pub fn mode(&self) -> VoltageMode<'_> {
+            VoltageMode::Custom(&self.thresholds)
+        }
+    }
+
+    #[derive(Serialize, Default, Debug, Clone, PartialEq)]
+    pub struct Voltage<'a> {
+        pub req: &'a str,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub hours: Option<u32>,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub offset: Option<u32>,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub vmax: Option<f32>,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub vmin: Option<f32>,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub mode: Option<VoltageMode<'a>>,
+    }
+
+    impl<'a> Voltage<'a> {
+        /// A `card.voltage` request with all optional fields unset.
+        pub fn new() -> Voltage<'a> {
+            Voltage {
+                req: "card.voltage",
+                ..Default::default()
+            }
+        }
+    }
+
+    #[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "defmt-format", derive(defmt::Format))]
+    pub struct Temp<'a> {
+        pub req: &'a str,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub minutes: Option<u32>,
+    }
+
+    impl<'a> Temp<'a> {
+        /// A `card.temp` request with all optional fields unset.
+        pub fn new() -> Temp<'a> {
+            Temp {
+                req: "card.temp",
+                ..Default::default()
+            }
+        }
+    }
+
+    #[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "defmt-format", derive(defmt::Format))]
+    #[serde(rename_all = "kebab-case")]
+    pub enum DfuName {
+        Stm32,
+        Stm32Bi,
+        Esp32,
+        Mcuboot,
+        /// Reset to the default (no outboard DFU).
+        #[serde(rename = "-")]
+        Reset,
+    }
+
+    #[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "defmt-format", derive(defmt::Format))]
+    pub struct Dfu<'a> {
+        pub req: &'a str,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub name: Option<DfuName>,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub on: Option<bool>,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub off: Option<bool>,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub seconds: Option<u32>,
+    }
+
+    impl<'a> Dfu<'a> {
+        /// A `card.dfu` request with all optional fields unset.
+        pub fn new() -> Dfu<'a> {
+            Dfu {
+                req: "card.dfu",
+                ..Default::default()
+            }
+        }
+    }
+
+    #[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "defmt-format", derive(defmt::Format))]
+    pub struct UsageTest<'a> {
+        pub req: &'a str,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub days: Option<u32>,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub hours: Option<u32>,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub megabytes: Option<u32>,
+    }
+
+    impl<'a> UsageTest<'a> {
+        /// A `card.usage.test` request with all optional fields unset.
+        pub fn new() -> UsageTest<'a> {
+            UsageTest {
+                req: "card.usage.test",
+                ..Default::default()
+            }
+        }
+    }
+
+    #[derive(Serialize, Default, Debug, Clone, PartialEq)]
+    pub struct Io<'a> {
+        pub req: &'a str,
+
+        /// I2C address, or `-1` for the default address.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub i2c: Option<i16>,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub mode: Option<&'a str>,
+    }
+
+    impl<'a> Io<'a> {
+        /// A `card.io` request with all optional fields unset.
+        pub fn new() -> Io<'a> {
+            Io {
+                req: "card.io",
+                ..Default::default()
+            }
+        }
+    }
+
+    #[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "defmt-format", derive(defmt::Format))]
+    #[serde(rename_all = "lowercase")]
+    pub enum LedColor {
+        Red,
+        Green,
+        Blue,
+        Yellow,
+        Cyan,
+        Magenta,
+        Orange,
+        White,
+        Gray,
+    }
+
+    #[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "defmt-format", derive(defmt::Format))]
+    pub struct Led<'a> {
+        pub req: &'a str,
+
+        pub mode: LedColor,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub on: Option<bool>,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub off: Option<bool>,
+    }
+
+    impl<'a> Led<'a> {
+        /// A `card.led` request for the LED of `mode` color.
+        pub fn new(mode: LedColor) -> Led<'a> {
+            Led {
+                req: "card.led",
+                mode,
+                on: None,
+                off: None,
+            }
+        }
+    }
+
+    #[derive(Serialize, Default, Debug, Clone, PartialEq)]
+    pub struct Wifi<'a> {
+        pub req: &'a str,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub ssid: Option<&'a str>,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub password: Option<&'a str>,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub name: Option<&'a str>,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub org: Option<&'a str>,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub start: Option<bool>,
+    }
+
+    impl<'a> Wifi<'a> {
+        /// A `card.wifi` request with all optional fields unset.
+        pub fn new() -> Wifi<'a> {
+            Wifi {
+                req: "card.wifi",
+                ..Default::default()
+            }
+        }
+    }
+
+    #[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "defmt-format", derive(defmt::Format))]
+    #[serde(rename_all = "kebab-case")]
+    pub enum TransportMethod {
+        /// Reset to the default transport of the Notecard.
+        #[serde(rename = "-")]
+        Default,
+        Cell,
+        Wifi,
+        Ntn,
+        WifiCell,
+        WifiNtn,
+        CellNtn,
+        WifiCellNtn,
+        DualWifiCell,
+
+        /// A method reported by the Notecard that is not known to the driver. Not accepted in
+        /// requests.
+        #[serde(other)]
+        Unknown,
+    }
+
+    #[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "defmt-format", derive(defmt::Format))]
+    pub struct Transport<'a> {
+        pub req: &'a str,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub method: Option<TransportMethod>,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub allow: Option<bool>,
+    }
+
+    impl<'a> Transport<'a> {
+        /// A `card.transport` request with all optional fields unset.
+        pub fn new() -> Transport<'a> {
+            Transport {
+                req: "card.transport",
+                ..Default::default()
+            }
+        }
+    }
+
+    #[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "defmt-format", derive(defmt::Format))]
+    pub struct Binary<'a> {
+        pub req: &'a str,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub delete: Option<bool>,
+    }
+
+    impl<'a> Binary<'a> {
+        /// A `card.binary` request with all optional fields unset.
+        pub fn new() -> Binary<'a> {
+            Binary {
+                req: "card.binary",
+                ..Default::default()
+            }
+        }
+    }
+
+    #[derive(Serialize, Default, Debug, Clone, PartialEq)]
+    pub struct BinaryPut<'a> {
+        pub req: &'a str,
+
+        /// Length of the COBS encoded data.
+        pub cobs: u32,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub offset: Option<u32>,
+
+        /// MD5 of the data.
+        pub status: &'a str,
+    }
+
+    impl<'a> BinaryPut<'a> {
+        /// A `card.binary.put` request for `cobs` bytes of encoded data with the MD5 `status`.
+        pub fn new(cobs: u32, status: &'a str) -> BinaryPut<'a> {
+            BinaryPut {
+                req: "card.binary.put",
+                cobs,
+                offset: None,
+                status,
+            }
+        }
+    }
+
+    #[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "defmt-format", derive(defmt::Format))]
+    pub struct Random<'a> {
+        pub req: &'a str,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub count: Option<u32>,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub mode: Option<&'a str>,
+    }
+
+    impl<'a> Random<'a> {
+        /// A `card.random` request with all optional fields unset.
+        pub fn new() -> Random<'a> {
+            Random {
+                req: "card.random",
+                ..Default::default()
+            }
+        }
+    }
+}
+
+pub mod res {
+    use super::*;
+
+    #[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "defmt-format", derive(defmt::Format))]
+    pub struct Empty {}
+
+    #[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "defmt-format", derive(defmt::Format))]
+    pub struct LocationTrack<S = heapless::String<120>> {
+        pub start: Option<bool>,
+        pub stop: Option<bool>,
+        pub heartbeat: Option<bool>,
+        pub sync: Option<bool>,
+        pub seconds: Option<u32>,
+        pub hours: Option<i32>,
+        pub file: Option<heapless::String<{ crate::NOTEFILE_NAME_LEN }>>,
+
+        /// Base64 encoded payload added to every tracking Note.
+        pub payload: Option<S>,
+    }
+
+    #[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "defmt-format", derive(defmt::Format))]
+    pub struct LocationMode {
+        pub mode: crate::card::LocationMode,
+        pub seconds: Option<u32>,
+        pub vseconds: Option<heapless::String<40>>,
+        pub max: Option<u32>,
+        pub lat: Option<f64>,
+        pub lon: Option<f64>,
+        pub minutes: Option<u32>,
+    }
+
+    impl LocationMode {
+        /// `lat` and `lon` in millionths of a degree.
+        pub fn position_e6(&self) -> Option<(i32, i32)> {
+            Some((microdegrees(self.lat?), microdegrees(self.lon?)))
+        }
+    }
+
+    #[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "defmt-format", derive(defmt::Format))]
+    pub struct Location<S = heapless::String<120>> {
+        pub status: S,
+        pub mode: S,
+        pub lat: Option<f64>,
+        pub lon: Option<f64>,
+        pub time: Option<u64>,
+        pub max: Option<u32>,
+    }
+
+    impl<S: AsRef<str>> Location<S> {
+        /// The GPS state described by `status`.
+        pub fn gps_status(&self) -> GpsStatus {
+            GpsStatus::parse(self.status.as_ref())
+        }
+
+        /// `lat` and `lon` in millionths of a degree.
+        pub fn position_e6(&self) -> Option<(i32, i32)> {
+            Some((microdegrees(self.lat?), microdegrees(self.lon?)))
+        }
+
+        /// The time of the location fix.
+        #[cfg(feature = "chrono")]
+        pub fn to_chrono(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+            crate::epoch::to_chrono(self.time?)
+        }
+
+        /// The time of the location fix.
+        #[cfg(feature = "time")]
+        pub fn to_offset_date_time(&self) -> Option<time::OffsetDateTime> {
+            crate::epoch::to_offset_date_time(self.time?)
+        }
+    }
+
+    /// The GPS state in [`Location::status`], e.g. `GPS updated (58 sec, 41dB SNR, 9 sats)
+    /// {gps-active} {gps-signal} {gps-sats} {gps}` or `GPS search (111 sec, 32/33 dB SNR, 0/1
+    /// sats) {gps-active} {gps-signal} {gps-sats}`. Values that are not in the status are `None`.
+    #[derive(Debug, Default, Clone, Copy, PartialEq)]
+    #[cfg_attr(feature = "defmt-format", derive(defmt::Format))]
+    pub struct GpsStatus {
+        /// Seconds spent searching for, or since acquiring, a fix.
+        pub seconds: Option<u32>,
+
+        /// Signal to noise ratio in dB. The first value if the status gives two.
+        pub snr: Option<u8>,
+
+        /// Satellites used for the fix.
+        pub sats_used: Option<u8>,
+
+        /// Satellites in view, when the status gives both counts.
+        pub sats_seen: Option<u8>,
+
+        /// `{gps-active}`: the GPS is powered.
+        pub active: bool,
+
+        /// `{gps-inactive}`: the GPS is powered off.
+        pub inactive: bool,
+
+        /// `{gps-signal}`: a GPS signal has been received.
+        pub signal: bool,
+
+        /// `{gps-sats}`: satellites have been found.
+        pub sats: bool,
+
+        /// `{gps}`: the location has been updated from a fix.
+        pub fix: bool,
+    }
+
+    impl GpsStatus {
+        pub fn parse(status: &str) -> GpsStatus {
+            let mut gps = GpsStatus::default();
+
+            let details = status
+                .split_once('(')
+                .and_then(|(_, d)| d.split_once(')'))
+                .map_or("", |(d, _)| d);
+
+            for part in details.split(',').map(str::trim) {
+                if let Some(s) = part.strip_suffix("sec") {
+                    gps.seconds = s.trim().parse().ok();
+                } else if let Some(s) = part.strip_suffix("SNR") {
+                    let s = s.trim().trim_end_matches("dB").trim();
+                    gps.snr = s.split('/').next().and_then(|s| s.parse().ok());
+                } else if let Some(s) = part.strip_suffix("sats") {
+                    match s.trim().split_once('/') {
+                        Some((used, seen)) => {
+                            gps.sats_used = used.parse().ok();
+                            gps.sats_seen = seen.parse().ok();
+                        }
+                        None => gps.sats_used = s.trim().parse().ok(),
+                    }
+                }
+            }
+
+            for token in tokens(status) {
+                match token {
+                    "gps-active" => gps.active = true,
+                    "gps-inactive" => gps.inactive = true,
+                    "gps-signal" => gps.signal = true,
+                    "gps-sats" => gps.sats = true,
+                    "gps" => gps.fix = true,
+                    _ => (),
+                }
+            }
+
+            gps
+        }
+    }
+
+    #[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "defmt-format", derive(defmt::Format))]
+    pub struct Time<S = heapless::String<120>> {
+        pub time: Option<u64>,
+        pub area: Option<S>,
+        pub zone: Option<S>,
+        pub minutes: Option<i32>,
+        pub lat: Option<f64>,
+        pub lon: Option<f64>,
+        pub country: Option<S>,
+    }
+
+    #[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "defmt-format", derive(defmt::Format))]
+    pub struct Status {
+        pub status: heapless::String<40>,
+        #[serde(default)]
+        pub usb: bool,
+        pub storage: usize,
+        pub time: Option<u64>,
+        #[serde(default)]
+        pub connected: bool,
+    }
+
+    impl Status {
+        /// The tokens of `status`.
+        pub fn flags(&self) -> StatusFlags<'_> {
+            StatusFlags::parse(&self.status)
+        }
+
+        /// The current time, when known by the Notecard.
+        #[cfg(feature = "chrono")]
+        pub fn to_chrono(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+            crate::epoch::to_chrono(self.time?)
+        }
+
+        /// The current time, when known by the Notecard.
+        #[cfg(feature = "time")]
+        pub fn to_offset_date_time(&self) -> Option<time::OffsetDateTime> {
+            crate::epoch::to_offset_date_time(self.time?)
+        }
+    }
+
+    /// The `{...}` tokens of [`Status::status`], e.g. `{normal}` or `{recovery}{usb}`.
+    #[derive(Debug, Clone, PartialEq, Default)]
+    #[cfg_attr(feature = "defmt-format", derive(defmt::Format))]
+    pub struct StatusFlags<'a> {
+        /// `{normal}`: the Notecard is operating normally.
+        pub normal: bool,
+
+        /// `{recovery}`: the Notecard is recovering from a fault.
+        pub recovery: bool,
+
+        /// `{usb}`: the Notecard is powered over USB.
+        pub usb: bool,
+
+        /// Other tokens, without braces. Only the first four are kept.
+        pub unknown: heapless::Vec<&'a str, 4>,
+    }
+
+    impl<'a> StatusFlags<'a> {
+        pub fn parse(status: &'a str) -> StatusFlags<'a> {
+            let mut flags = StatusFlags::default();
+
+            for token in tokens(status) {
+                match token {
+                    "normal" => flags.normal = true,
+                    "recovery" => flags.recovery = true,
+                    "usb" => flags.usb = true,
+                    t => {
+                        flags.unknown.push(t).ok();
+                    }
+                }
+            }
+
+            flags
+        }
+    }
+
+    #[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "defmt-format", derive(defmt::Format))]
+    pub struct WirelessNet {
+        pub iccid: Option<heapless::String<24>>,
+        pub imsi: Option<heapless::String<24>>,
+        pub imei: Option<heapless::String<24>>,
+        pub modem: Option<heapless::String<64>>,
+        pub band: Option<heapless::String<24>>,
+        pub rat: Option<heapless::String<24>>,
+        pub rssir: Option<i32>,
+        pub rssi: Option<i32>,
+        pub rsrp: Option<i32>,
+        pub sinr: Option<i32>,
+        pub rsrq: Option<i32>,
+        pub bars: Option<i32>,
+        pub mcc: Option<i32>,
+        pub mnc: Option<i32>,
+        pub lac: Option<i32>,
+        pub cid: Option<i32>,
+        pub updated: Option<u64>,
+    }
+
+    impl WirelessNet {
+        /// The radio access technology in `rat`.
+        pub fn rat_kind(&self) -> Option<Rat> {
+            self.rat.as_deref().map(Rat::parse)
+        }
+
+        /// Signal strength in dBm: `rsrp` on LTE networks, otherwise `rssi`.
+        pub fn signal_dbm(&self) -> Option<i32> {
+            self.rsrp.or(self.rssi)
+        }
+
+        /// Signal bars from 0 to 4, as shown by the Notecard.
+        pub fn signal_bars(&self) -> Option<u8> {
+            self.bars.map(|b| b.clamp(0, 4) as u8)
+        }
+    }
+
+    /// Radio access technology of the cellular network.
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    #[cfg_attr(feature = "defmt-format", derive(defmt::Format))]
+    pub enum Rat {
+        Gsm,
+        Umts,
+        Lte,
+        LteM,
+        NbIot,
+        Wifi,
+        Other,
+    }
+
+    impl Rat {
+        pub fn parse(rat: &str) -> Rat {
+            match rat {
+                "gsm" | "gprs" | "edge" => Rat::Gsm,
+                "umts" | "wcdma" | "hspa" => Rat::Umts,
+                "lte" => Rat::Lte,
+                "catm" | "cat-m" | "cat-m1" | "ltem" | "lte-m" | "emtc" => Rat::LteM,
+                "nbiot" | "nb-iot" | "nb1" => Rat::NbIot,
+                "wifi" => Rat::Wifi,
+                _ => Rat::Other,
+            }
+        }
+
+        /// The signal strength in dBm at which 1, 2, 3 and 4 bars are shown: RSRP on LTE
+        /// networks and RSSI otherwise.
+        pub fn thresholds(self) -> [i32; 4] {
+            match self {
+                Rat::Lte | Rat::LteM | Rat::NbIot => [-115, -105, -95, -85],
+                Rat::Wifi => [-85, -75, -65, -55],
+                Rat::Gsm | Rat::Umts | Rat::Other => [-107, -101, -95, -89],
+            }
+        }
+    }
+
+    /// Connection strength across radio types, from [`Wireless`] or [`Wifi`].
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    #[cfg_attr(feature = "defmt-format", derive(defmt::Format))]
+    pub struct SignalQuality {
+        /// Bars from 0 to 4.
+        pub bars: u8,
+
+        /// Signal strength in dBm, see [`Rat::thresholds`].
+        pub dbm: Option<i32>,
+
+        pub rat: Rat,
+    }
+
+    impl SignalQuality {
+        /// The bars for a signal strength of `dbm` on `rat`.
+        pub fn from_dbm(rat: Rat, dbm: i32) -> SignalQuality {
+            let bars = rat.thresholds().iter().filter(|t| dbm >= **t).count() as u8;
+
+            SignalQuality {
+                bars,
+                dbm: Some(dbm),
+                rat,
+            }
+        }
+
+        /// The signal quality of the cellular (or WiFi) network in `card.wireless`. The bars
+        /// reported by the Notecard are used when present. `None` when there is no signal
+        /// information, e.g. while the modem is off.
+        pub fn from_wireless(net: &WirelessNet) -> Option<SignalQuality> {
+            let rat = net.rat_kind().unwrap_or(Rat::Other);
+            let dbm = net.signal_dbm();
+
+            match (net.signal_bars(), dbm) {
+                (Some(bars), dbm) => Some(SignalQuality { bars, dbm, rat }),
+                (None, Some(dbm)) => Some(SignalQuality::from_dbm(rat, dbm)),
+                (None, None) => None,
+            }
+        }
+
+        /// The signal quality of the access point in `card.wifi`.
+        pub fn from_wifi(wifi: &Wifi) -> Option<SignalQuality> {
+            wifi.rssi.map(|dbm| SignalQuality::from_dbm(Rat::Wifi, dbm))
+        }
+    }
+
+    #[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "defmt-format", derive(defmt::Format))]
+    pub struct Wireless {
+        /// One or more `{...}` tags, e.g. `{cell-registration-wait}{network-up}`.
+        pub status: heapless::String<120>,
+        pub mode: Option<heapless::String<24>>,
+        pub count: Option<u8>,
+        pub net: Option<WirelessNet>,
+    }
+
+    impl Wireless {
+        pub fn signal_quality(&self) -> Option<SignalQuality> {
+            self.net.as_ref().and_then(SignalQuality::from_wireless)
+        }
+    }
+
+    #[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "defmt-format", derive(defmt::Format))]
+    pub struct Attn {
+        pub files: Option<heapless::Vec<heapless::String<{ crate::NOTEFILE_NAME_LEN }>, 8>>,
+        pub set: Option<bool>,
+        pub payload: Option<heapless::String<{ payload::encoded_len(ATTN_PAYLOAD_MAX) }>>,
+        pub time: Option<u64>,
+    }
+
+    impl<S: AsRef<str>> Time<S> {
+        /// Whether the Notecard knows the time. The time zone may still be unknown, see
+        /// [`Time::is_valid`].
+        pub fn is_set(&self) -> bool {
+            self.time.is_some_and(|t| t > 0)
+        }
+
+        /// The time, once the Notecard knows both the time and its time zone.
+        pub fn valid_time(&self) -> Option<u64> {
+            self.time.filter(|_| self.is_valid())
+        }
+
+        /// Whether the Notecard knows both the time and its time zone. Before the location of the
+        /// Notecard is known the zone is reported as `UTC,Unknown`, and the local time is the
+        /// same as UTC.
+        pub fn is_valid(&self) -> bool {
+            self.is_set()
+                && self
+                    .zone
+                    .as_ref()
+                    .is_some_and(|z| !z.as_ref().starts_with("UTC,Unknown"))
+        }
+
+        /// Local time as seconds since the epoch, using the UTC offset in `minutes`.
+        pub fn local_time(&self) -> Option<i64> {
+            let t = i64::try_from(self.time?).ok()?;
+            Some(t + self.minutes.unwrap_or(0) as i64 * 60)
+        }
+
+        /// The time as a `chrono` date time with the UTC offset of the Notecard.
+        #[cfg(feature = "chrono")]
+        pub fn to_chrono(&self) -> Option<chrono::DateTime<chrono::FixedOffset>> {
+            let offset = chrono::FixedOffset::east_opt(self.minutes.unwrap_or(0) * 60)?;
+            crate::epoch::to_chrono(self.time?).map(|t| t.with_timezone(&offset))
+        }
+
+        /// The time as a `time` date time with the UTC offset of the Notecard.
+        #[cfg(feature = "time")]
+        pub fn to_offset_date_time(&self) -> Option<time::OffsetDateTime> {
+            let offset = time::UtcOffset::from_whole_seconds(self.minutes.unwrap_or(0) * 60).ok()?;
+            crate::epoch::to_offset_date_time(self.time?).map(|t| t.to_offset(offset))
+        }
+    }
+
+    impl Attn {
+        /// Decode the payload stored with [`Card::attn_sleep`] into `out`. Returns an empty slice
+        /// if there is no payload.
+        pub fn decode_payload<'b>(&self, out: &'b mut [u8]) -> Result<&'b [u8], NoteError> {
+            match &self.payload {
+                Some(p) => {
+                    let sz = payload::decode(p, out)?;
+                    Ok(&out[..sz])
+                }
+                None => Ok(&out[..0]),
+            }
+        }
+    }
+
+    #[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "defmt-format", derive(defmt::Format))]
+    pub struct VersionInner {
+        pub org: heapless::String<40>,
+        /// E.g. `Blues Wireless Notecard`.
+        pub product: heapless::String<40>,
+        pub version: heapless::String<24>,
+        pub ver_major: u8,
+        pub ver_minor: u8,
+        pub ver_patch: u8,
+        pub ver_build: u32,
+        pub built: heapless::String<40>,
+    }
+
+    #[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "defmt-format", derive(defmt::Format))]
+    pub struct Version {
+        pub body: VersionInner,
+        pub version: heapless::String<24>,
+        pub device: heapless::String<24>,
+        /// E.g. `Blues Wireless Notecard`.
+        pub name: heapless::String<40>,
+        pub board: heapless::String<24>,
+        pub sku: heapless::String<24>,
+        pub api: u16,
+        pub cell: Option<bool>,
+        pub gps: Option<bool>,
+        pub wifi: Option<bool>,
+    }
 
-        #[serde(skip_serializing_if = "Option::is_none")]
-        pub seconds: Option<u32>,
+    /// Notecard firmware version, ordered so that newer versions compare greater.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+    #[cfg_attr(feature = "defmt-format", derive(defmt::Format))]
+    pub struct FirmwareVersion {
+        pub major: u8,
+        pub minor: u8,
+        pub patch: u8,
+        pub build: u32,
+    }
 
-        #[serde(skip_serializing_if = "Option::is_none")]
-        pub vseconds: Option<heapless::String<20>>,
+    impl FirmwareVersion {
+        pub const fn new(major: u8, minor: u8, patch: u8) -> FirmwareVersion {
+            FirmwareVersion {
+                major,
+                minor,
+                patch,
+                build: 0,
+            }
+        }
+    }
 
-        #[serde(skip_serializing_if = "Option::is_none")]
-        pub delete: Option<bool>,
+    impl Version {
+        /// The firmware version of the Notecard.
+        pub fn firmware(&self) -> FirmwareVersion {
+            FirmwareVersion {
+                major: self.body.ver_major,
+                minor: self.body.ver_minor,
+                patch: self.body.ver_patch,
+                build: self.body.ver_build,
+            }
+        }
 
-        #[serde(skip_serializing_if = "Option::is_none")]
-        pub max: Option<u32>,
+        /// Check whether the Notecard firmware is at least `major.minor.patch`, e.g. to gate
+        /// requests that are only supported on newer firmware.
+        pub fn at_least(&self, major: u8, minor: u8, patch: u8) -> bool {
+            self.firmware() >= FirmwareVersion::new(major, minor, patch)
+        }
+    }
 
-        #[serde(skip_serializing_if = "Option::is_none")]
-        pub lat: Option<f32>,
+    /// The state of an AUX pin in `gpio` mode. Empty for pins that are off.
+    #[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "defmt-format", derive(defmt::Format))]
+    pub struct AuxPinState {
+        pub high: Option<bool>,
+        pub low: Option<bool>,
+        pub count: Option<heapless::Vec<u32, 16>>,
+    }
 
-        #[serde(skip_serializing_if = "Option::is_none")]
-        pub lon: Option<f32>,
+    impl AuxPinState {
+        /// The level of the pin, `None` if the pin is not an output or input.
+        pub fn is_high(&self) -> Option<bool> {
+            match (self.high, self.low) {
+                (Some(true), _) => Some(true),
+                (_, Some(true)) => Some(false),
+                _ => None,
+            }
+        }
+    }
 
-        #[serde(skip_serializing_if = "Option::is_none")]
-        pub minutes: Option<u32>,
+    #[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "defmt-format", derive(defmt::Format))]
+    pub struct Aux {
+        pub mode: Option<heapless::String<20>>,
+        pub state: Option<heapless::Vec<AuxPinState, 4>>,
+        pub time: Option<u64>,
+        pub seconds: Option<u32>,
     }
-}
 
-pub mod res {
-    use super::*;
+    #[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "defmt-format", derive(defmt::Format))]
+    pub struct AuxSerial {
+        pub mode: Option<heapless::String<40>>,
+        pub rate: Option<u32>,
+    }
 
-    #[derive(Deserialize, defmt::Format)]
-    pub struct Empty {}
+    #[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "defmt-format", derive(defmt::Format))]
+    pub struct Voltage {
+        pub value: Option<f64>,
+        pub mode: Option<heapless::String<20>>,
+        pub usb: Option<bool>,
+        pub alert: Option<bool>,
+        pub hours: Option<u32>,
+        pub vmin: Option<f64>,
+        pub vmax: Option<f64>,
+        pub vavg: Option<f64>,
+        pub daily: Option<f64>,
+        pub weekly: Option<f64>,
+        pub monthly: Option<f64>,
+    }
 
-    #[derive(Deserialize, defmt::Format)]
-    pub struct LocationTrack {
-        pub start: Option<bool>,
-        pub stop: Option<bool>,
-        pub heartbeat: Option<bool>,
-        pub seconds: Option<u32>,
-        pub hours: Option<i32>,
-        pub file: Option<heapless::String<20>>,
+    #[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "defmt-format", derive(defmt::Format))]
+    pub struct Temp {
+        pub value: f64,
+        pub calibration: Option<f64>,
     }
 
-    #[derive(Deserialize, defmt::Format)]
-    pub struct LocationMode {
-        pub mode: heapless::String<60>,
-        pub seconds: Option<u32>,
-        pub vseconds: Option<heapless::String<40>>,
-        pub max: Option<u32>,
-        pub lat: Option<f64>,
-        pub lon: Option<f64>,
-        pub minutes: Option<u32>,
+    #[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "defmt-format", derive(defmt::Format))]
+    pub struct Dfu {
+        pub name: Option<heapless::String<20>>,
     }
 
-    #[derive(Deserialize, defmt::Format)]
-    pub struct Location {
-        pub status: heapless::String<120>,
-        pub mode: heapless::String<120>,
-        pub lat: Option<f64>,
-        pub lon: Option<f64>,
-        pub time: Option<u32>,
+    #[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "defmt-format", derive(defmt::Format))]
+    pub struct UsageTest {
+        /// Data plan size (MB).
         pub max: Option<u32>,
+        pub days: Option<u32>,
+        pub hours: Option<u32>,
+        pub bytes_per_day: Option<u32>,
+        /// Projected number of months the data plan will last.
+        pub months: Option<u32>,
+        pub time: Option<u64>,
+        pub bytes_sent: Option<u32>,
+        pub bytes_received: Option<u32>,
+        pub notes_sent: Option<u32>,
+        pub notes_received: Option<u32>,
+        pub sessions_standard: Option<u32>,
+        pub sessions_secure: Option<u32>,
     }
 
-    #[derive(Deserialize, defmt::Format)]
-    pub struct Time {
-        pub time: Option<u32>,
-        pub area: Option<heapless::String<120>>,
-        pub zone: Option<heapless::String<120>>,
-        pub minutes: Option<i32>,
-        pub lat: Option<f64>,
-        pub lon: Option<f64>,
-        pub country: Option<heapless::String<120>>,
+    #[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "defmt-format", derive(defmt::Format))]
+    pub struct Wifi {
+        pub ssid: Option<heapless::String<40>>,
+        pub security: Option<heapless::String<24>>,
+        pub secure: Option<bool>,
+        pub version: Option<heapless::String<24>>,
+        /// Signal strength of the access point in dBm.
+        pub rssi: Option<i32>,
     }
 
-    #[derive(Deserialize, defmt::Format)]
-    pub struct Status {
-        pub status: heapless::String<40>,
-        #[serde(default)]
-        pub usb: bool,
-        pub storage: usize,
-        pub time: Option<u64>,
-        #[serde(default)]
-        pub connected: bool,
+    #[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "defmt-format", derive(defmt::Format))]
+    pub struct Transport {
+        pub method: Option<self::req::TransportMethod>,
     }
 
-    #[derive(Deserialize, defmt::Format)]
-    pub struct WirelessNet {
-        iccid: Option<heapless::String<24>>,
-        imsi: Option<heapless::String<24>>,
-        imei: Option<heapless::String<24>>,
-        modem: Option<heapless::String<35>>,
-        band: Option<heapless::String<24>>,
-        rat: Option<heapless::String<24>>,
-        rssir: Option<i32>,
-        rssi: Option<i32>,
-        rsrp: Option<i32>,
-        sinr: Option<i32>,
-        rsrq: Option<i32>,
-        bars: Option<i32>,
-        mcc: Option<i32>,
-        mnc: Option<i32>,
-        lac: Option<i32>,
-        cid: Option<i32>,
-        updated: Option<u32>,
-    }
-
-    #[derive(Deserialize, defmt::Format)]
-    pub struct Wireless {
-        pub status: heapless::String<24>,
-        pub mode: Option<heapless::String<24>>,
-        pub count: Option<u8>,
-        pub net: Option<WirelessNet>,
+    #[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "defmt-format", derive(defmt::Format))]
+    pub struct Binary {
+        /// COBS encoded length of the stored data.
+        pub cobs: Option<u32>,
+        /// Length of the stored data.
+        pub length: Option<u32>,
+        /// Size of the binary storage area.
+        pub max: Option<u32>,
+        /// MD5 of the stored data.
+        pub status: Option<heapless::String<32>>,
+        pub connected: Option<bool>,
     }
 
-    #[derive(Deserialize, defmt::Format)]
-    pub struct VersionInner {
-        pub org: heapless::String<24>,
-        pub product: heapless::String<24>,
-        pub version: heapless::String<24>,
-        pub ver_major: u8,
-        pub ver_minor: u8,
-        pub ver_patch: u8,
-        pub ver_build: u32,
-        pub built: heapless::String<24>,
+    #[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "defmt-format", derive(defmt::Format))]
+    pub struct Random {
+        pub count: Option<u32>,
+        pub payload: Option<heapless::String<{ payload::encoded_len(RANDOM_PAYLOAD_MAX) }>>,
     }
 
-    #[derive(Deserialize, defmt::Format)]
-    pub struct Version {
-        pub body: VersionInner,
-        pub version: heapless::String<24>,
-        pub device: heapless::String<24>,
-        pub name: heapless::String<24>,
-        pub board: heapless::String<24>,
-        pub sku: heapless::String<24>,
-        pub api: u16,
+    impl Random {
+        /// Decode the random bytes into `out`. Returns an empty slice if there is no payload.
+        pub fn decode_payload<'b>(&self, out: &'b mut [u8]) -> Result<&'b [u8], NoteError> {
+            match &self.payload {
+                Some(p) => {
+                    let sz = payload::decode(p, out)?;
+                    Ok(&out[..sz])
+                }
+                None => Ok(&out[..0]),
+            }
+        }
+    }
+
+    #[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "defmt-format", derive(defmt::Format))]
+    pub struct Illumination {
+        pub value: f64,
     }
 }
 
@@ -306,6 +2063,26 @@ mod tests {
         serde_json_core::from_slice::<res::Version>(r).unwrap();
     }
 
+    #[test]
+    fn test_version_semantics() {
+        let r = br##"{"version":"notecard-4.1.1.4015681","device":"dev:000000000000000","name":"Blues Wireless Notecard","sku":"NOTE-WBEX-500","board":"1.11","api":4,"body":{"org":"Blues Wireless","product":"Notecard","version":"notecard-4.1.1","ver_major":4,"ver_minor":1,"ver_patch":1,"ver_build":4015681,"built":"Dec  5 2022 12:54:58"}}"##;
+        let (v, _) = serde_json_core::from_slice::<res::Version>(r).unwrap();
+
+        assert_eq!(
+            v.firmware(),
+            res::FirmwareVersion {
+                major: 4,
+                minor: 1,
+                patch: 1,
+                build: 4015681
+            }
+        );
+        assert!(v.at_least(3, 4, 0));
+        assert!(v.at_least(4, 1, 1));
+        assert!(!v.at_least(4, 1, 2));
+        assert!(!v.at_least(5, 0, 0));
+    }
+
     #[test]
     fn test_card_wireless() {
         let r = br##"{"status":"{modem-on}","count":3,"net":{"iccid":"89011703278520607527","imsi":"310170852060752","imei":"864475044204278","modem":"BG95M3LAR02A03_01.006.01.006","band":"GSM 900","rat":"gsm","rssir":-77,"rssi":-77,"bars":3,"mcc":242,"mnc":1,"lac":11001,"cid":12313,"updated":1643923524}}"##;
@@ -321,6 +2098,71 @@ mod tests {
         serde_json_core::from_slice::<res::Wireless>(r).unwrap();
     }
 
+    #[test]
+    fn wireless_net() {
+        let r = br##"{"status":"{modem-on}","count":3,"net":{"iccid":"89011703278520607527","imsi":"310170852060752","imei":"864475044204278","modem":"BG95M3LAR02A03_01.006.01.006","band":"GSM 900","rat":"gsm","rssir":-77,"rssi":-77,"bars":3,"mcc":242,"mnc":1,"lac":11001,"cid":12313,"updated":1643923524}}"##;
+        let (w, _) = serde_json_core::from_slice::<res::Wireless>(r).unwrap();
+        let net = w.net.unwrap();
+        assert_eq!(net.iccid.as_deref(), Some("89011703278520607527"));
+        assert_eq!(net.rat_kind(), Some(res::Rat::Gsm));
+        assert_eq!(net.signal_dbm(), Some(-77));
+        assert_eq!(net.signal_bars(), Some(3));
+
+        let r = br##"{"status":"{modem-off}","net":{}}"##;
+        let (w, _) = serde_json_core::from_slice::<res::Wireless>(r).unwrap();
+        let net = w.net.unwrap();
+        assert_eq!(net.rat_kind(), None);
+        assert_eq!(net.signal_dbm(), None);
+
+        let r = br##"{"status":"{network-up}","net":{"rat":"catm","rssi":-70,"rsrp":-95,"bars":5}}"##;
+        let (w, _) = serde_json_core::from_slice::<res::Wireless>(r).unwrap();
+        let net = w.net.unwrap();
+        assert_eq!(net.rat_kind(), Some(res::Rat::LteM));
+        assert_eq!(net.signal_dbm(), Some(-95));
+        assert_eq!(net.signal_bars(), Some(4));
+    }
+
+    #[test]
+    fn reserialize() {
+        let r = br##"{"status":"{network-up}","mode":"auto","count":3,"net":{"iccid":"89011703278520578660","rat":"gsm","rssi":-78,"bars":3,"updated":1646227929}}"##;
+        let (w, _) = serde_json_core::from_slice::<res::Wireless>(r).unwrap();
+
+        let s = serde_json_core::to_string::<_, 1024>(&w).unwrap();
+        let (w2, _) = serde_json_core::from_str::<res::Wireless>(&s).unwrap();
+        assert_eq!(w, w2);
+    }
+
+    #[test]
+    fn signal_quality() {
+        let q = res::SignalQuality::from_dbm(res::Rat::Lte, -100);
+        assert_eq!(q.bars, 2);
+        assert_eq!(res::SignalQuality::from_dbm(res::Rat::Gsm, -120).bars, 0);
+        assert_eq!(res::SignalQuality::from_dbm(res::Rat::Wifi, -40).bars, 4);
+
+        let r = br##"{"status":"{network-up}","net":{"rat":"lte","rsrp":-90,"bars":2}}"##;
+        let (w, _) = serde_json_core::from_slice::<res::Wireless>(r).unwrap();
+        let q = w.signal_quality().unwrap();
+        assert_eq!((q.bars, q.dbm, q.rat), (2, Some(-90), res::Rat::Lte));
+
+        let r = br##"{"status":"{network-up}","net":{"rat":"nbiot","rsrp":-90}}"##;
+        let (w, _) = serde_json_core::from_slice::<res::Wireless>(r).unwrap();
+        assert_eq!(w.signal_quality().unwrap().bars, 3);
+
+        let r = br##"{"status":"{modem-off}","net":{}}"##;
+        let (w, _) = serde_json_core::from_slice::<res::Wireless>(r).unwrap();
+        assert_eq!(w.signal_quality(), None);
+
+        let r = br##"{"ssid":"home","rssi":-70}"##;
+        let (wifi, _) = serde_json_core::from_slice::<res::Wifi>(r).unwrap();
+        let q = res::SignalQuality::from_wifi(&wifi).unwrap();
+        assert_eq!((q.bars, q.rat), (2, res::Rat::Wifi));
+
+        let r = br##"{"status":"{network-up}","mode":"auto","count":3,"net":{"iccid":"89011703278520578660","imsi":"310170852057866","imei":"867730051260788","modem":"BG95M3LAR02A03_01.006.01.006","band":"GSM 900","rat":"gsm","rssir":-77,"rssi":-78,"bars":3,"mcc":242,"mnc":1,"lac":11,"cid":12286,"updated":1646227929}}"##;
+        let (w, _) = serde_json_core::from_slice::<res::Wireless>(r).unwrap();
+        let q = w.signal_quality().unwrap();
+        assert_eq!((q.bars, q.dbm, q.rat), (3, Some(-78), res::Rat::Gsm));
+    }
+
     #[test]
     fn test_card_time_ok() {
         let r = br##"
@@ -389,6 +2231,88 @@ mod tests {
         .unwrap();
     }
 
+    #[test]
+    fn status_clone_eq() {
+        let (s, _) = serde_json_core::from_str::<res::Status>(
+            r#"{"status":"{normal}","usb":true,"storage":8}"#,
+        )
+        .unwrap();
+
+        let mut c = s.clone();
+        assert_eq!(s, c);
+
+        c.storage = 9;
+        assert_ne!(s, c);
+        assert_eq!(
+            std::format!("{:?}", s),
+            r#"Status { status: "{normal}", usb: true, storage: 8, time: None, connected: false }"#
+        );
+    }
+
+    #[test]
+    fn status_flags() {
+        let flags = res::StatusFlags::parse("{normal}");
+        assert!(flags.normal && !flags.recovery && !flags.usb);
+        assert!(flags.unknown.is_empty());
+
+        let flags = res::StatusFlags::parse("{recovery}{usb}{new-token} {other}");
+        assert!(!flags.normal && flags.recovery && flags.usb);
+        assert_eq!(flags.unknown, ["new-token", "other"]);
+
+        let (s, _) = serde_json_core::from_str::<res::Status>(
+            r#"{"status":"{normal}{usb}","usb":true,"storage":8}"#,
+        )
+        .unwrap();
+        assert!(s.flags().normal && s.flags().usb);
+
+        assert_eq!(tokens("no tokens {unterminated").count(), 0);
+    }
+
+    #[test]
+    fn gps_status() {
+        let gps = res::GpsStatus::parse(
+            "GPS updated (58 sec, 41dB SNR, 9 sats) {gps-active} {gps-signal} {gps-sats} {gps}",
+        );
+        assert_eq!(
+            gps,
+            res::GpsStatus {
+                seconds: Some(58),
+                snr: Some(41),
+                sats_used: Some(9),
+                sats_seen: None,
+                active: true,
+                inactive: false,
+                signal: true,
+                sats: true,
+                fix: true,
+            }
+        );
+
+        let gps = res::GpsStatus::parse(
+            "GPS search (111 sec, 32/33 dB SNR, 0/1 sats) {gps-active} {gps-signal} {gps-sats}",
+        );
+        assert_eq!(gps.seconds, Some(111));
+        assert_eq!(gps.snr, Some(32));
+        assert_eq!((gps.sats_used, gps.sats_seen), (Some(0), Some(1)));
+        assert!(gps.active && !gps.fix);
+
+        let gps = res::GpsStatus::parse("GPS inactive {gps-inactive}");
+        assert_eq!(
+            gps,
+            res::GpsStatus {
+                inactive: true,
+                ..Default::default()
+            }
+        );
+
+        let (l, _) = serde_json_core::from_str::<res::Location>(
+            r#"{"status":"GPS search (3 sec) {gps-active}","mode":"continuous"}"#,
+        )
+        .unwrap();
+        assert_eq!(l.gps_status().seconds, Some(3));
+        assert!(l.gps_status().active);
+    }
+
     #[test]
     fn test_partial_location_mode() {
         serde_json_core::from_str::<res::LocationMode>(r#"{"seconds":60,"mode":"periodic"}"#)
@@ -400,15 +2324,598 @@ mod tests {
         serde_json_core::from_str::<res::LocationMode>(r#"{"seconds":60,"mode":"periodicperiodicperiodicperiodicperiodicperiodicperiodic"}"#).ok();
     }
 
+    #[test]
+    fn transport_method() {
+        let (r, _) = serde_json_core::from_str::<res::Transport>(r#"{"method":"dual-wifi-cell"}"#)
+            .unwrap();
+        assert_eq!(r.method, Some(req::TransportMethod::DualWifiCell));
+
+        // Methods added in later firmware.
+        let (r, _) = serde_json_core::from_str::<res::Transport>(r#"{"method":"lora"}"#).unwrap();
+        assert_eq!(r.method, Some(req::TransportMethod::Unknown));
+    }
+
+    #[test]
+    fn location_mode_enum() {
+        let (r, _) =
+            serde_json_core::from_str::<res::LocationMode>(r#"{"mode":"fixed","lat":1.0,"lon":2.0}"#)
+                .unwrap();
+        assert!(matches!(r.mode, LocationMode::Fixed));
+
+        // Modes added in later firmware.
+        let (r, _) =
+            serde_json_core::from_str::<res::LocationMode>(r#"{"mode":"interval","seconds":60}"#)
+                .unwrap();
+        assert_eq!(r.mode, LocationMode::Unknown);
+        assert_eq!(r.seconds, Some(60));
+
+        assert_eq!(
+            &serde_json_core::to_string::<_, 64>(&req::LocationMode {
+                req: "card.location.mode",
+                mode: Some(LocationMode::Continuous),
+                ..Default::default()
+            })
+            .unwrap(),
+            r#"{"req":"card.location.mode","mode":"continuous"}"#
+        );
+    }
+
     #[test]
     fn test_location_searching() {
         serde_json_core::from_str::<res::Location>(
             r#"{"status":"GPS search (111 sec, 32/33 dB SNR, 0/1 sats) {gps-active} {gps-signal} {gps-sats}","mode":"continuous"}"#).unwrap();
     }
 
+    #[test]
+    fn test_attn_sleep() {
+        let mut b64 = [0u8; 16];
+        let payload = payload::encode(&[1, 2, 3, 4], &mut b64).unwrap();
+
+        let r = req::Attn {
+            req: "card.attn",
+            mode: Some("sleep"),
+            seconds: Some(3600),
+            payload: Some(payload),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            &serde_json_core::to_string::<_, 256>(&r).unwrap(),
+            r#"{"req":"card.attn","mode":"sleep","seconds":3600,"payload":"AQIDBA=="}"#
+        );
+
+        let (r, _) = serde_json_core::from_str::<res::Attn>(r#"{"payload":"AQIDBA==","time":1599769214}"#).unwrap();
+        let mut out = [0u8; 16];
+        assert_eq!(r.decode_payload(&mut out).unwrap(), &[1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_attn_files() {
+        let r = req::Attn {
+            req: "card.attn",
+            mode: Some("arm,files"),
+            files: Some(&["data.qi", "my-settings.db"]),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            &serde_json_core::to_string::<_, 256>(&r).unwrap(),
+            r#"{"req":"card.attn","mode":"arm,files","files":["data.qi","my-settings.db"]}"#
+        );
+
+        serde_json_core::from_str::<res::Attn>(r#"{"files":["data.qi","modified"],"set":true}"#).unwrap();
+    }
+
     #[test]
     fn test_location_mode_err() {
         let r = br##"{"err":"seconds: field seconds: unmarshal: expected a int32 {io}"}"##;
         serde_json_core::from_slice::<NotecardError>(r).unwrap();
     }
+
+    #[test]
+    fn test_aux_gpio() {
+        let (r, _) = serde_json_core::from_str::<res::Aux>(
+            r#"{"mode":"gpio","state":[{},{"low":true},{"high":true},{"count":[3]}]}"#,
+        )
+        .unwrap();
+
+        let state = r.state.unwrap();
+        assert_eq!(state[0].is_high(), None);
+        assert_eq!(state[1].is_high(), Some(false));
+        assert_eq!(state[2].is_high(), Some(true));
+        assert_eq!(&state[3].count.as_ref().unwrap()[..], &[3]);
+    }
+
+    #[test]
+    fn test_voltage_thresholds() {
+        let t = req::VoltageThresholds::new()
+            .usb(4.6)
+            .and_then(|t| t.high(4.0))
+            .and_then(|t| t.normal(3.5))
+            .and_then(|t| t.low(3.0))
+            .and_then(|t| t.dead(0.0))
+            .unwrap();
+
+        let r = req::Voltage {
+            req: "card.voltage",
+            mode: Some(t.mode()),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            &serde_json_core::to_string::<_, 256>(&r).unwrap(),
+            r#"{"req":"card.voltage","mode":"usb:4.6;high:4;normal:3.5;low:3;dead:0"}"#
+        );
+    }
+
+    #[test]
+    fn test_binary_put_md5() {
+        assert_eq!(&md5_hex(b"hello notecard"), "3e2fdb1266b971f5c8963edd370ebd90");
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn wait_for_time() {
+        use crate::mock::{MockTransport, NoDelay};
+
+        let mut note: Notecard<_> = MockTransport::new()
+            .expect_req("card.time", r#"{"err":"time is not yet set","zone":"UTC,Unknown"}"#)
+            .expect_req("card.time", r#"{"time":1599769214,"zone":"UTC,Unknown"}"#)
+            .expect_req("card.time", vector!("card.time.res.json").trim())
+            .expect_req("card.time", r#"{"err":"time is not yet set","zone":"UTC,Unknown"}"#)
+            .expect_req("card.time", r#"{"err":"time is not yet set","zone":"UTC,Unknown"}"#)
+            .notecard();
+        let d = &mut NoDelay;
+
+        let t = note.card().wait_for_time(d, 5000).unwrap();
+        assert_eq!(t.valid_time(), Some(1599769214));
+
+        assert!(matches!(
+            note.card().wait_for_time(d, 1000),
+            Err(NoteError::TimeOut { .. })
+        ));
+
+        note.transport().done();
+
+        // Errors that retrying does not fix are returned straight away.
+        let mut note: Notecard<_> = MockTransport::new()
+            .expect_req("card.time", r#"{"err":"card.time: i2c timeout {io}"}"#)
+            .expect_req("card.time", r#"{"err":"request not supported {not-supported}"}"#)
+            .notecard();
+
+        match note.card().wait_for_time(d, 5000) {
+            Err(NoteError::NotecardErr(e)) => {
+                assert_eq!(e.kind(), crate::NotecardErrorKind::NotSupported)
+            }
+            r => panic!("unexpected: {:?}", r.map(|t| t.time)),
+        }
+
+        note.transport().done();
+    }
+
+    #[test]
+    fn time_local() {
+        let (t, _) = serde_json_core::from_str::<res::Time>(vector!("card.time.res.json").trim()).unwrap();
+        assert!(t.is_valid());
+        assert_eq!(t.local_time(), Some(1599769214 - 300 * 60));
+
+        let (t, _) =
+            serde_json_core::from_str::<res::Time>(r#"{"time":1599769214,"zone":"UTC,Unknown"}"#)
+                .unwrap();
+        assert!(!t.is_valid());
+        assert!(t.is_set());
+        assert_eq!(t.valid_time(), None);
+        assert_eq!(t.local_time(), Some(1599769214));
+
+        // After 2106.
+        let (t, _) =
+            serde_json_core::from_str::<res::Time>(r#"{"time":4354819200,"minutes":60}"#).unwrap();
+        assert_eq!(t.local_time(), Some(4354819200 + 3600));
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn time_chrono() {
+        let (t, _) = serde_json_core::from_str::<res::Time>(vector!("card.time.res.json").trim()).unwrap();
+        let d = t.to_chrono().unwrap();
+        assert_eq!(d.timestamp(), 1599769214);
+        assert_eq!(d.offset().local_minus_utc(), -300 * 60);
+    }
+
+    #[cfg(feature = "time")]
+    #[test]
+    fn time_time() {
+        let (t, _) = serde_json_core::from_str::<res::Time>(vector!("card.time.res.json").trim()).unwrap();
+        let d = t.to_offset_date_time().unwrap();
+        assert_eq!(d.unix_timestamp(), 1599769214);
+        assert_eq!(d.offset().whole_minutes(), -300);
+    }
+
+    #[cfg(all(feature = "chrono", feature = "time"))]
+    #[test]
+    fn status_time() {
+        let (s, _) = serde_json_core::from_str::<res::Status>(vector!("card.status.res.json").trim()).unwrap();
+        assert_eq!(s.to_chrono().unwrap().timestamp(), 1599684765);
+        assert_eq!(s.to_offset_date_time().unwrap().unix_timestamp(), 1599684765);
+
+        let (l, _) = serde_json_core::from_str::<res::Location>(r#"{"status":"","mode":"off"}"#).unwrap();
+        assert_eq!(l.to_chrono(), None);
+        assert_eq!(l.to_offset_date_time(), None);
+    }
+    mod vectors {
+        use super::*;
+
+        vector_res!(time, "card.time.res.json", res::Time);
+        vector_res!(status, "card.status.res.json", res::Status);
+        vector_res!(restart, "card.restart.res.json", res::Empty);
+        vector_res!(location, "card.location.res.json", res::Location);
+        vector_res!(location_mode, "card.location.mode.res.json", res::LocationMode);
+        vector_res!(location_track, "card.location.track.res.json", res::LocationTrack);
+        vector_res!(wireless, "card.wireless.res.json", res::Wireless);
+        vector_res!(attn, "card.attn.res.json", res::Attn);
+        vector_res!(version, "card.version.res.json", res::Version);
+
+        vector_req!(
+            location_mode_req,
+            "card.location.mode.req.json",
+            req::LocationMode {
+                req: "card.location.mode",
+                mode: Some(LocationMode::Periodic),
+                seconds: Some(3600),
+                ..Default::default()
+            }
+        );
+
+        vector_req!(
+            location_track_req,
+            "card.location.track.req.json",
+            req::LocationTrack {
+                req: "card.location.track",
+                start: Some(true),
+                heartbeat: Some(true),
+                hours: Some(2),
+                ..Default::default()
+            }
+        );
+
+        vector_req!(
+            attn_req,
+            "card.attn.req.json",
+            req::Attn {
+                req: "card.attn",
+                mode: Some("arm,files"),
+                files: Some(&["data.qi", "my-settings.db"]),
+                ..Default::default()
+            }
+        );
+
+        vector_res!(aux_gpio, "card.aux.gpio.res.json", res::Aux);
+
+        vector_req!(
+            aux_gpio_req,
+            "card.aux.gpio.req.json",
+            req::Aux {
+                req: "card.aux",
+                mode: Some(req::AuxMode::Gpio),
+                usage: Some([
+                    req::AuxGpioUsage::Off,
+                    req::AuxGpioUsage::Low,
+                    req::AuxGpioUsage::High,
+                    req::AuxGpioUsage::CountPulldown,
+                ]),
+                ..Default::default()
+            }
+        );
+
+        vector_res!(aux_serial, "card.aux.serial.res.json", res::AuxSerial);
+
+        vector_req!(
+            aux_serial_req,
+            "card.aux.serial.req.json",
+            req::AuxSerial {
+                req: "card.aux.serial",
+                mode: Some("notify,accel"),
+                duration: Some(500),
+                rate: Some(115200),
+                ..Default::default()
+            }
+        );
+
+        vector_res!(aux_neo_monitor, "card.aux.neo-monitor.res.json", res::Aux);
+
+        vector_req!(
+            aux_neo_monitor_req,
+            "card.aux.neo-monitor.req.json",
+            req::Aux {
+                req: "card.aux",
+                mode: Some(req::AuxMode::NeoMonitor),
+                count: Some(5),
+                offset: Some(2),
+                ..Default::default()
+            }
+        );
+
+        vector_req!(
+            aux_off_req,
+            "card.aux.off.req.json",
+            req::Aux {
+                req: "card.aux",
+                mode: Some(req::AuxMode::Off),
+                ..Default::default()
+            }
+        );
+
+        vector_res!(voltage, "card.voltage.res.json", res::Voltage);
+
+        vector_req!(
+            voltage_req,
+            "card.voltage.req.json",
+            req::Voltage {
+                req: "card.voltage",
+                hours: Some(120),
+                offset: Some(24),
+                ..Default::default()
+            }
+        );
+
+        vector_req!(
+            voltage_mode_req,
+            "card.voltage.mode.req.json",
+            req::Voltage {
+                req: "card.voltage",
+                mode: Some(req::VoltageMode::Lipo),
+                ..Default::default()
+            }
+        );
+
+        vector_res!(temp, "card.temp.res.json", res::Temp);
+
+        vector_req!(
+            temp_req,
+            "card.temp.req.json",
+            req::Temp {
+                req: "card.temp",
+                minutes: Some(60),
+            }
+        );
+
+        vector_res!(dfu, "card.dfu.res.json", res::Dfu);
+
+        vector_req!(
+            dfu_req,
+            "card.dfu.req.json",
+            req::Dfu {
+                req: "card.dfu",
+                name: Some(req::DfuName::Stm32),
+                on: Some(true),
+                ..Default::default()
+            }
+        );
+
+        vector_res!(usage_test, "card.usage.test.res.json", res::UsageTest);
+
+        vector_req!(
+            usage_test_req,
+            "card.usage.test.req.json",
+            req::UsageTest {
+                req: "card.usage.test",
+                days: Some(7),
+                megabytes: Some(500),
+                ..Default::default()
+            }
+        );
+
+        vector_req!(
+            io_req,
+            "card.io.req.json",
+            req::Io {
+                req: "card.io",
+                i2c: Some(0x18),
+                ..Default::default()
+            }
+        );
+
+        vector_req!(
+            led_req,
+            "card.led.req.json",
+            req::Led {
+                req: "card.led",
+                mode: req::LedColor::Red,
+                on: Some(true),
+                off: None,
+            }
+        );
+
+        vector_res!(wifi, "card.wifi.res.json", res::Wifi);
+
+        vector_req!(
+            wifi_req,
+            "card.wifi.req.json",
+            req::Wifi {
+                req: "card.wifi",
+                ssid: Some("<ssid name>"),
+                password: Some("<password>"),
+                ..Default::default()
+            }
+        );
+
+        vector_res!(transport, "card.transport.res.json", res::Transport);
+
+        vector_req!(
+            transport_req,
+            "card.transport.req.json",
+            req::Transport {
+                req: "card.transport",
+                method: Some(req::TransportMethod::WifiCellNtn),
+                allow: Some(true),
+            }
+        );
+
+        vector_res!(binary, "card.binary.res.json", res::Binary);
+
+        vector_req!(
+            binary_req,
+            "card.binary.req.json",
+            req::Binary {
+                req: "card.binary",
+                delete: Some(true),
+            }
+        );
+
+        vector_req!(
+            binary_put_req,
+            "card.binary.put.req.json",
+            req::BinaryPut {
+                req: "card.binary.put",
+                cobs: 5,
+                offset: None,
+                status: "ce6fdef565eeecf14ab38d83643b922d",
+            }
+        );
+
+        vector_res!(random, "card.random.res.json", res::Random);
+        vector_res!(random_payload, "card.random.payload.res.json", res::Random);
+
+        vector_req!(
+            random_req,
+            "card.random.req.json",
+            req::Random {
+                req: "card.random",
+                count: Some(100),
+                mode: None,
+            }
+        );
+
+        vector_req!(
+            random_payload_req,
+            "card.random.payload.req.json",
+            req::Random {
+                req: "card.random",
+                count: Some(10),
+                mode: Some("payload"),
+            }
+        );
+
+        vector_res!(illumination, "card.illumination.res.json", res::Illumination);
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn location_track_file() {
+        use crate::mock::{MockTransport, NoDelay};
+
+        // Any name that is a valid notefile name fits in the request.
+        let file = "a".repeat(crate::NOTEFILE_NAME_LEN - 3) + ".qo";
+        let mut note: Notecard<_, 1024> = MockTransport::new()
+            .expect(
+                &format!(r#"{{"req":"card.location.track","start":true,"file":"{}"}}"#, file),
+                "{}",
+            )
+            .notecard();
+
+        note.card()
+            .location_track(&mut NoDelay, true, false, false, None, Some(&file), None)
+            .unwrap()
+            .wait(&mut NoDelay)
+            .unwrap();
+
+        note.transport().done();
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn transport_unknown_method() {
+        use crate::mock::{MockTransport, NoDelay};
+
+        let mut note: Notecard<_, 1024> = MockTransport::new().notecard();
+
+        assert!(matches!(
+            note.card().transport(&mut NoDelay, Some(req::TransportMethod::Unknown), None),
+            Err(NoteError::InvalidArgument)
+        ));
+
+        note.transport().done();
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn io_default_address() {
+        use crate::mock::{MockTransport, NoDelay};
+
+        let mut note: Notecard<_, 1024> = MockTransport::new()
+            .expect(r#"{"req":"card.io","i2c":-1}"#, "{}")
+            .notecard();
+
+        note.card().io(&mut NoDelay, Some(-1), None).unwrap();
+        assert!(matches!(
+            note.card().io(&mut NoDelay, Some(0x80), None),
+            Err(NoteError::InvalidArgument)
+        ));
+
+        note.transport().done();
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn location_mode_builder() {
+        use crate::mock::{MockTransport, NoDelay};
+
+        let mut note: Notecard<_, 1024> = MockTransport::new()
+            .expect(
+                std::fs::read_to_string("tests/vectors/card.location.mode.req.json")
+                    .unwrap()
+                    .trim(),
+                r#"{"mode":"periodic","seconds":3600}"#,
+            )
+            .expect(
+                r#"{"req":"card.location.mode","mode":"fixed","lat":60.5,"lon":5.25}"#,
+                r#"{"mode":"fixed","lat":60.5,"lon":5.25}"#,
+            )
+            .expect(
+                r#"{"req":"card.location.mode","mode":"fixed","lat":59.913868,"lon":-10.752245}"#,
+                r#"{"mode":"fixed","lat":59.913868,"lon":-10.752245}"#,
+            )
+            .notecard();
+
+        let r = note
+            .card()
+            .location_mode_builder()
+            .mode(LocationMode::Periodic)
+            .seconds(3600)
+            .send(&mut NoDelay)
+            .unwrap()
+            .wait(&mut NoDelay)
+            .unwrap();
+        assert!(matches!(r.mode, LocationMode::Periodic));
+
+        note.card()
+            .location_mode_builder()
+            .fixed(60.5, 5.25)
+            .send(&mut NoDelay)
+            .unwrap()
+            .wait(&mut NoDelay)
+            .unwrap();
+
+        // Full precision, which an `f32` does not have.
+        let r = note
+            .card()
+            .location_mode_builder()
+            .mode(LocationMode::Fixed)
+            .position_e6(59913868, -10752245)
+            .send(&mut NoDelay)
+            .unwrap()
+            .wait(&mut NoDelay)
+            .unwrap();
+        assert_eq!(r.position_e6(), Some((59913868, -10752245)));
+
+        assert!(matches!(
+            note.card().location_mode_builder().vseconds("usb:3600;normal:7200;low:86400"),
+            Err(NoteError::ArgumentTooLong { len: 30, capacity: 20 })
+        ));
+
+        assert!(matches!(
+            note.card().location_mode_builder().mode(LocationMode::Unknown).send(&mut NoDelay),
+            Err(NoteError::InvalidArgument)
+        ));
+    }
 }
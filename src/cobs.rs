@@ -0,0 +1,138 @@
+//! COBS encoding of binary data sent with `card.binary.put`.
+//!
+//! The encoded data is XOR'ed with the end-of-packet byte (`\n`) so that the encoded data never
+//! contains it, as in `note-c`: <https://github.com/blues/note-c/blob/master/n_cobs.c>.
+
+use super::NoteError;
+
+/// End-of-packet byte used by the Notecard.
+pub const EOP: u8 = b'\n';
+
+/// Upper bound on the encoded length of `len` bytes.
+pub const fn max_encoded_len(len: usize) -> usize {
+    len + len / 254 + 1
+}
+
+/// Exact encoded length of `data`.
+pub fn encoded_len(data: &[u8]) -> usize {
+    let mut len = 1;
+    let mut code = 1u8;
+
+    for &c in data {
+        if c != 0 {
+            len += 1;
+            code += 1;
+        }
+
+        if c == 0 || code == 0xff {
+            len += 1;
+            code = 1;
+        }
+    }
+
+    len
+}
+
+/// Encode `data` into `out`, returning the number of bytes written.
+pub fn encode(data: &[u8], eop: u8, out: &mut [u8]) -> Result<usize, NoteError> {
+    if out.len() < encoded_len(data) {
+        return Err(NoteError::BufOverflow);
+    }
+
+    let mut code = 1u8;
+    let mut code_ptr = 0;
+    let mut dst = 1;
+
+    for &c in data {
+        if c != 0 {
+            out[dst] = c ^ eop;
+            dst += 1;
+            code += 1;
+        }
+
+        if c == 0 || code == 0xff {
+            out[code_ptr] = code ^ eop;
+            code = 1;
+            code_ptr = dst;
+            dst += 1;
+        }
+    }
+
+    out[code_ptr] = code ^ eop;
+
+    Ok(dst)
+}
+
+/// Decode `data` into `out`, returning the number of bytes written.
+pub fn decode(data: &[u8], eop: u8, out: &mut [u8]) -> Result<usize, NoteError> {
+    let mut dst = 0;
+    let mut code = 0xffu8;
+    let mut copy = 0u8;
+    let mut data = data.iter();
+
+    loop {
+        if copy != 0 {
+            let c = match data.next() {
+                Some(c) => c,
+                None => break,
+            };
+            *out.get_mut(dst).ok_or(NoteError::BufOverflow)? = c ^ eop;
+            dst += 1;
+        } else {
+            let c = match data.next() {
+                Some(c) => c,
+                None => break,
+            };
+
+            if code != 0xff {
+                *out.get_mut(dst).ok_or(NoteError::BufOverflow)? = 0;
+                dst += 1;
+            }
+
+            code = c ^ eop;
+            copy = code;
+
+            if code == 0 {
+                break;
+            }
+        }
+
+        copy = copy.wrapping_sub(1);
+    }
+
+    Ok(dst)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(data: &[u8]) {
+        let mut enc = [0u8; 1024];
+        let sz = encode(data, EOP, &mut enc).unwrap();
+        assert_eq!(sz, encoded_len(data));
+        assert!(sz <= max_encoded_len(data.len()));
+        assert!(!enc[..sz].contains(&EOP));
+
+        let mut dec = [0u8; 1024];
+        let dsz = decode(&enc[..sz], EOP, &mut dec).unwrap();
+        assert_eq!(&dec[..dsz], data);
+    }
+
+    #[test]
+    fn encode_simple() {
+        let mut enc = [0u8; 8];
+        let sz = encode(&[0x11, 0x22, 0x00, 0x33], 0, &mut enc).unwrap();
+        assert_eq!(&enc[..sz], &[0x03, 0x11, 0x22, 0x02, 0x33]);
+    }
+
+    #[test]
+    fn round_trips() {
+        round_trip(&[]);
+        round_trip(&[0]);
+        round_trip(&[0, 0]);
+        round_trip(b"hello\nnotecard\n");
+        round_trip(&(0..=255).collect::<Vec<u8>>());
+        round_trip(&[1u8; 600]);
+    }
+}
@@ -0,0 +1,366 @@
+//! Request and response integrity checks, as in `note-c`.
+//!
+//! A `crc` field with a sequence number and the CRC32 of the request is added as the last field
+//! of the request: `{"req":"card.time","crc":"0001:6F1A0B6D"}`. Notecard firmware that supports
+//! it responds with the same sequence number and the CRC32 of the response. A request that is
+//! corrupted on the way to the Notecard fails with an `{io}` error. When a response is corrupted
+//! the driver retransmits the request with a new sequence number while waiting for the response,
+//! up to [`RESENDS`] times, before failing with [`NoteError::CrcMismatch`]. The request is kept in
+//! the buffer for this, so it is not retransmitted if the response needs the whole buffer, if it
+//! is followed by binary data or a streamed payload, or if the response is polled without a delay
+//! (e.g. [`FutureResponse::poll`](crate::FutureResponse::poll)). Both errors are
+//! [transient](NoteError::is_transient) and can be retried with
+//! [`Notecard::retry`](crate::Notecard::retry).
+//!
+//! > `note-c`: https://github.com/blues/note-c/blob/master/n_request.c (`crcAdd`, `crcError`)
+
+#[allow(unused_imports)]
+use crate::fmt::{debug, error, info, trace, warn};
+use crate::buffer::Buffer;
+use crate::NoteError;
+
+const CRC_FIELD: &[u8] = br#""crc":""#;
+
+/// Number of times a request is retransmitted after its response failed the check.
+pub const RESENDS: u8 = 3;
+
+/// Length of `SSSS:CCCCCCCC`.
+const CRC_VALUE_LENGTH: usize = 13;
+
+fn hex(v: u32, digits: usize, out: &mut [u8]) {
+    const HEX: &[u8] = b"0123456789ABCDEF";
+
+    for (i, c) in out[..digits].iter_mut().enumerate() {
+        *c = HEX[(v >> (4 * (digits - 1 - i)) & 0xf) as usize];
+    }
+}
+
+fn parse_hex(s: &[u8]) -> Option<u32> {
+    u32::from_str_radix(core::str::from_utf8(s).ok()?, 16).ok()
+}
+
+/// Add the `crc` field to the request in `buf` (terminated by a new-line).
+pub(crate) fn add<const N: usize>(buf: &mut Buffer<N>, seq: u16) -> Result<(), NoteError> {
+    if !buf.ends_with(b"}\n") {
+        return Err(NoteError::InvalidRequest);
+    }
+
+    // The closing brace and new-line are moved after the field.
+    let len = buf.len();
+    let size = len + CRC_FIELD.len() + CRC_VALUE_LENGTH + 1 + usize::from(len > 3);
+    if size > buf.capacity() {
+        return Err(NoteError::RequestTooLarge(size));
+    }
+
+    // Without the new-line, and then without the closing brace.
+    let crc = crc32fast::hash(&buf[..len - 1]);
+    buf.truncate(len - 2);
+
+    if buf.len() > 1 {
+        buf.push(b',').map_err(|_| NoteError::BufOverflow)?;
+    }
+    buf.extend_from_slice(&field(seq, crc))
+        .and_then(|_| buf.extend_from_slice(b"}\n"))
+        .map_err(|_| NoteError::BufOverflow)
+}
+
+/// Length of `"crc":"SSSS:CCCCCCCC"`.
+pub(crate) const FIELD_LENGTH: usize = CRC_FIELD.len() + CRC_VALUE_LENGTH + 1;
+
+/// The `crc` field of a request with sequence number `seq`, `crc` is the CRC32 of the request
+/// without the field.
+pub(crate) fn field(seq: u16, crc: u32) -> [u8; FIELD_LENGTH] {
+    let mut field = [0u8; FIELD_LENGTH];
+    field[..CRC_FIELD.len()].copy_from_slice(CRC_FIELD);
+
+    let value = &mut field[CRC_FIELD.len()..];
+    hex(seq as u32, 4, &mut value[..4]);
+    value[4] = b':';
+    hex(crc, 8, &mut value[5..CRC_VALUE_LENGTH]);
+    value[CRC_VALUE_LENGTH] = b'"';
+
+    field
+}
+
+/// Replace the sequence number in the `crc` field of the request in `buf`. The CRC32 does not
+/// cover the field, so it stays the same.
+pub(crate) fn renumber(buf: &mut [u8], seq: u16) -> Result<(), NoteError> {
+    let start = buf
+        .windows(CRC_FIELD.len())
+        .rposition(|w| w == CRC_FIELD)
+        .ok_or(NoteError::InvalidRequest)?
+        + CRC_FIELD.len();
+    let digits = buf
+        .get_mut(start..start + 4)
+        .ok_or(NoteError::InvalidRequest)?;
+    hex(seq as u32, 4, digits);
+
+    Ok(())
+}
+
+/// The error for a response that does not match its `crc`, the driver adds the request name.
+fn mismatch() -> NoteError {
+    NoteError::CrcMismatch {
+        req: heapless::String::new(),
+    }
+}
+
+/// Check the `crc` field of a response, if the Notecard added one.
+pub(crate) fn check(body: &[u8], seq: u16) -> Result<(), NoteError> {
+    verify(crc32fast::Hasher::new(), body, seq)
+}
+
+/// Check the `crc` field at the end of `json`, `hasher` has been updated with the part of the
+/// response preceding `json`.
+fn verify(mut hasher: crc32fast::Hasher, json: &[u8], seq: u16) -> Result<(), NoteError> {
+    let end = json
+        .iter()
+        .rposition(|c| !c.is_ascii_whitespace())
+        .map_or(0, |p| p + 1);
+    let json = &json[..end];
+
+    let start = match json
+        .windows(CRC_FIELD.len())
+        .rposition(|w| w == CRC_FIELD)
+    {
+        Some(start) => start,
+        None => return Ok(()),
+    };
+
+    let value = json
+        .get(start + CRC_FIELD.len()..start + CRC_FIELD.len() + CRC_VALUE_LENGTH)
+        .ok_or_else(mismatch)?;
+
+    let rseq = parse_hex(&value[..4]).ok_or_else(mismatch)?;
+    let rcrc = parse_hex(&value[5..]).ok_or_else(mismatch)?;
+
+    // The CRC is of the response without the `crc` field.
+    let head = &json[..start];
+    let head = head.strip_suffix(b",").unwrap_or(head);
+
+    hasher.update(head);
+    hasher.update(b"}");
+    let crc = hasher.finalize();
+
+    if rseq != seq as u32 || rcrc != crc {
+        error!(
+            "crc: response does not match (seq: {} != {}, crc: {} != {})",
+            rseq, seq, rcrc, crc
+        );
+        return Err(mismatch());
+    }
+
+    Ok(())
+}
+
+/// Length of the end of a streamed response that is held back, enough for `,"crc":"SSSS:CCCCCCCC"}`
+/// followed by some white-space.
+const TAIL_LENGTH: usize = 64;
+
+/// [`check`] of a response that is read in chunks, without keeping the whole response. The end of
+/// the response is held back until [`Stream::finish`], since the `crc` field is the last field.
+pub(crate) struct Stream {
+    hasher: crc32fast::Hasher,
+    tail: [u8; TAIL_LENGTH],
+    len: usize,
+}
+
+impl Stream {
+    pub fn new() -> Stream {
+        Stream {
+            hasher: crc32fast::Hasher::new(),
+            tail: [0u8; TAIL_LENGTH],
+            len: 0,
+        }
+    }
+
+    pub fn update(&mut self, mut chunk: &[u8]) {
+        // Hash what no longer fits in the tail, oldest first.
+        let over = (self.len + chunk.len()).saturating_sub(TAIL_LENGTH);
+        if over > 0 {
+            let from_tail = over.min(self.len);
+            self.hasher.update(&self.tail[..from_tail]);
+            self.tail.copy_within(from_tail..self.len, 0);
+            self.len -= from_tail;
+
+            let (head, rest) = chunk.split_at(over - from_tail);
+            self.hasher.update(head);
+            chunk = rest;
+        }
+
+        self.tail[self.len..self.len + chunk.len()].copy_from_slice(chunk);
+        self.len += chunk.len();
+    }
+
+    pub fn finish(self, seq: u16) -> Result<(), NoteError> {
+        verify(self.hasher, &self.tail[..self.len], seq)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn add_crc() {
+        let mut buf = Buffer::<128>::from_slice(b"{\"req\":\"card.time\"}\n").unwrap();
+        add(&mut buf, 1).unwrap();
+
+        let crc = crc32fast::hash(b"{\"req\":\"card.time\"}");
+        let expected = format!("{{\"req\":\"card.time\",\"crc\":\"0001:{:08X}\"}}\n", crc);
+        assert_eq!(core::str::from_utf8(&buf).unwrap(), expected);
+
+        let mut buf = Buffer::<128>::from_slice(b"{}\n").unwrap();
+        add(&mut buf, 0xabc).unwrap();
+        assert!(buf.starts_with(b"{\"crc\":\"0ABC:"));
+
+        let mut buf = Buffer::<128>::from_slice(b"{\"req\":\"card.time\"}").unwrap();
+        assert!(matches!(add(&mut buf, 1), Err(NoteError::InvalidRequest)));
+
+        let mut buf = Buffer::<41>::from_slice(b"{\"req\":\"card.time\"}\n").unwrap();
+        assert!(matches!(add(&mut buf, 1), Err(NoteError::RequestTooLarge(42))));
+        assert_eq!(&buf[..], b"{\"req\":\"card.time\"}\n");
+
+        let mut buf = Buffer::<42>::from_slice(b"{\"req\":\"card.time\"}\n").unwrap();
+        add(&mut buf, 1).unwrap();
+        assert_eq!(buf.len(), 42);
+    }
+
+    #[test]
+    fn check_crc() {
+        let crc = crc32fast::hash(b"{\"time\":1599769214}");
+        let body = format!("{{\"time\":1599769214,\"crc\":\"0002:{:08X}\"}}\r\n", crc);
+
+        check(body.as_bytes(), 2).unwrap();
+        assert!(matches!(check(body.as_bytes(), 3), Err(NoteError::CrcMismatch { .. })));
+
+        let corrupt = body.replace("1599769214", "1599769215");
+        assert!(matches!(check(corrupt.as_bytes(), 2), Err(NoteError::CrcMismatch { .. })));
+
+        // Older firmware does not add a `crc`.
+        check(b"{\"time\":1599769214}\r\n", 2).unwrap();
+    }
+
+    #[test]
+    fn check_stream() {
+        let value = "x".repeat(300);
+        let crc = crc32fast::hash(format!("{{\"value\":\"{}\"}}", value).as_bytes());
+        let body = format!("{{\"value\":\"{}\",\"crc\":\"0002:{:08X}\"}}\r\n", value, crc);
+
+        for chunk in [1, 7, 64, 65, 128, 1024] {
+            let mut s = Stream::new();
+            body.as_bytes().chunks(chunk).for_each(|c| s.update(c));
+            s.finish(2).unwrap();
+
+            let mut s = Stream::new();
+            body.replace("xxx", "xxy")
+                .as_bytes()
+                .chunks(chunk)
+                .for_each(|c| s.update(c));
+            assert!(matches!(s.finish(2), Err(NoteError::CrcMismatch { .. })));
+        }
+
+        let mut s = Stream::new();
+        s.update(b"{\"time\":1599769214}\r\n");
+        s.finish(2).unwrap();
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn streamed_payload() {
+        use crate::mock::{MockTransport, NoDelay};
+        use crate::transport::record::Recorder;
+        use crate::{Notecard, NotecardConfig};
+
+        let capture: std::vec::Vec<u8> = (0..2000u32).map(|i| (i * 31) as u8).collect();
+        let mock = MockTransport::new()
+            .expect_req("note.add", "{}")
+            .expect_req("note.add", "{}");
+
+        // The payload is streamed through a buffer that is much smaller than the request.
+        let mut note: Notecard<_, 256> = Notecard::new_with_transport(
+            Recorder::new(mock, std::vec::Vec::new()),
+            NotecardConfig {
+                crc: true,
+                chunk_delay: 0,
+                segment_delay: 0,
+                ..NotecardConfig::default()
+            },
+        )
+        .initialize(&mut NoDelay)
+        .unwrap();
+
+        for data in [&capture[..], &[][..]] {
+            note.note()
+                .add_from::<()>(&mut NoDelay, Some("a.qo"), None, None, &mut &data[..], true)
+                .unwrap()
+                .wait(&mut NoDelay)
+                .unwrap();
+        }
+
+        let log = std::str::from_utf8(note.transport().sink()).unwrap();
+        let requests: std::vec::Vec<_> = log.lines().filter_map(|l| l.strip_prefix("> ")).collect();
+        assert_eq!(requests.len(), 2);
+
+        for (seq, r) in requests.into_iter().enumerate() {
+            // The same `crc` as if the whole request had been in the buffer.
+            let start = r.rfind(r#","crc":""#).unwrap();
+            let mut buf = Buffer::<4096>::from_slice(&r.as_bytes()[..start]).unwrap();
+            buf.extend_from_slice(b"}\n").unwrap();
+            add(&mut buf, seq as u16).unwrap();
+
+            assert_eq!(std::str::from_utf8(&buf).unwrap().trim_end(), r);
+        }
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn request_with_crc() {
+        use crate::mock::{MockTransport, NoDelay};
+
+        let crc = crc32fast::hash(b"{\"time\":1599769214}");
+        let good = format!("{{\"time\":1599769214,\"crc\":\"0000:{:08X}\"}}", crc);
+        let bad = format!("{{\"time\":1599769215,\"crc\":\"0001:{:08X}\"}}", crc);
+
+        let mut mock = MockTransport::new().expect_req("card.time", &good);
+        for _ in 0..=RESENDS {
+            mock = mock.expect_req("card.time", &bad);
+        }
+        let mut note: crate::Notecard<_, 1024> = mock.notecard();
+
+        let t = note.card().time(&mut NoDelay).unwrap().wait(&mut NoDelay).unwrap();
+        assert_eq!(t.time, Some(1599769214));
+
+        assert!(matches!(
+            note.card().time(&mut NoDelay).unwrap().wait(&mut NoDelay),
+            Err(NoteError::CrcMismatch { .. })
+        ));
+        assert_eq!(note.stats().retries, RESENDS as u32);
+        note.transport().done();
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn resend_corrupted() {
+        use crate::mock::{MockTransport, NoDelay};
+
+        let crc = crc32fast::hash(b"{\"time\":1599769214}");
+        let corrupted = format!("{{\"time\":1599769215,\"crc\":\"0000:{:08X}\"}}", crc);
+        let good = format!("{{\"time\":1599769214,\"crc\":\"0001:{:08X}\"}}", crc);
+
+        let mut note: crate::Notecard<_, 1024> = MockTransport::new()
+            .expect_req("card.time", &corrupted)
+            .expect_req("card.time", &good)
+            .notecard();
+
+        let t = note.card().time(&mut NoDelay).unwrap().wait(&mut NoDelay).unwrap();
+        assert_eq!(t.time, Some(1599769214));
+        assert_eq!(note.stats().retries, 1);
+        assert_eq!(note.stats().requests, 2);
+        note.transport().done();
+
+        let mut buf = *b"{\"req\":\"card.time\",\"crc\":\"0001:6F1A0B6D\"}\n";
+        renumber(&mut buf, 0xabc).unwrap();
+        assert_eq!(&buf, b"{\"req\":\"card.time\",\"crc\":\"0ABC:6F1A0B6D\"}\n");
+    }
+}
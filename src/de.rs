@@ -0,0 +1,355 @@
+//! Deserialization of responses.
+//!
+//! `serde-json-core` discards the message of errors raised by the types being deserialized, so a
+//! `heapless::String` or `heapless::Vec` that is too short for the value in the response fails
+//! the same way as e.g. a missing field. [`from_slice`] wraps the deserializer and the visitors of
+//! the response type to notice when [`invalid_length`](de::Error::invalid_length) is raised, which
+//! is how `heapless` reports that a value does not fit. The error raised by the visitor carries
+//! this up to the wrapper, which notes it for the duration of the call.
+
+use core::cell::Cell;
+use core::fmt;
+
+use serde::de::{
+    self, DeserializeSeed, Deserializer, EnumAccess, Expected, MapAccess, SeqAccess, VariantAccess,
+    Visitor,
+};
+use serde::Deserialize;
+
+/// Why deserializing a response failed.
+#[derive(Debug)]
+pub(crate) enum Error {
+    /// A string or list is longer than the capacity of its field.
+    Overflow,
+
+    /// The response does not match the response type.
+    Other,
+}
+
+/// Deserialize `T` from `body`.
+pub(crate) fn from_slice<'de, T: Deserialize<'de>>(body: &'de [u8]) -> Result<T, Error> {
+    let overflow = Cell::new(false);
+    let mut de = serde_json_core::de::Deserializer::new(body, None);
+
+    match T::deserialize(Wrap(&mut de, &overflow)).and_then(|t| de.end().map(|_| t)) {
+        Ok(t) => Ok(t),
+        Err(_) if overflow.get() => Err(Error::Overflow),
+        Err(_) => Err(Error::Other),
+    }
+}
+
+/// Error passed to the visitors of the response type, notes when a value does not fit.
+#[derive(Debug)]
+struct Flag<E> {
+    err: E,
+    overflow: bool,
+}
+
+impl<E> Flag<E> {
+    fn new(err: E) -> Flag<E> {
+        Flag {
+            err,
+            overflow: false,
+        }
+    }
+
+    /// Unwrap the error, noting in `overflow` if a value did not fit.
+    fn note(self, overflow: &Cell<bool>) -> E {
+        if self.overflow {
+            overflow.set(true);
+        }
+
+        self.err
+    }
+}
+
+impl<E: fmt::Display> fmt::Display for Flag<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.err.fmt(f)
+    }
+}
+
+impl<E: de::Error> de::StdError for Flag<E> {}
+
+impl<E: de::Error> de::Error for Flag<E> {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Flag::new(E::custom(msg))
+    }
+
+    fn invalid_length(len: usize, exp: &dyn Expected) -> Self {
+        Flag {
+            err: E::invalid_length(len, exp),
+            overflow: true,
+        }
+    }
+}
+
+/// Wraps the deserializer, visitors, seeds and accessors so that every visitor of the response
+/// type is called with [`Flag`] as its error, and notes in the cell when a value did not fit.
+struct Wrap<'a, T>(T, &'a Cell<bool>);
+
+macro_rules! forward_deserialize {
+    ($($method:ident($($arg:ident: $ty:ty),*);)*) => {$(
+        fn $method<V: Visitor<'de>>(self, $($arg: $ty,)* visitor: V) -> Result<V::Value, D::Error> {
+            self.0.$method($($arg,)* Wrap(visitor, self.1))
+        }
+    )*};
+}
+
+impl<'de, D: Deserializer<'de>> Deserializer<'de> for Wrap<'_, D> {
+    type Error = D::Error;
+
+    forward_deserialize! {
+        deserialize_any();
+        deserialize_bool();
+        deserialize_i8();
+        deserialize_i16();
+        deserialize_i32();
+        deserialize_i64();
+        deserialize_i128();
+        deserialize_u8();
+        deserialize_u16();
+        deserialize_u32();
+        deserialize_u64();
+        deserialize_u128();
+        deserialize_f32();
+        deserialize_f64();
+        deserialize_char();
+        deserialize_str();
+        deserialize_string();
+        deserialize_bytes();
+        deserialize_byte_buf();
+        deserialize_option();
+        deserialize_unit();
+        deserialize_unit_struct(name: &'static str);
+        deserialize_newtype_struct(name: &'static str);
+        deserialize_seq();
+        deserialize_tuple(len: usize);
+        deserialize_tuple_struct(name: &'static str, len: usize);
+        deserialize_map();
+        deserialize_struct(name: &'static str, fields: &'static [&'static str]);
+        deserialize_enum(name: &'static str, variants: &'static [&'static str]);
+        deserialize_identifier();
+        deserialize_ignored_any();
+    }
+
+    fn is_human_readable(&self) -> bool {
+        self.0.is_human_readable()
+    }
+}
+
+macro_rules! forward_visit {
+    ($($method:ident($ty:ty);)*) => {$(
+        fn $method<E: de::Error>(self, v: $ty) -> Result<V::Value, E> {
+            self.0.$method::<Flag<E>>(v).map_err(|e| e.note(self.1))
+        }
+    )*};
+}
+
+impl<'de, V: Visitor<'de>> Visitor<'de> for Wrap<'_, V> {
+    type Value = V::Value;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.expecting(f)
+    }
+
+    forward_visit! {
+        visit_bool(bool);
+        visit_i8(i8);
+        visit_i16(i16);
+        visit_i32(i32);
+        visit_i64(i64);
+        visit_i128(i128);
+        visit_u8(u8);
+        visit_u16(u16);
+        visit_u32(u32);
+        visit_u64(u64);
+        visit_u128(u128);
+        visit_f32(f32);
+        visit_f64(f64);
+        visit_char(char);
+        visit_str(&str);
+        visit_borrowed_str(&'de str);
+        visit_bytes(&[u8]);
+        visit_borrowed_bytes(&'de [u8]);
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<V::Value, E> {
+        self.0.visit_none::<Flag<E>>().map_err(|e| e.note(self.1))
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<V::Value, E> {
+        self.0.visit_unit::<Flag<E>>().map_err(|e| e.note(self.1))
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, d: D) -> Result<V::Value, D::Error> {
+        self.0.visit_some(Wrap(d, self.1))
+    }
+
+    fn visit_newtype_struct<D: Deserializer<'de>>(self, d: D) -> Result<V::Value, D::Error> {
+        self.0.visit_newtype_struct(Wrap(d, self.1))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<V::Value, A::Error> {
+        self.0
+            .visit_seq(Wrap(seq, self.1))
+            .map_err(|e| e.note(self.1))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<V::Value, A::Error> {
+        self.0
+            .visit_map(Wrap(map, self.1))
+            .map_err(|e| e.note(self.1))
+    }
+
+    fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> Result<V::Value, A::Error> {
+        self.0
+            .visit_enum(Wrap(data, self.1))
+            .map_err(|e| e.note(self.1))
+    }
+}
+
+impl<'de, S: DeserializeSeed<'de>> DeserializeSeed<'de> for Wrap<'_, S> {
+    type Value = S::Value;
+
+    fn deserialize<D: Deserializer<'de>>(self, d: D) -> Result<S::Value, D::Error> {
+        self.0.deserialize(Wrap(d, self.1))
+    }
+}
+
+impl<'de, A: SeqAccess<'de>> SeqAccess<'de> for Wrap<'_, A> {
+    type Error = Flag<A::Error>;
+
+    fn next_element_seed<S: DeserializeSeed<'de>>(
+        &mut self,
+        seed: S,
+    ) -> Result<Option<S::Value>, Self::Error> {
+        self.0
+            .next_element_seed(Wrap(seed, self.1))
+            .map_err(Flag::new)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.0.size_hint()
+    }
+}
+
+impl<'de, A: MapAccess<'de>> MapAccess<'de> for Wrap<'_, A> {
+    type Error = Flag<A::Error>;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Self::Error> {
+        self.0.next_key_seed(Wrap(seed, self.1)).map_err(Flag::new)
+    }
+
+    fn next_value_seed<S: DeserializeSeed<'de>>(
+        &mut self,
+        seed: S,
+    ) -> Result<S::Value, Self::Error> {
+        self.0
+            .next_value_seed(Wrap(seed, self.1))
+            .map_err(Flag::new)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.0.size_hint()
+    }
+}
+
+impl<'de, 'a, A: EnumAccess<'de>> EnumAccess<'de> for Wrap<'a, A> {
+    type Error = Flag<A::Error>;
+    type Variant = Wrap<'a, A::Variant>;
+
+    fn variant_seed<S: DeserializeSeed<'de>>(
+        self,
+        seed: S,
+    ) -> Result<(S::Value, Self::Variant), Self::Error> {
+        self.0
+            .variant_seed(Wrap(seed, self.1))
+            .map(|(v, variant)| (v, Wrap(variant, self.1)))
+            .map_err(Flag::new)
+    }
+}
+
+impl<'de, A: VariantAccess<'de>> VariantAccess<'de> for Wrap<'_, A> {
+    type Error = Flag<A::Error>;
+
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        self.0.unit_variant().map_err(Flag::new)
+    }
+
+    fn newtype_variant_seed<S: DeserializeSeed<'de>>(
+        self,
+        seed: S,
+    ) -> Result<S::Value, Self::Error> {
+        self.0
+            .newtype_variant_seed(Wrap(seed, self.1))
+            .map_err(Flag::new)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(
+        self,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.0
+            .tuple_variant(len, Wrap(visitor, self.1))
+            .map_err(Flag::new)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.0
+            .struct_variant(fields, Wrap(visitor, self.1))
+            .map_err(Flag::new)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use heapless::{String, Vec};
+
+    #[derive(Deserialize)]
+    struct Inner {
+        files: Option<Vec<String<4>, 2>>,
+    }
+
+    #[derive(Deserialize)]
+    struct Response {
+        zone: Option<String<8>>,
+        inner: Option<Inner>,
+    }
+
+    #[test]
+    fn overflow() {
+        let r: Response = from_slice(br#"{"zone":"CEST","inner":{"files":["a.db"]}}"#).unwrap();
+        assert_eq!(r.zone.as_deref(), Some("CEST"));
+        assert_eq!(r.inner.unwrap().files.unwrap()[0], "a.db");
+
+        assert!(matches!(
+            from_slice::<Response>(br#"{"zone":"CEST,Europe/Oslo"}"#),
+            Err(Error::Overflow)
+        ));
+        assert!(from_slice::<Response>(br#"{"inner":{"files":["a.qo"]}}"#).is_ok());
+        assert!(matches!(
+            from_slice::<Response>(br#"{"inner":{"files":["a.qos"]}}"#),
+            Err(Error::Overflow)
+        ));
+        assert!(matches!(
+            from_slice::<Response>(br#"{"inner":{"files":["a","b","c"]}}"#),
+            Err(Error::Overflow)
+        ));
+
+        assert!(matches!(
+            from_slice::<Response>(br#"{"zone":8}"#),
+            Err(Error::Other)
+        ));
+        assert!(matches!(from_slice::<Inner>(br#"{"#), Err(Error::Other)));
+    }
+}
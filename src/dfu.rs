@@ -0,0 +1,256 @@
+//! <https://dev.blues.io/reference/notecard-api/dfu-requests/>
+
+#[allow(unused_imports)]
+use crate::fmt::{debug, error, info, trace, warn};
+use embedded_hal::blocking::delay::DelayMs;
+use serde::{Deserialize, Serialize};
+
+use super::{payload, FutureResponse, NoteError, Notecard, NotecardTransport};
+
+#[cfg(feature = "dfu")]
+pub mod downloader;
+
+/// Maximum size of a firmware chunk retrieved with [`Dfu::get`].
+pub const DFU_CHUNK_MAX: usize = 4096;
+
+pub struct Dfu<'a, IOM: NotecardTransport, const BS: usize> {
+    note: &'a mut Notecard<IOM, BS>,
+}
+
+impl<'a, IOM: NotecardTransport, const BS: usize> Dfu<'a, IOM, BS> {
+    pub fn from(note: &mut Notecard<IOM, BS>) -> Dfu<'_, IOM, BS> {
+        Dfu { note }
+    }
+
+    /// Retrieves `length` bytes of the downloaded host firmware image starting at `offset`. The
+    /// chunk may not be larger than [`DFU_CHUNK_MAX`]. Use [`res::Get::decode_payload`] to get the
+    /// binary data, or [`FutureResponse::wait_streaming`] with a [`payload::Decoder`] to decode
+    /// chunks that do not fit in the buffer of the driver.
+    pub fn get(
+        self,
+        delay: &mut impl DelayMs<u16>,
+        length: u32,
+        offset: Option<u32>,
+    ) -> Result<FutureResponse<'a, res::Get, IOM, BS>, NoteError> {
+        if length as usize > DFU_CHUNK_MAX {
+            return Err(NoteError::BufOverflow);
+        }
+
+        self.note.request(
+            delay,
+            req::Get {
+                offset,
+                ..req::Get::new(length)
+            },
+        )?;
+        Ok(FutureResponse::from(self.note))
+    }
+
+    /// Gets and sets the background download status of host (`user`) or Notecard (`card`)
+    /// firmware updates. `on`/`off` allow or disallow the download, `stop` clears the current
+    /// image, and `status`/`err` report host-side progress or failure back to Notehub. `version`
+    /// and `vvalue` describe the currently running host firmware.
+    #[allow(clippy::too_many_arguments)]
+    pub fn status(
+        self,
+        delay: &mut impl DelayMs<u16>,
+        name: Option<req::DfuTarget>,
+        stop: bool,
+        status: Option<&str>,
+        version: Option<&str>,
+        vvalue: Option<&str>,
+        on: bool,
+        off: bool,
+        err: Option<&str>,
+    ) -> Result<FutureResponse<'a, res::Status, IOM, BS>, NoteError> {
+        self.note.request(
+            delay,
+            req::Status {
+                name,
+                stop: stop.then_some(true),
+                status,
+                version,
+                vvalue,
+                on: on.then_some(true),
+                off: off.then_some(true),
+                err,
+                ..req::Status::new()
+            },
+        )?;
+        Ok(FutureResponse::from(self.note))
+    }
+}
+
+pub mod req {
+    use super::*;
+
+    #[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "defmt-format", derive(defmt::Format))]
+    pub struct Get<'a> {
+        pub req: &'a str,
+
+        pub length: u32,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub offset: Option<u32>,
+    }
+
+    impl<'a> Get<'a> {
+        /// A `dfu.get` request for `length` bytes of the firmware image.
+        pub fn new(length: u32) -> Get<'a> {
+            Get {
+                req: "dfu.get",
+                length,
+                offset: None,
+            }
+        }
+    }
+
+    #[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "defmt-format", derive(defmt::Format))]
+    #[serde(rename_all = "lowercase")]
+    pub enum DfuTarget {
+        /// Host firmware.
+        User,
+        /// Notecard firmware.
+        Card,
+    }
+
+    #[derive(Serialize, Default, Debug, Clone, PartialEq)]
+    pub struct Status<'a> {
+        pub req: &'a str,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub name: Option<DfuTarget>,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub stop: Option<bool>,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub status: Option<&'a str>,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub version: Option<&'a str>,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub vvalue: Option<&'a str>,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub on: Option<bool>,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub off: Option<bool>,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub err: Option<&'a str>,
+    }
+
+    impl<'a> Status<'a> {
+        /// A `dfu.status` request with all optional fields unset.
+        pub fn new() -> Status<'a> {
+            Status {
+                req: "dfu.status",
+                ..Default::default()
+            }
+        }
+    }
+}
+
+pub mod res {
+    use super::*;
+
+    #[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "defmt-format", derive(defmt::Format))]
+    pub struct Get {
+        pub payload: heapless::String<{ payload::encoded_len(DFU_CHUNK_MAX) }>,
+    }
+
+    impl Get {
+        /// Decode the firmware chunk into `out`.
+        pub fn decode_payload<'b>(&self, out: &'b mut [u8]) -> Result<&'b [u8], NoteError> {
+            let sz = payload::decode(&self.payload, out)?;
+            Ok(&out[..sz])
+        }
+    }
+
+    /// Phase of the DFU state machine.
+    #[derive(Deserialize, Serialize, PartialEq, Debug, Clone)]
+    #[cfg_attr(feature = "defmt-format", derive(defmt::Format))]
+    #[serde(rename_all = "lowercase")]
+    pub enum DfuMode {
+        Idle,
+        Error,
+        Downloading,
+        Ready,
+        Completed,
+    }
+
+    /// Metadata of the downloaded firmware image.
+    #[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "defmt-format", derive(defmt::Format))]
+    pub struct Image {
+        pub crc32: Option<u32>,
+        pub created: Option<u32>,
+        pub modified: Option<u32>,
+        pub length: Option<u32>,
+        pub md5: Option<heapless::String<32>>,
+        pub name: Option<heapless::String<120>>,
+        pub source: Option<heapless::String<120>>,
+        pub notes: Option<heapless::String<120>>,
+        #[serde(rename = "type")]
+        pub type_: Option<heapless::String<20>>,
+        pub version: Option<heapless::String<120>>,
+    }
+
+    #[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "defmt-format", derive(defmt::Format))]
+    pub struct Status {
+        pub mode: Option<DfuMode>,
+        pub status: Option<heapless::String<120>>,
+        pub on: Option<bool>,
+        pub off: Option<bool>,
+        pub pending: Option<bool>,
+        pub body: Option<Image>,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn get_decode() {
+        let (r, _) = serde_json_core::from_str::<res::Get>(r#"{"payload":"AQIDBA=="}"#).unwrap();
+        let mut out = [0u8; 8];
+        assert_eq!(r.decode_payload(&mut out).unwrap(), &[1, 2, 3, 4]);
+    }
+
+    mod vectors {
+        use super::*;
+
+        vector_res!(get, "dfu.get.res.json", res::Get);
+
+        vector_req!(
+            get_req,
+            "dfu.get.req.json",
+            req::Get {
+                req: "dfu.get",
+                length: 32,
+                offset: Some(32),
+            }
+        );
+
+        vector_res!(status, "dfu.status.res.json", res::Status);
+
+        vector_req!(
+            status_req,
+            "dfu.status.req.json",
+            req::Status {
+                req: "dfu.status",
+                name: Some(req::DfuTarget::User),
+                status: Some("applying image"),
+                ..Default::default()
+            }
+        );
+    }
+}
@@ -0,0 +1,351 @@
+//! Host firmware update (outboard DFU) using `dfu.status` and `dfu.get`.
+//!
+//! ```ignore
+//! let mut buf = [0u8; 1024];
+//! let mut downloader = DfuDownloader::new(&mut buf);
+//!
+//! let length = downloader.download(&mut note, &mut delay, &mut flash, |offset, length| {
+//!     info!("dfu: {} / {}", offset, length);
+//! })?;
+//! ```
+
+#[allow(unused_imports)]
+use crate::fmt::{debug, error, info, trace, warn};
+use embedded_hal::blocking::delay::DelayMs;
+use md5::{Digest, Md5};
+
+use super::{res, DFU_CHUNK_MAX};
+use crate::card::digest_hex;
+use crate::{NoteError, Notecard, NotecardTransport};
+
+/// Destination of the downloaded firmware image, typically the inactive flash bank.
+pub trait FlashWriter {
+    type Error;
+
+    /// Write `data` at `offset` from the start of the image.
+    fn write(&mut self, offset: u32, data: &[u8]) -> Result<(), Self::Error>;
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt-format", derive(defmt::Format))]
+pub enum DfuError<E> {
+    Note(NoteError),
+
+    /// Error from the [`FlashWriter`].
+    Flash(E),
+
+    /// No firmware image is ready for download.
+    NotReady,
+
+    /// Chunk returned by `dfu.get` does not have the requested length.
+    ChunkLength,
+
+    Md5Mismatch,
+
+    Crc32Mismatch,
+}
+
+impl<E> From<NoteError> for DfuError<E> {
+    fn from(e: NoteError) -> DfuError<E> {
+        DfuError::Note(e)
+    }
+}
+
+/// Downloads a host firmware image from the Notecard in chunks, verifying the MD5 and CRC32 of
+/// the image.
+pub struct DfuDownloader<'b> {
+    buf: &'b mut [u8],
+}
+
+impl<'b> DfuDownloader<'b> {
+    /// Create a new downloader using `buf` for decoding chunks. The chunk size is the size of the
+    /// buffer (rounded down to a multiple of 3), but at most [`DFU_CHUNK_MAX`]. The download
+    /// fails with [`NoteError::InvalidArgument`] if `buf` is shorter than 3 bytes.
+    pub fn new(buf: &'b mut [u8]) -> DfuDownloader<'b> {
+        DfuDownloader { buf }
+    }
+
+    fn chunk_size(&self) -> u32 {
+        (self.buf.len().min(DFU_CHUNK_MAX) / 3 * 3) as u32
+    }
+
+    /// Check whether a firmware image is ready for download, returning its metadata.
+    pub fn ready<IOM: NotecardTransport, const BS: usize>(
+        &mut self,
+        note: &mut Notecard<IOM, BS>,
+        delay: &mut impl DelayMs<u16>,
+    ) -> Result<Option<res::Image>, NoteError> {
+        let status = note
+            .dfu()
+            .status(delay, None, false, None, None, None, false, false, None)?
+            .wait(delay)?;
+
+        match (status.mode, status.body) {
+            (Some(res::DfuMode::Ready), Some(image)) => Ok(Some(image)),
+            _ => Ok(None),
+        }
+    }
+
+    /// Download the ready firmware image into `writer`. `progress` is called with the number of
+    /// bytes written so far and the total length after each chunk. Returns the length of the
+    /// image once it has been verified.
+    pub fn download<
+        IOM: NotecardTransport,
+        const BS: usize,
+        W: FlashWriter,
+    >(
+        &mut self,
+        note: &mut Notecard<IOM, BS>,
+        delay: &mut impl DelayMs<u16>,
+        writer: &mut W,
+        mut progress: impl FnMut(u32, u32),
+    ) -> Result<u32, DfuError<W::Error>> {
+        let chunk = self.chunk_size();
+        if chunk == 0 {
+            error!("dfu: buffer of {} bytes is too small for a chunk.", self.buf.len());
+            return Err(NoteError::InvalidArgument.into());
+        }
+
+        let image = self.ready(note, delay)?.ok_or(DfuError::NotReady)?;
+        let length = image.length.ok_or(DfuError::NotReady)?;
+
+        info!("dfu: downloading image of {} bytes in chunks of {}.", length, chunk);
+
+        let mut md5 = Md5::new();
+        let mut crc32 = crc32fast::Hasher::new();
+        let mut offset = 0;
+
+        while offset < length {
+            let len = chunk.min(length - offset);
+
+            let r = note.dfu().get(delay, len, Some(offset))?.wait(delay)?;
+            let data = r.decode_payload(self.buf)?;
+
+            if data.len() != len as usize {
+                error!("dfu: got {} bytes, expected {}.", data.len(), len);
+                return Err(DfuError::ChunkLength);
+            }
+
+            md5.update(data);
+            crc32.update(data);
+            writer.write(offset, data).map_err(DfuError::Flash)?;
+
+            offset += len;
+            progress(offset, length);
+        }
+
+        if let Some(expected) = image.md5 {
+            if !digest_hex(&md5.finalize()).eq_ignore_ascii_case(&expected) {
+                error!("dfu: md5 mismatch.");
+                return Err(DfuError::Md5Mismatch);
+            }
+        }
+
+        if let Some(expected) = image.crc32 {
+            if crc32.finalize() != expected {
+                error!("dfu: crc32 mismatch.");
+                return Err(DfuError::Crc32Mismatch);
+            }
+        }
+
+        info!("dfu: image downloaded and verified.");
+
+        Ok(length)
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use super::*;
+    use crate::mock::{MockTransport, NoDelay};
+    use std::vec::Vec;
+
+    const IMAGE: &[u8] = b"0123456789";
+
+    /// Flash that keeps the written image, or fails writes at `fail_at`.
+    #[derive(Default)]
+    struct Flash {
+        image: Vec<u8>,
+        fail_at: Option<u32>,
+    }
+
+    impl FlashWriter for Flash {
+        type Error = u32;
+
+        fn write(&mut self, offset: u32, data: &[u8]) -> Result<(), u32> {
+            if self.fail_at == Some(offset) {
+                return Err(offset);
+            }
+
+            assert_eq!(offset as usize, self.image.len());
+            self.image.extend_from_slice(data);
+            Ok(())
+        }
+    }
+
+    fn status(md5: &str, crc32: u32) -> std::string::String {
+        format!(
+            r#"{{"mode":"ready","body":{{"length":{},"md5":"{}","crc32":{}}}}}"#,
+            IMAGE.len(),
+            md5,
+            crc32
+        )
+    }
+
+    fn chunk(data: &[u8]) -> std::string::String {
+        let mut out = [0u8; 64];
+        format!(
+            r#"{{"payload":"{}"}}"#,
+            crate::payload::encode(data, &mut out).unwrap()
+        )
+    }
+
+    /// A Notecard with the image ready, in chunks of 6 bytes.
+    fn ready(md5: &str, crc32: u32) -> MockTransport {
+        MockTransport::new()
+            .expect_req("dfu.status", &status(md5, crc32))
+            .expect(
+                r#"{"req":"dfu.get","length":6,"offset":0}"#,
+                &chunk(&IMAGE[..6]),
+            )
+            .expect(
+                r#"{"req":"dfu.get","length":4,"offset":6}"#,
+                &chunk(&IMAGE[6..]),
+            )
+    }
+
+    fn md5() -> heapless::String<32> {
+        digest_hex(&Md5::digest(IMAGE))
+    }
+
+    #[test]
+    fn download() {
+        let mut note: Notecard<_, 1024> =
+            ready(&md5().to_uppercase(), crc32fast::hash(IMAGE)).notecard();
+        let mut buf = [0u8; 8];
+        let mut flash = Flash::default();
+        let mut progress = Vec::new();
+
+        let length = DfuDownloader::new(&mut buf)
+            .download(&mut note, &mut NoDelay, &mut flash, |o, l| {
+                progress.push((o, l))
+            })
+            .unwrap();
+
+        assert_eq!(length, 10);
+        assert_eq!(flash.image, IMAGE);
+        assert_eq!(progress, [(6, 10), (10, 10)]);
+        note.transport().done();
+    }
+
+    #[test]
+    fn download_mismatch() {
+        let mut buf = [0u8; 8];
+
+        let mut note: Notecard<_, 1024> =
+            ready("3e2fdb1266b971f5c8963edd370ebd90", crc32fast::hash(IMAGE)).notecard();
+        assert!(matches!(
+            DfuDownloader::new(&mut buf).download(
+                &mut note,
+                &mut NoDelay,
+                &mut Flash::default(),
+                |_, _| ()
+            ),
+            Err(DfuError::Md5Mismatch)
+        ));
+        note.transport().done();
+
+        let mut note: Notecard<_, 1024> = ready(&md5(), 1).notecard();
+        assert!(matches!(
+            DfuDownloader::new(&mut buf).download(
+                &mut note,
+                &mut NoDelay,
+                &mut Flash::default(),
+                |_, _| ()
+            ),
+            Err(DfuError::Crc32Mismatch)
+        ));
+        note.transport().done();
+    }
+
+    #[test]
+    fn download_errors() {
+        let mut buf = [0u8; 8];
+        let crc32 = crc32fast::hash(IMAGE);
+
+        // No image.
+        let mut note: Notecard<_, 1024> = MockTransport::new()
+            .expect_req("dfu.status", r#"{"mode":"idle"}"#)
+            .notecard();
+        assert!(matches!(
+            DfuDownloader::new(&mut buf).download(
+                &mut note,
+                &mut NoDelay,
+                &mut Flash::default(),
+                |_, _| ()
+            ),
+            Err(DfuError::NotReady)
+        ));
+
+        // Buffer too small for a chunk, nothing is requested.
+        let mut note: Notecard<_, 1024> = MockTransport::new().notecard();
+        assert!(matches!(
+            DfuDownloader::new(&mut buf[..2]).download(
+                &mut note,
+                &mut NoDelay,
+                &mut Flash::default(),
+                |_, _| ()
+            ),
+            Err(DfuError::Note(NoteError::InvalidArgument))
+        ));
+        note.transport().done();
+
+        // Short chunk.
+        let mut note: Notecard<_, 1024> = MockTransport::new()
+            .expect_req("dfu.status", &status(&md5(), crc32))
+            .expect_req("dfu.get", &chunk(&IMAGE[..5]))
+            .notecard();
+        assert!(matches!(
+            DfuDownloader::new(&mut buf).download(
+                &mut note,
+                &mut NoDelay,
+                &mut Flash::default(),
+                |_, _| ()
+            ),
+            Err(DfuError::ChunkLength)
+        ));
+        note.transport().done();
+
+        // Flash fails.
+        let mut note: Notecard<_, 1024> = MockTransport::new()
+            .expect_req("dfu.status", &status(&md5(), crc32))
+            .expect_req("dfu.get", &chunk(&IMAGE[..6]))
+            .expect_req("dfu.get", &chunk(&IMAGE[6..]))
+            .notecard();
+        let mut flash = Flash {
+            fail_at: Some(6),
+            ..Flash::default()
+        };
+        assert!(matches!(
+            DfuDownloader::new(&mut buf).download(&mut note, &mut NoDelay, &mut flash, |_, _| ()),
+            Err(DfuError::Flash(6))
+        ));
+        note.transport().done();
+
+        // The Notecard fails.
+        let mut note: Notecard<_, 1024> = MockTransport::new()
+            .expect_req("dfu.status", &status(&md5(), crc32))
+            .expect_req("dfu.get", r#"{"err":"no firmware image {io}"}"#)
+            .notecard();
+        assert!(matches!(
+            DfuDownloader::new(&mut buf).download(
+                &mut note,
+                &mut NoDelay,
+                &mut Flash::default(),
+                |_, _| ()
+            ),
+            Err(DfuError::Note(NoteError::NotecardErr(_)))
+        ));
+        note.transport().done();
+    }
+}
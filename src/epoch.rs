@@ -0,0 +1,42 @@
+//! Conversion of the seconds since the epoch reported by the Notecard into `chrono` and `time`
+//! types. Times are `u64` in all responses. A time of `0` means that the Notecard did not know the
+//! time, and converts to `None`.
+
+#[cfg(feature = "chrono")]
+pub(crate) fn to_chrono(secs: u64) -> Option<chrono::DateTime<chrono::Utc>> {
+    use chrono::TimeZone;
+
+    match secs {
+        0 => None,
+        s => chrono::Utc.timestamp_opt(i64::try_from(s).ok()?, 0).single(),
+    }
+}
+
+#[cfg(feature = "time")]
+pub(crate) fn to_offset_date_time(secs: u64) -> Option<time::OffsetDateTime> {
+    match secs {
+        0 => None,
+        s => time::OffsetDateTime::from_unix_timestamp(i64::try_from(s).ok()?).ok(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn chrono() {
+        assert_eq!(super::to_chrono(1599769214).unwrap().timestamp(), 1599769214);
+        assert_eq!(super::to_chrono(0), None);
+        assert_eq!(super::to_chrono(u64::MAX), None);
+    }
+
+    #[cfg(feature = "time")]
+    #[test]
+    fn time() {
+        assert_eq!(
+            super::to_offset_date_time(1599769214).unwrap().unix_timestamp(),
+            1599769214
+        );
+        assert_eq!(super::to_offset_date_time(0), None);
+    }
+}
@@ -0,0 +1,62 @@
+//! Logging macros. These forward to `defmt` with the `defmt` feature, and otherwise to `log` with
+//! the `log` feature. With the `std` feature (and in the tests of the crate) `defmt` can not be
+//! linked, and messages only go to `log`. Without either feature the messages are discarded.
+
+#![allow(unused_macros)]
+
+macro_rules! log {
+    ($level:ident, $fmt:literal $(, $arg:expr)* $(,)?) => {{
+        #[cfg(all(feature = "defmt", not(any(feature = "std", test))))]
+        defmt::$level!($fmt $(, $arg)*);
+
+        #[cfg(all(feature = "log", any(not(feature = "defmt"), feature = "std", test)))]
+        ::log::$level!($fmt $(, $arg)*);
+
+        #[cfg(not(any(all(feature = "defmt", not(any(feature = "std", test))), feature = "log")))]
+        {
+            $( let _ = &$arg; )*
+        }
+    }};
+}
+
+macro_rules! trace {
+    ($($arg:tt)*) => { $crate::fmt::log!(trace, $($arg)*) };
+}
+
+macro_rules! debug {
+    ($($arg:tt)*) => { $crate::fmt::log!(debug, $($arg)*) };
+}
+
+macro_rules! info {
+    ($($arg:tt)*) => { $crate::fmt::log!(info, $($arg)*) };
+}
+
+// Named `warning` since `warn` can not be exported as it is also a built-in attribute.
+macro_rules! warning {
+    ($($arg:tt)*) => { $crate::fmt::log!(warn, $($arg)*) };
+}
+
+macro_rules! error {
+    ($($arg:tt)*) => { $crate::fmt::log!(error, $($arg)*) };
+}
+
+/// Log the complete JSON of a request (`>`) or response (`<`) at trace level with the
+/// `trace-wire` feature.
+macro_rules! wire {
+    ($dir:literal, $data:expr) => {{
+        #[cfg(feature = "trace-wire")]
+        $crate::fmt::trace!(
+            "note: {} {}",
+            $dir,
+            core::str::from_utf8($data).unwrap_or("[invalid utf-8]")
+        );
+
+        #[cfg(not(feature = "trace-wire"))]
+        {
+            let _ = &$data;
+        }
+    }};
+}
+
+#[allow(unused_imports)]
+pub(crate) use {debug, error, info, log, trace, warning as warn, wire};
@@ -1,18 +1,17 @@
 //! <https://dev.blues.io/reference/notecard-api/hub-requests/>
 
 #[allow(unused_imports)]
-use defmt::{debug, error, info, trace, warn};
+use crate::fmt::{debug, error, info, trace, warn};
 use embedded_hal::blocking::delay::DelayMs;
-use embedded_hal::blocking::i2c::{Read, SevenBitAddress, Write};
 use serde::{Deserialize, Serialize};
 
-use super::{FutureResponse, NoteError, Notecard};
+use super::{FutureResponse, NoteError, Notecard, NotecardTransport};
 
-pub struct Hub<'a, IOM: Write<SevenBitAddress> + Read<SevenBitAddress>, const BS: usize> {
+pub struct Hub<'a, IOM: NotecardTransport, const BS: usize> {
     note: &'a mut Notecard<IOM, BS>,
 }
 
-impl<'a, IOM: Write<SevenBitAddress> + Read<SevenBitAddress>, const BS: usize> Hub<'a, IOM, BS> {
+impl<'a, IOM: NotecardTransport, const BS: usize> Hub<'a, IOM, BS> {
     pub fn from(note: &mut Notecard<IOM, BS>) -> Hub<'_, IOM, BS> {
         Hub { note }
     }
@@ -28,10 +27,9 @@ impl<'a, IOM: Write<SevenBitAddress> + Read<SevenBitAddress>, const BS: usize> H
         self.note.request(
             delay,
             req::HubLog {
-                req: "hub.log",
-                text,
                 alert,
                 sync,
+                ..req::HubLog::new(text)
             },
         )?;
         Ok(FutureResponse::from(self.note))
@@ -44,8 +42,28 @@ impl<'a, IOM: Write<SevenBitAddress> + Read<SevenBitAddress>, const BS: usize> H
         Ok(FutureResponse::from(self.note))
     }
 
+    /// [hub.set](https://dev.blues.io/reference/notecard-api/hub-requests/#hub-set) with a
+    /// [`HubSetBuilder`], see [`Hub::set`].
+    ///
+    /// ```ignore
+    /// note.hub()
+    ///     .set_builder()
+    ///     .product("com.example:sensor")
+    ///     .mode(HubMode::Periodic)
+    ///     .outbound(60)
+    ///     .send(&mut delay)?
+    ///     .wait(&mut delay)?;
+    /// ```
+    pub fn set_builder<'b>(self) -> HubSetBuilder<'a, 'b, IOM, BS> {
+        HubSetBuilder {
+            note: self.note,
+            req: req::HubSet::new(),
+        }
+    }
+
     /// The [hub.set](https://dev.blues.io/reference/notecard-api/hub-requests/#hub-set) request is
     /// the primary method for controlling the Notecard's Notehub connection and sync behavior.
+    #[allow(clippy::too_many_arguments)]
     pub fn set(
         self,
         delay: &mut impl DelayMs<u16>,
@@ -61,10 +79,11 @@ impl<'a, IOM: Write<SevenBitAddress> + Read<SevenBitAddress>, const BS: usize> H
         align: Option<bool>,
         sync: Option<bool>,
     ) -> Result<FutureResponse<'a, res::Empty, IOM, BS>, NoteError> {
+        let product = product.map(|p| ProductUid::new(p).map(|p| p.as_str())).transpose()?;
+
         self.note.request(
             delay,
             req::HubSet {
-                req: "hub.set",
                 product,
                 host,
                 mode,
@@ -76,6 +95,7 @@ impl<'a, IOM: Write<SevenBitAddress> + Read<SevenBitAddress>, const BS: usize> H
                 vinbound,
                 align,
                 sync,
+                ..req::HubSet::new()
             },
         )?;
         Ok(FutureResponse::from(self.note))
@@ -89,8 +109,8 @@ impl<'a, IOM: Write<SevenBitAddress> + Read<SevenBitAddress>, const BS: usize> H
         allow: bool,
     ) -> Result<FutureResponse<'a, res::Empty, IOM, BS>, NoteError> {
         self.note.request(delay, req::HubSync {
-            req: "hub.sync",
-            allow: if allow { Some(true) } else { None }
+            allow: if allow { Some(true) } else { None },
+            ..req::HubSync::new()
         })?;
 
         Ok(FutureResponse::from(self.note))
@@ -105,20 +125,190 @@ impl<'a, IOM: Write<SevenBitAddress> + Read<SevenBitAddress>, const BS: usize> H
             .request_raw(delay, b"{\"req\":\"hub.sync.status\"}\n")?;
         Ok(FutureResponse::from(self.note))
     }
+
+    /// Displays the current status of the Notecard's connection to Notehub.
+    pub fn status(
+        self,
+        delay: &mut impl DelayMs<u16>,
+    ) -> Result<FutureResponse<'a, res::Status, IOM, BS>, NoteError> {
+        self.note.request_raw(delay, b"{\"req\":\"hub.status\"}\n")?;
+        Ok(FutureResponse::from(self.note))
+    }
+}
+
+/// Builder for `hub.set`, created with [`Hub::set_builder`]. Arguments that are not set are left
+/// unchanged on the Notecard.
+pub struct HubSetBuilder<'a, 'b, IOM: NotecardTransport, const BS: usize> {
+    note: &'a mut Notecard<IOM, BS>,
+    req: req::HubSet<'b>,
+}
+
+impl<'a, 'b, IOM: NotecardTransport, const BS: usize> HubSetBuilder<'a, 'b, IOM, BS> {
+    /// ProductUID of the Notehub project, checked with [`ProductUid::new`] when the request is
+    /// sent.
+    pub fn product(mut self, product: &'b str) -> Self {
+        self.req.product = Some(product);
+        self
+    }
+
+    /// Notehub host, leave unset for the default.
+    pub fn host(mut self, host: &'b str) -> Self {
+        self.req.host = Some(host);
+        self
+    }
+
+    pub fn mode(mut self, mode: req::HubMode) -> Self {
+        self.req.mode = Some(mode);
+        self
+    }
+
+    /// Serial number of the device in Notehub.
+    pub fn sn(mut self, sn: &'b str) -> Self {
+        self.req.sn = Some(sn);
+        self
+    }
+
+    /// Max time between syncs of outbound data (minutes).
+    pub fn outbound(mut self, minutes: u32) -> Self {
+        self.req.outbound = Some(minutes);
+        self
+    }
+
+    /// Outbound sync interval depending on the voltage, e.g. `usb:60;high:120;normal:240`.
+    pub fn voutbound(mut self, voutbound: &'b str) -> Self {
+        self.req.voutbound = Some(voutbound);
+        self
+    }
+
+    /// Max time between syncs of inbound data (minutes).
+    pub fn inbound(mut self, minutes: u32) -> Self {
+        self.req.inbound = Some(minutes);
+        self
+    }
+
+    /// Inbound sync interval depending on the voltage.
+    pub fn vinbound(mut self, vinbound: &'b str) -> Self {
+        self.req.vinbound = Some(vinbound);
+        self
+    }
+
+    /// Time to stay connected in `minimum` mode (minutes).
+    pub fn duration(mut self, minutes: u32) -> Self {
+        self.req.duration = Some(minutes);
+        self
+    }
+
+    /// Align syncs to a regular time-interval.
+    pub fn align(mut self, align: bool) -> Self {
+        self.req.align = Some(align);
+        self
+    }
+
+    /// Sync inbound notefiles automatically in `continuous` mode.
+    pub fn sync(mut self, sync: bool) -> Self {
+        self.req.sync = Some(sync);
+        self
+    }
+
+    pub fn send(
+        self,
+        delay: &mut impl DelayMs<u16>,
+    ) -> Result<FutureResponse<'a, res::Empty, IOM, BS>, NoteError> {
+        if let Some(p) = self.req.product {
+            ProductUid::new(p)?;
+        }
+
+        self.note.request(delay, self.req)?;
+        Ok(FutureResponse::from(self.note))
+    }
+}
+
+/// A ProductUID of a Notehub project: a reverse domain name, a `:` and the name of the product,
+/// e.g. `com.your-company.your-name:your_product`. The Notecard accepts any ProductUID, and
+/// silently never connects to Notehub if it is malformed, so [`Hub::set`] checks it with
+/// [`ProductUid::new`] and fails with [`NoteError::InvalidArgument`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt-format", derive(defmt::Format))]
+pub struct ProductUid<'a>(&'a str);
+
+impl<'a> ProductUid<'a> {
+    pub fn new(uid: &'a str) -> Result<ProductUid<'a>, NoteError> {
+        fn valid(s: &str, extra: &[u8]) -> bool {
+            !s.is_empty()
+                && s.bytes()
+                    .all(|c| c.is_ascii_alphanumeric() || extra.contains(&c))
+        }
+
+        let ok = match uid.split_once(':') {
+            Some((domain, product)) => {
+                domain.split('.').count() >= 2
+                    && domain.split('.').all(|l| valid(l, b"-_"))
+                    && valid(product, b"-_.")
+            }
+            None => false,
+        };
+
+        if ok {
+            Ok(ProductUid(uid))
+        } else {
+            error!("hub: invalid ProductUID: {}", uid);
+            Err(NoteError::InvalidArgument)
+        }
+    }
+
+    pub fn as_str(&self) -> &'a str {
+        self.0
+    }
+
+    /// The reverse domain name, e.g. `com.your-company.your-name`.
+    pub fn domain(&self) -> &'a str {
+        self.0.split_once(':').map_or("", |(d, _)| d)
+    }
+
+    /// The name of the product, e.g. `your_product`.
+    pub fn product(&self) -> &'a str {
+        self.0.split_once(':').map_or("", |(_, p)| p)
+    }
+}
+
+impl<'a> TryFrom<&'a str> for ProductUid<'a> {
+    type Error = NoteError;
+
+    fn try_from(uid: &'a str) -> Result<Self, Self::Error> {
+        ProductUid::new(uid)
+    }
+}
+
+impl AsRef<str> for ProductUid<'_> {
+    fn as_ref(&self) -> &str {
+        self.0
+    }
 }
 
 pub mod req {
     use super::*;
 
-    #[derive(Deserialize, Serialize, defmt::Format, Default)]
-    pub struct HubSync {
-        pub req: &'static str,
+    #[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "defmt-format", derive(defmt::Format))]
+    pub struct HubSync<'a> {
+        pub req: &'a str,
 
         #[serde(skip_serializing_if = "Option::is_none")]
         pub allow: Option<bool>,
     }
 
-    #[derive(Deserialize, Serialize, defmt::Format)]
+    impl<'a> HubSync<'a> {
+        /// A `hub.sync` request with all optional fields unset.
+        pub fn new() -> HubSync<'a> {
+            HubSync {
+                req: "hub.sync",
+                ..Default::default()
+            }
+        }
+    }
+
+    #[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "defmt", derive(defmt::Format))]
     #[serde(rename_all = "lowercase")]
     pub enum HubMode {
         Periodic,
@@ -128,10 +318,12 @@ pub mod req {
         DFU,
     }
 
-    #[derive(Deserialize, Serialize, defmt::Format, Default)]
+    #[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "defmt-format", derive(defmt::Format))]
     pub struct HubSet<'a> {
-        pub req: &'static str,
+        pub req: &'a str,
 
+        #[serde(skip_serializing_if = "Option::is_none")]
         pub product: Option<&'a str>,
 
         #[serde(skip_serializing_if = "Option::is_none")]
@@ -165,22 +357,47 @@ pub mod req {
         pub sync: Option<bool>,
     }
 
-    #[derive(Deserialize, Serialize, defmt::Format, Default)]
+    impl<'a> HubSet<'a> {
+        /// A `hub.set` request with all optional fields unset.
+        pub fn new() -> HubSet<'a> {
+            HubSet {
+                req: "hub.set",
+                ..Default::default()
+            }
+        }
+    }
+
+    #[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "defmt-format", derive(defmt::Format))]
     pub struct HubLog<'a> {
-        pub req: &'static str,
+        pub req: &'a str,
         pub text: &'a str,
         pub alert: bool,
         pub sync: bool,
     }
+
+    impl<'a> HubLog<'a> {
+        /// A `hub.log` request logging `text`.
+        pub fn new(text: &'a str) -> HubLog<'a> {
+            HubLog {
+                req: "hub.log",
+                text,
+                alert: false,
+                sync: false,
+            }
+        }
+    }
 }
 
 pub mod res {
     use super::*;
 
-    #[derive(Deserialize, defmt::Format)]
+    #[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "defmt-format", derive(defmt::Format))]
     pub struct Empty {}
 
-    #[derive(Deserialize, defmt::Format)]
+    #[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "defmt-format", derive(defmt::Format))]
     pub struct Hub {
         pub device: Option<heapless::String<40>>,
         pub product: Option<heapless::String<120>>,
@@ -189,19 +406,42 @@ pub mod res {
         pub voutbound: Option<f32>,
         pub inbound: Option<u32>,
         pub vinbound: Option<f32>,
-        pub host: Option<heapless::String<40>>,
+        pub host: Option<heapless::String<120>>,
         pub sn: Option<heapless::String<120>>,
         pub sync: Option<bool>,
     }
 
-    #[derive(Deserialize, defmt::Format)]
-    pub struct SyncStatus {
-        pub status: Option<heapless::String<1024>>,
-        pub time: Option<u32>,
+    #[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "defmt-format", derive(defmt::Format))]
+    pub struct SyncStatus<S = heapless::String<1024>> {
+        pub status: Option<S>,
+        pub time: Option<u64>,
         pub sync: Option<bool>,
         pub completed: Option<u32>,
         pub requested: Option<u32>,
     }
+
+    impl<S> SyncStatus<S> {
+        /// The time of the last sync.
+        #[cfg(feature = "chrono")]
+        pub fn to_chrono(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+            crate::epoch::to_chrono(self.time?)
+        }
+
+        /// The time of the last sync.
+        #[cfg(feature = "time")]
+        pub fn to_offset_date_time(&self) -> Option<time::OffsetDateTime> {
+            crate::epoch::to_offset_date_time(self.time?)
+        }
+    }
+
+    #[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "defmt-format", derive(defmt::Format))]
+    pub struct Status {
+        pub status: Option<heapless::String<120>>,
+        #[serde(default)]
+        pub connected: bool,
+    }
 }
 
 #[cfg(test)]
@@ -242,4 +482,100 @@ mod tests {
             r#"{"req":"hub.set","product":"testprod","host":"testhost","mode":"periodic"}"#
         );
     }
+
+    #[test]
+    fn product_uid() {
+        let p = ProductUid::new("com.your-company.your-name:your_product").unwrap();
+        assert_eq!(p.domain(), "com.your-company.your-name");
+        assert_eq!(p.product(), "your_product");
+        assert!(ProductUid::new("com.blues.tester:sensors.v2").is_ok());
+
+        for uid in [
+            "",
+            "testprod",
+            "com:product",
+            "com.blues.tester:",
+            ":product",
+            "com..tester:product",
+            "com.blues tester:product",
+            "com.blues.tester:product:x",
+            " com.blues.tester:product",
+        ] {
+            assert!(
+                matches!(ProductUid::new(uid), Err(NoteError::InvalidArgument)),
+                "{}",
+                uid
+            );
+        }
+    }
+
+    mod vectors {
+        use super::*;
+
+        vector_res!(get, "hub.get.res.json", res::Hub);
+        vector_res!(sync_status, "hub.sync.status.res.json", res::SyncStatus);
+
+        vector_req!(
+            set_req,
+            "hub.set.req.json",
+            req::HubSet {
+                req: "hub.set",
+                product: Some("com.your-company.your-name:your_product"),
+                mode: Some(req::HubMode::Periodic),
+                outbound: Some(60),
+                inbound: Some(240),
+                ..Default::default()
+            }
+        );
+
+        vector_req!(
+            log_req,
+            "hub.log.req.json",
+            req::HubLog {
+                req: "hub.log",
+                text: "something is wrong!",
+                alert: true,
+                sync: true,
+            }
+        );
+
+        vector_req!(
+            sync_req,
+            "hub.sync.req.json",
+            req::HubSync {
+                req: "hub.sync",
+                allow: Some(true),
+            }
+        );
+
+        vector_res!(status, "hub.status.res.json", res::Status);
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn set_builder() {
+        use crate::mock::{MockTransport, NoDelay};
+
+        let mut note: Notecard<_, 1024> = MockTransport::new()
+            .expect(
+                std::fs::read_to_string("tests/vectors/hub.set.req.json")
+                    .unwrap()
+                    .trim(),
+                "{}",
+            )
+            .notecard();
+
+        note.hub()
+            .set_builder()
+            .product("com.your-company.your-name:your_product")
+            .mode(req::HubMode::Periodic)
+            .outbound(60)
+            .inbound(240)
+            .send(&mut NoDelay)
+            .unwrap()
+            .wait(&mut NoDelay)
+            .unwrap();
+
+        note.transport().done();
+    }
 }
@@ -1,37 +1,128 @@
 //! Protocol for transmitting: <https://dev.blues.io/notecard/notecard-guides/serial-over-i2c-protocol/>
 //! API: <https://dev.blues.io/reference/notecard-api/introduction/>
 //!
-#![feature(split_array)]
 #![feature(type_changing_struct_update)]
-#![cfg_attr(not(test), no_std)]
+#![cfg_attr(not(any(test, feature = "std")), no_std)]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
 
 use core::marker::PhantomData;
 
 #[allow(unused_imports)]
-use defmt::{debug, error, info, trace, warn};
+use crate::fmt::{debug, error, info, trace, warn, wire};
 use embedded_hal::blocking::delay::DelayMs;
 use embedded_hal::blocking::i2c::{Read, SevenBitAddress, Write};
-use heapless::{String, Vec};
+use buffer::Buffer;
+use observer::Observer;
+use heapless::String;
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
+#[cfg(test)]
+#[macro_use]
+mod vectors;
+
+mod fmt;
+
+#[cfg(feature = "async")]
+pub mod asynch;
+pub mod attn;
+pub mod binary;
+mod buffer;
 pub mod card;
+pub mod cobs;
+#[cfg(feature = "crc")]
+pub mod crc;
+mod de;
+pub mod dfu;
+#[cfg(any(feature = "chrono", feature = "time"))]
+mod epoch;
 pub mod hub;
+#[cfg(feature = "mock")]
+pub mod mock;
 pub mod note;
-
-/// Delay between polling for new response.
+pub mod notefile;
+pub mod ntn;
+pub mod observer;
+pub mod payload;
+pub mod transport;
+pub mod var;
+pub mod web;
+
+pub use nb;
+pub use transport::{I2cTransport, NotecardTransport};
+
+/// Default delay between polling for new response (ms).
 const RESPONSE_DELAY: u16 = 25;
 
+/// Time without any data from the Notecard before a recovery is complete (ms).
+const DRAIN_IDLE: u16 = 250;
+
+// This is presumably limited by the notecard firmware.
+const CHUNK_LENGTH_MAX: usize = 127;
+// This is a limit that was required on some Arduinos. Can probably be increased up to
+// `CHUNK_LENGTH_MAX`.
+const CHUNK_LENGTH: usize = 30;
+
+// `note-c` uses `250` for `SEGMENT_LENGTH`. https://github.com/blues/note-c/blob/master/n_lib.h#L40 .
+const SEGMENT_LENGTH: usize = 250;
+
 /// The size of the shared request and receive buffer. Requests and responses may not serialize to
 /// any greater value than this.
 pub const DEFAULT_BUF_SIZE: usize = 18 * 1024;
 
-#[derive(Debug, defmt::Format)]
+/// The default I2C address of the Notecard. The address can be changed with `card.io` (see
+/// [`card::Card::io`]), e.g. to connect several Notecards to the same bus.
+pub const DEFAULT_I2C_ADDR: u8 = 0x17;
+
+/// Capacity of Notefile names and Note IDs in responses. The Notecard does not limit their length,
+/// but most are well below this. Longer names fail with [`NoteError::FieldOverflow`].
+pub const NOTEFILE_NAME_LEN: usize = 64;
+
+/// Configuration of the driver, used when it is created with e.g. [`Notecard::new_with_config`].
+/// The [`Default`] configuration works with most hosts, so only the fields that need to be
+/// changed should be given. That way new fields do not break existing configurations:
+///
+/// ```ignore
+/// let mut note = Notecard::<_>::new_with_config(
+///     i2c,
+///     NotecardConfig {
+///         i2c_addr: 0x18,
+///         chunk_length: 127,
+///         poll_interval: 100,
+///         response_timeout: 10_000,
+///         ..NotecardConfig::default()
+///     },
+/// )
+/// .initialize(&mut delay)?;
+/// ```
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "defmt-format", derive(defmt::Format))]
 pub struct NotecardConfig {
-    /// I2C address of Notecard.
+    /// I2C address of Notecard, [`DEFAULT_I2C_ADDR`] by default.
     pub i2c_addr: u8,
 
-    /// Timeout while waiting for response (ms).
-    pub response_timeout: u16,
+    /// Timeout while waiting for response (ms), 5 s by default.
+    pub response_timeout: u32,
+
+    /// Interval between polls for the response while waiting for it (ms). A long interval saves
+    /// power during slow requests, a short interval lowers the latency of quick requests. 25 ms
+    /// by default.
+    pub poll_interval: u16,
+
+    /// Length of the chunks a request is sent in (bytes). Some I2C masters can not transfer
+    /// more than a few bytes at once. Limited to 127 bytes.
+    ///
+    /// > `note-c`: https://github.com/blues/note-c/blob/master/n_lib.h#L33
+    pub chunk_length: usize,
+
+    /// Length of the segments a request is sent in (bytes), there is a longer delay between
+    /// each segment. Rounded down to a multiple of `chunk_length` so that we don't end up with
+    /// unnecessarily fragmented chunks.
+    ///
+    /// > `note-c`: https://github.com/blues/note-c/blob/master/n_lib.h#L40
+    /// > Original: 250 bytes.
+    pub segment_length: usize,
 
     /// Delay between chunks when transmitting (ms).
     ///
@@ -44,25 +135,111 @@ pub struct NotecardConfig {
     /// Delay between segments when transmitting (ms).
     ///
     /// > These delay may be almost eliminated for Notecard firmware version 3.4 (and presumably
-    /// above).
+    /// > above).
     ///
     /// > `note-c`: https://github.com/blues/note-c/blob/master/n_lib.h#L46
     /// > Original: 250 ms.
     pub segment_delay: u16,
+
+    /// Retry policy used by [`Notecard::retry`].
+    pub retry: RetryPolicy,
+
+    /// Add a `crc` to requests and check the `crc` of responses, see [`crc`].
+    #[cfg(feature = "crc")]
+    pub crc: bool,
+}
+
+/// How transient errors are retried, see [`Notecard::retry`] and [`NoteError::is_transient`].
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "defmt-format", derive(defmt::Format))]
+pub struct RetryPolicy {
+    /// Number of retries after the first attempt.
+    pub retries: u8,
+
+    /// Delay before the first retry (ms), doubled for every following retry.
+    pub delay: u16,
+
+    /// Largest delay between retries (ms).
+    pub max_delay: u16,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        // `note-c` retries I/O errors 5 times.
+        RetryPolicy {
+            retries: 5,
+            delay: 100,
+            max_delay: 2000,
+        }
+    }
+}
+
+/// Counters of the exchange with the Notecard since the driver was created, see
+/// [`Notecard::stats`]. The counters wrap around when they overflow.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt-format", derive(defmt::Format))]
+pub struct Stats {
+    /// Requests sent.
+    pub requests: u32,
+
+    /// Responses received, including discarded responses.
+    pub responses: u32,
+
+    /// Responses that could not be deserialized into the response type.
+    pub deser_errors: u32,
+
+    /// Requests retried by [`Notecard::retry`], or retransmitted after a response failed the
+    /// `crc` check.
+    pub retries: u32,
+
+    /// Requests that did not get a response within the time-out.
+    pub timeouts: u32,
+
+    /// Bytes written to the Notecard.
+    pub bytes_out: u32,
+
+    /// Bytes read from the Notecard.
+    pub bytes_in: u32,
+}
+
+impl Stats {
+    /// Count `r` if it failed to deserialize.
+    fn deserialized<T>(&mut self, r: &Result<T, NoteError>) {
+        if matches!(r, Err(NoteError::DeserError { .. } | NoteError::FieldOverflow { .. })) {
+            self.deser_errors = self.deser_errors.wrapping_add(1);
+        }
+    }
 }
 
 impl Default for NotecardConfig {
     fn default() -> Self {
         NotecardConfig {
-            i2c_addr: 0x17,
+            i2c_addr: DEFAULT_I2C_ADDR,
             response_timeout: 5000,
+            poll_interval: RESPONSE_DELAY,
+            chunk_length: CHUNK_LENGTH,
+            segment_length: SEGMENT_LENGTH,
             chunk_delay: 20,
             segment_delay: 250,
+            retry: RetryPolicy::default(),
+            #[cfg(feature = "crc")]
+            crc: true,
         }
     }
 }
 
-#[derive(Debug, defmt::Format)]
+impl NotecardConfig {
+    /// The chunk and segment length, limited to what the Notecard can receive.
+    pub(crate) fn chunking(&self) -> (usize, usize) {
+        let chunk = self.chunk_length.clamp(1, CHUNK_LENGTH_MAX);
+        let segment = (self.segment_length / chunk).max(1) * chunk;
+
+        (chunk, segment)
+    }
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum NoteState {
     /// Perform handshake with Notecard.
     Handshake,
@@ -78,15 +255,27 @@ pub enum NoteState {
 
     /// Full response has been read into `buf`.
     ResponseReady,
+
+    /// A transfer failed, the driver must [recover](Notecard::recover) before the next request.
+    Desync,
 }
 
-#[derive(Debug, defmt::Format, Clone)]
+/// Errors of the driver. The errors that occur while waiting for the response to a request carry
+/// the name of the request in `req`, e.g. `card.location.mode`, see [`NoteError::req`].
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum NoteError {
     I2cWriteError,
 
     I2cReadError,
 
-    DeserError(String<256>),
+    SerialWriteError,
+
+    SerialReadError,
+
+    /// The response could not be deserialized into the response type, `msg` is the start of the
+    /// response. The whole response is available from [`Notecard::last_response`].
+    DeserError { req: String<24>, msg: String<64> },
 
     SerError,
 
@@ -95,229 +284,895 @@ pub enum NoteError {
 
     RemainingData,
 
-    TimeOut,
+    TimeOut { req: String<24> },
 
     BufOverflow,
 
+    /// Payload is not valid base64.
+    InvalidPayload,
+
+    /// Argument is not valid for the request.
+    InvalidArgument,
+
+    /// A string argument of `len` bytes does not fit in its field of `capacity` bytes, and was
+    /// not sent rather than truncated.
+    ArgumentTooLong { len: usize, capacity: usize },
+
+    /// A [data source](binary::BinarySource) ended after `read` of the `size` bytes it reported.
+    SourceEnded { read: usize, size: usize },
+
     /// Method called when notecarrier is in invalid state.
     WrongState,
 
+    /// Could not read host GPIO pin.
+    PinError,
+
+    /// Notecard is not connected to Notehub in continuous mode, which is required for web
+    /// requests.
+    NotConnected,
+
     /// Notecard firmware is being updated.
     DFUInProgress,
 
-    NotecardErr(String<256>),
+    /// The `crc` of the response does not match the response.
+    CrcMismatch { req: String<24> },
+
+    /// A string or list in the response is longer than the capacity of its field in the response
+    /// type.
+    FieldOverflow { req: String<24> },
+
+    /// The Notecard responded with an error, see [`NotecardError::req`].
+    NotecardErr(NotecardError),
+
+    /// The request, of the given size in bytes, does not fit in the buffer of the driver and was
+    /// not sent. The full size of requests that fail to serialize is not known, and is reported
+    /// as one byte more than the buffer.
+    RequestTooLarge(usize),
+
+    /// The response does not fit in the buffer of `capacity` bytes, it is at least `needed`
+    /// bytes. The rest of the response is left with the Notecard, so that it can be read with
+    /// [`Notecard::read_response_into`]. Otherwise it is discarded before the next request.
+    ResponseTooLarge {
+        req: String<24>,
+        needed: usize,
+        capacity: usize,
+    },
 }
 
 impl NoteError {
+    /// The error is likely to go away if the request is retried: bus errors, time-outs and
+    /// [transient](NotecardErrorKind::is_transient) errors reported by the Notecard.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            NoteError::I2cWriteError
+            | NoteError::I2cReadError
+            | NoteError::SerialWriteError
+            | NoteError::SerialReadError
+            | NoteError::RemainingData
+            | NoteError::TimeOut { .. }
+            | NoteError::CrcMismatch { .. } => true,
+            NoteError::NotecardErr(e) => e.is_transient(),
+            _ => false,
+        }
+    }
+
+    /// The error for a request that could not be serialized into a buffer of `capacity` bytes.
+    pub(crate) fn from_ser(e: serde_json_core::ser::Error, capacity: usize) -> NoteError {
+        match e {
+            serde_json_core::ser::Error::BufferFull => NoteError::RequestTooLarge(capacity + 1),
+            _ => NoteError::SerError,
+        }
+    }
+
     pub fn new_desererror(msg: &[u8]) -> NoteError {
-        let msg = core::str::from_utf8(&msg).unwrap_or("[invalid utf-8]");
-        let mut s = String::new();
-        s.push_str(msg).ok();
-        NoteError::DeserError(s)
+        let msg = core::str::from_utf8(msg).unwrap_or("[invalid utf-8]");
+        NoteError::DeserError {
+            req: String::new(),
+            msg: truncated(msg),
+        }
+    }
+
+    /// A time-out while waiting for the response to `req`.
+    pub(crate) fn timeout(req: &str) -> NoteError {
+        NoteError::TimeOut { req: String::new() }.with_req(req)
+    }
+
+    /// The name of the request that failed, for errors that occur while waiting for the
+    /// response. Empty if the error did not occur in a request.
+    pub fn req(&self) -> &str {
+        match self {
+            NoteError::DeserError { req, .. }
+            | NoteError::TimeOut { req }
+            | NoteError::CrcMismatch { req }
+            | NoteError::FieldOverflow { req }
+            | NoteError::ResponseTooLarge { req, .. } => req,
+            NoteError::NotecardErr(e) => e.req(),
+            _ => "",
+        }
+    }
+
+    /// Set the name of the request that failed, if the error carries it and it is not set yet.
+    pub(crate) fn with_req(mut self, name: &str) -> NoteError {
+        let req = match &mut self {
+            NoteError::DeserError { req, .. }
+            | NoteError::TimeOut { req }
+            | NoteError::CrcMismatch { req }
+            | NoteError::FieldOverflow { req }
+            | NoteError::ResponseTooLarge { req, .. } => req,
+            NoteError::NotecardErr(e) => &mut e.req,
+            _ => return self,
+        };
+
+        if req.is_empty() {
+            for c in name.chars() {
+                if req.push(c).is_err() {
+                    break;
+                }
+            }
+        }
+
+        self
+    }
+}
+
+#[cfg(feature = "std")]
+impl core::fmt::Display for NoteError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        core::fmt::Debug::fmt(self, f)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for NoteError {}
+
+/// Class of an error reported by the Notecard, from the `{...}` token in the error message, e.g.
+/// `{io}` in `i2c: timeout {io}`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum NotecardErrorKind {
+    /// `{io}`: the request was corrupted on the way to the Notecard.
+    Io,
+
+    /// `{bad-bin}`: the binary data sent to the Notecard is corrupt.
+    BadBin,
+
+    /// `{card-busy}`: the Notecard is busy with another request.
+    CardBusy,
+
+    /// `{dfu-not-ready}`: the firmware update is not ready yet.
+    DfuNotReady,
+
+    /// `{dfu-in-progress}`: the Notecard firmware is being updated.
+    DfuInProgress,
+
+    /// `{not-supported}`: the request is not supported by this Notecard or firmware.
+    NotSupported,
+
+    /// No known token in the error message.
+    Other,
+}
+
+impl NotecardErrorKind {
+    const TOKENS: &'static [(&'static str, NotecardErrorKind)] = &[
+        ("{io}", NotecardErrorKind::Io),
+        ("{bad-bin}", NotecardErrorKind::BadBin),
+        ("{card-busy}", NotecardErrorKind::CardBusy),
+        ("{dfu-not-ready}", NotecardErrorKind::DfuNotReady),
+        ("{dfu-in-progress}", NotecardErrorKind::DfuInProgress),
+        ("{not-supported}", NotecardErrorKind::NotSupported),
+    ];
+
+    /// Parse the first known token in an error message.
+    pub fn from_err(err: &str) -> NotecardErrorKind {
+        err.match_indices('{')
+            .find_map(|(i, _)| {
+                Self::TOKENS
+                    .iter()
+                    .find(|(token, _)| err[i..].starts_with(token))
+                    .map(|(_, kind)| *kind)
+            })
+            .unwrap_or(NotecardErrorKind::Other)
+    }
+
+    /// The request is likely to succeed if it is retried, possibly after a delay: `{io}`,
+    /// `{bad-bin}` and `{card-busy}`.
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            NotecardErrorKind::Io | NotecardErrorKind::BadBin | NotecardErrorKind::CardBusy
+        )
     }
 }
 
-#[derive(Deserialize, defmt::Format)]
+/// An error response from the Notecard: `{"err":"..."}`.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct NotecardError {
-    err: String<256>,
+    err: String<64>,
+
+    /// Name of the request, set by the driver.
+    #[serde(skip)]
+    req: String<24>,
+
+    #[serde(skip)]
+    kind: NotecardErrorKind,
+}
+
+impl NotecardError {
+    /// An error with the message `err`, truncated if it is too long. The kind of the error is
+    /// taken from the whole message.
+    pub fn new(err: &str) -> NotecardError {
+        NotecardError {
+            err: truncated(err),
+            req: String::new(),
+            kind: NotecardErrorKind::from_err(err),
+        }
+    }
+
+    /// The error message, or its start if it is long. The whole response is available from
+    /// [`Notecard::last_response`].
+    pub fn message(&self) -> &str {
+        &self.err
+    }
+
+    /// Name of the request the Notecard responded to with this error, e.g. `card.location.mode`.
+    pub fn req(&self) -> &str {
+        &self.req
+    }
+
+    pub fn kind(&self) -> NotecardErrorKind {
+        self.kind
+    }
+
+    /// See [`NotecardErrorKind::is_transient`].
+    pub fn is_transient(&self) -> bool {
+        self.kind().is_transient()
+    }
+}
+
+impl<'de> Deserialize<'de> for NotecardError {
+    fn deserialize<D: serde::Deserializer<'de>>(d: D) -> Result<NotecardError, D::Error> {
+        #[derive(Deserialize)]
+        struct Response<'a> {
+            err: &'a str,
+        }
+
+        Response::deserialize(d).map(|r| NotecardError::new(r.err))
+    }
 }
 
 impl From<NotecardError> for NoteError {
     fn from(n: NotecardError) -> NoteError {
-        if n.err.contains("{dfu-in-progress}") {
+        if n.kind() == NotecardErrorKind::DfuInProgress {
             NoteError::DFUInProgress
         } else {
-            NoteError::NotecardErr(n.err)
+            NoteError::NotecardErr(n)
         }
     }
 }
 
-/// The driver for the Notecard. Must be intialized before making any requests.
-pub struct Notecard<
-    IOM: Write<SevenBitAddress> + Read<SevenBitAddress>,
-    const BUF_SIZE: usize = DEFAULT_BUF_SIZE,
-> {
-    i2c: IOM,
-    addr: u8,
+/// The driver for the Notecard.
+///
+/// The Notecard is connected through a [`NotecardTransport`], usually I2C using [`Notecard::new`].
+/// A new driver is [`Uninit`] and must be [initialized](Notecard::initialize) before making any
+/// requests, which gives a [`Ready`] driver.
+///
+/// Requests and responses are stored in a buffer of `BUF_SIZE` bytes which is part of the
+/// driver, [`DEFAULT_BUF_SIZE`] by default. RAM-constrained targets can use a smaller buffer,
+/// while large responses (e.g. `hub.sync.status` or web requests) may need a larger one:
+///
+/// ```ignore
+/// let mut note: Notecard<_, 2048> = Notecard::new(i2c).initialize(&mut delay)?;
+/// ```
+///
+/// The driver is [`Send`] when the transport is, so that it can be moved to another task or kept
+/// in an RTIC resource or an Embassy `Mutex`. It is not [`Sync`], requests need `&mut` access to
+/// the driver anyway. The sub-handles (e.g. [`card::Card`]) and [`FutureResponse`] borrow the
+/// driver mutably, and are [`Send`] too.
+pub struct Notecard<IOM: NotecardTransport, const BUF_SIZE: usize = DEFAULT_BUF_SIZE, S = Ready> {
+    transport: IOM,
     state: NoteState,
 
     /// The receive buffer. Must be large enough to hold the largest response that will be received.
-    buf: Vec<u8, BUF_SIZE>,
+    buf: Buffer<BUF_SIZE>,
 
-    response_timeout: u16,
+    response_timeout: u32,
+    poll_interval: u16,
+    chunk_length: usize,
+    segment_length: usize,
     chunk_delay: u16,
     segment_delay: u16,
+    retry: RetryPolicy,
+
+    /// The next request is sent as a command, see [`Notecard::command`].
+    command: bool,
+
+    /// `buf` holds the last response, see [`Notecard::last_response`].
+    response: bool,
+
+    /// Name of the last request.
+    name: String<24>,
+
+    observer: Option<&'static mut dyn Observer>,
+
+    stats: Stats,
+
+    #[cfg(feature = "crc")]
+    crc: CrcState,
+
+    init: PhantomData<S>,
+}
+
+/// A [`Notecard`] driver that has not been [initialized](Notecard::initialize) yet.
+pub struct Uninit;
+
+/// A [`Notecard`] driver that has been initialized and can make requests.
+pub struct Ready;
+
+/// Sequence numbers of requests with `crc`.
+#[cfg(feature = "crc")]
+#[derive(Clone, Copy)]
+struct CrcState {
+    enabled: bool,
+
+    /// Sequence number of the next request.
+    seq: u16,
+
+    /// Sequence number of the request waiting for a response.
+    pending: Option<u16>,
+
+    /// Length of the request kept at the start of the buffer while waiting for its response, so
+    /// that it can be retransmitted if the response fails the check. `0` if it is not kept.
+    kept: usize,
+
+    /// Number of times the request waiting for a response has been retransmitted.
+    resent: u8,
 }
 
 pub struct SuspendState<const BUF_SIZE: usize> {
-    addr: u8,
     state: NoteState,
-    buf: Vec<u8, BUF_SIZE>,
-    response_timeout: u16,
+    buf: Buffer<BUF_SIZE>,
+    response_timeout: u32,
+    poll_interval: u16,
+    chunk_length: usize,
+    segment_length: usize,
     chunk_delay: u16,
     segment_delay: u16,
+    retry: RetryPolicy,
+    name: String<24>,
+    observer: Option<&'static mut dyn Observer>,
+    stats: Stats,
+    #[cfg(feature = "crc")]
+    crc: CrcState,
 }
 
-impl<IOM: Write<SevenBitAddress> + Read<SevenBitAddress>, const BUF_SIZE: usize>
-    Notecard<IOM, BUF_SIZE>
+impl<I2C: Write<SevenBitAddress> + Read<SevenBitAddress>, const BUF_SIZE: usize>
+    Notecard<I2cTransport<I2C>, BUF_SIZE, Uninit>
 {
-    pub fn new(i2c: IOM) -> Notecard<IOM, BUF_SIZE> {
+    /// Create a driver for a Notecard connected over I2C.
+    pub fn new(i2c: I2C) -> Notecard<I2cTransport<I2C>, BUF_SIZE, Uninit> {
         Self::new_with_config(i2c, NotecardConfig::default())
     }
 
-    pub fn new_with_config(i2c: IOM, c: NotecardConfig) -> Notecard<IOM, BUF_SIZE> {
-        Notecard {
+    /// Create a driver for a Notecard at address `addr` on the I2C bus. Use together with
+    /// [`transport::shared`] to connect several Notecards to the same bus.
+    pub fn new_with_address(i2c: I2C, addr: u8) -> Notecard<I2cTransport<I2C>, BUF_SIZE, Uninit> {
+        Self::new_with_config(
             i2c,
-            addr: c.i2c_addr,
+            NotecardConfig {
+                i2c_addr: addr,
+                ..NotecardConfig::default()
+            },
+        )
+    }
+
+    pub fn new_with_config(
+        i2c: I2C,
+        c: NotecardConfig,
+    ) -> Notecard<I2cTransport<I2C>, BUF_SIZE, Uninit> {
+        let transport = I2cTransport::new(i2c, c.i2c_addr);
+        Notecard::new_with_transport(transport, c)
+    }
+}
+
+impl<IOM: NotecardTransport> Notecard<IOM, 0, Uninit> {
+    /// Create a driver for a Notecard connected through `transport`, using `buf` for requests and
+    /// responses instead of a buffer in the driver. E.g. to place the buffer in a specific RAM
+    /// region, or to share it with other code:
+    ///
+    /// ```ignore
+    /// static mut BUF: [u8; 4096] = [0; 4096];
+    ///
+    /// let buf = unsafe { &mut *core::ptr::addr_of_mut!(BUF) };
+    /// let mut note = Notecard::new_with_buffer(I2cTransport::new(i2c, 0x17), NotecardConfig::default(), buf)
+    ///     .initialize(&mut delay)?;
+    /// ```
+    pub fn new_with_buffer(
+        transport: IOM,
+        c: NotecardConfig,
+        buf: &'static mut [u8],
+    ) -> Notecard<IOM, 0, Uninit> {
+        Notecard {
+            buf: Buffer::borrowed(buf),
+            ..Notecard::<IOM, 0, Uninit>::new_with_transport(transport, c)
+        }
+    }
+}
+
+impl<IOM: NotecardTransport, const BUF_SIZE: usize> Notecard<IOM, BUF_SIZE, Uninit> {
+    /// Create a driver for a Notecard connected through `transport`. The `i2c_addr` of the
+    /// config is not used.
+    pub fn new_with_transport(
+        transport: IOM,
+        c: NotecardConfig,
+    ) -> Notecard<IOM, BUF_SIZE, Uninit> {
+        let (chunk_length, segment_length) = c.chunking();
+
+        Notecard {
+            transport,
             state: NoteState::Handshake,
-            buf: Vec::new(),
+            buf: Buffer::new(),
 
             response_timeout: c.response_timeout,
+            poll_interval: c.poll_interval.max(1),
+            chunk_length,
+            segment_length,
             chunk_delay: c.chunk_delay,
             segment_delay: c.segment_delay,
+            retry: c.retry,
+            command: false,
+            response: false,
+            name: String::new(),
+            observer: None,
+            stats: Stats::default(),
+            #[cfg(feature = "crc")]
+            crc: CrcState {
+                enabled: c.crc,
+                seq: 0,
+                pending: None,
+                kept: 0,
+                resent: 0,
+            },
+            init: PhantomData,
         }
     }
 
+    /// Initialize the notecard driver by discarding anything left over from before the host was
+    /// reset, see [`Notecard::sync_transport`]. Requests can only be made with the initialized
+    /// driver that is returned:
+    ///
+    /// ```ignore
+    /// let mut note = Notecard::<_>::new(i2c).initialize(&mut delay)?;
+    /// ```
+    pub fn initialize(
+        self,
+        delay: &mut impl DelayMs<u16>,
+    ) -> Result<Notecard<IOM, BUF_SIZE, Ready>, NoteError> {
+        info!("note: initializing.");
+
+        let mut note = Notecard {
+            init: PhantomData,
+            ..self
+        };
+        note.sync_transport(delay)?;
+        Ok(note)
+    }
+}
+
+impl<IOM: NotecardTransport, const BUF_SIZE: usize, S> Notecard<IOM, BUF_SIZE, S> {
     /// Resize the internal buffer, consuming the existing, and returning a new Notecard
-    /// instance.
-    pub fn resize_buf<const B: usize>(self) -> Result<Notecard<IOM, B>, NoteError> {
-        if B < self.buf.len() {
-            Err(NoteError::BufOverflow)
-        } else {
-            let (buf, _) = self.buf.split_array_ref::<B>();
-            Ok(Notecard {
-                buf: Vec::<_, B>::from_slice(buf).unwrap(),
-                ..self
-            })
-        }
+    /// instance. Fails if the content of the buffer does not fit in the new buffer.
+    pub fn resize_buf<const B: usize>(self) -> Result<Notecard<IOM, B, S>, NoteError> {
+        let buf = Buffer::from_slice(&self.buf).map_err(|_| NoteError::BufOverflow)?;
+        Ok(Notecard { buf, ..self })
     }
 
-    /// Free the IOM device and return the driver state so that it can be quickly resumed. It is
+    /// Size of the request and response buffer.
+    pub fn buf_size(&self) -> usize {
+        self.buf.capacity()
+    }
+
+    /// Check if notecarrier is connected and responding.
+    ///
+    /// > This is allowed no matter the state.
+    pub fn ping(&mut self) -> bool {
+        self.transport.ping()
+    }
+
+    /// Timeout while waiting for a response (ms).
+    pub fn response_timeout(&self) -> u32 {
+        self.response_timeout
+    }
+
+    /// Set the timeout while waiting for a response (ms), e.g. before a request that is known to
+    /// be slow. Waiting for a response fails with [`NoteError::TimeOut`] when the timeout is
+    /// exceeded.
+    pub fn set_response_timeout(&mut self, timeout: u32) {
+        self.response_timeout = timeout;
+    }
+
+    /// Interval between polls while waiting for a response (ms).
+    pub fn poll_interval(&self) -> u16 {
+        self.poll_interval
+    }
+
+    /// Set the interval between polls while waiting for a response (ms), e.g. a longer interval
+    /// before a request that takes several seconds. At least 1 ms.
+    pub fn set_poll_interval(&mut self, interval: u16) {
+        self.poll_interval = interval.max(1);
+    }
+
+    /// Counters of the requests and responses exchanged with the Notecard, e.g. to diagnose an
+    /// unreliable bus on a device in the field.
+    pub fn stats(&self) -> Stats {
+        self.stats
+    }
+
+    /// Register an [`Observer`] that is called with every request and response, replacing and
+    /// returning the previous observer. Use `None` to remove it.
+    pub fn set_observer(
+        &mut self,
+        observer: Option<&'static mut dyn Observer>,
+    ) -> Option<&'static mut dyn Observer> {
+        core::mem::replace(&mut self.observer, observer)
+    }
+
+    /// The transport to the Notecard.
+    pub fn transport(&mut self) -> &mut IOM {
+        &mut self.transport
+    }
+}
+
+impl<IOM: NotecardTransport, const BUF_SIZE: usize> Notecard<IOM, BUF_SIZE> {
+    /// Free the transport and return the driver state so that it can be quickly resumed. It is
     /// not safe to change the state of the Notecard in the meantime, or create a second driver
     /// without using this state.
     pub fn suspend(self) -> (IOM, SuspendState<BUF_SIZE>) {
         (
-            self.i2c,
+            self.transport,
             SuspendState {
                 state: self.state,
                 buf: self.buf,
-                addr: self.addr,
                 response_timeout: self.response_timeout,
+                poll_interval: self.poll_interval,
+                chunk_length: self.chunk_length,
+                segment_length: self.segment_length,
                 chunk_delay: self.chunk_delay,
                 segment_delay: self.segment_delay,
+                retry: self.retry,
+                name: self.name,
+                observer: self.observer,
+                stats: self.stats,
+                #[cfg(feature = "crc")]
+                crc: self.crc,
             },
         )
     }
 
     /// Resume a previously [`suspend`]ed Notecard driver.
-    pub fn resume(i2c: IOM, state: SuspendState<BUF_SIZE>) -> Notecard<IOM, BUF_SIZE> {
+    pub fn resume(transport: IOM, state: SuspendState<BUF_SIZE>) -> Notecard<IOM, BUF_SIZE> {
         Notecard {
-            i2c,
-            addr: state.addr,
+            transport,
             state: state.state,
             buf: state.buf,
             response_timeout: state.response_timeout,
+            poll_interval: state.poll_interval,
+            chunk_length: state.chunk_length,
+            segment_length: state.segment_length,
             chunk_delay: state.chunk_delay,
             segment_delay: state.segment_delay,
+            retry: state.retry,
+            command: false,
+            response: false,
+            name: state.name,
+            observer: state.observer,
+            stats: state.stats,
+            #[cfg(feature = "crc")]
+            crc: state.crc,
+            init: PhantomData,
         }
     }
 
-    /// Initialize the notecard driver by performing handshake with notecard.
-    pub fn initialize(&mut self, delay: &mut impl DelayMs<u16>) -> Result<(), NoteError> {
-        info!("note: initializing.");
-        self.reset(delay)
+    /// Make a request and wait for the response with `f`, retrying according to the
+    /// [`RetryPolicy`] of the config if it fails with a [transient](NoteError::is_transient)
+    /// error. The last error is returned when there are no retries left.
+    ///
+    /// ```ignore
+    /// let time = note.retry(&mut delay, |note, delay| note.card().time(delay)?.wait(delay))?;
+    /// ```
+    pub fn retry<D: DelayMs<u16>, R>(
+        &mut self,
+        delay: &mut D,
+        mut f: impl FnMut(&mut Self, &mut D) -> Result<R, NoteError>,
+    ) -> Result<R, NoteError> {
+        let mut tries = 0;
+        let mut backoff = self.retry.delay;
+
+        loop {
+            match f(self, delay) {
+                Err(e) if e.is_transient() && tries < self.retry.retries => {
+                    warn!("note: request failed: {:?}, retrying in {} ms..", e, backoff);
+                    tries += 1;
+                    self.stats.retries = self.stats.retries.wrapping_add(1);
+
+                    delay.delay_ms(backoff);
+                    backoff = backoff.saturating_mul(2).min(self.retry.max_delay);
+                }
+                r => return r,
+            }
+        }
     }
 
-    /// Check if notecarrier is connected and responding.
+    /// Send the request made in `f` as a command (`cmd` instead of `req`), which the Notecard
+    /// does not respond to. The [`FutureResponse`] is discarded, so time-critical code does not
+    /// have to wait for the Notecard. Requests that wait for their response internally (e.g.
+    /// [`card::Card::io`]) can not be sent as commands.
     ///
-    /// > This is allowed no matter the state.
-    pub fn ping(&mut self) -> bool {
-        self.i2c.write(self.addr, &[]).is_ok()
+    /// ```ignore
+    /// note.command(&mut delay, |note, delay| {
+    ///     note.note().add(delay, Some("readings.qo"), None, Some(reading), None, false)
+    /// })?;
+    /// ```
+    pub fn command<D: DelayMs<u16>, T: DeserializeOwned>(
+        &mut self,
+        delay: &mut D,
+        f: impl for<'n> FnOnce(
+            &'n mut Self,
+            &mut D,
+        ) -> Result<FutureResponse<'n, T, IOM, BUF_SIZE>, NoteError>,
+    ) -> Result<(), NoteError> {
+        self.command = true;
+        let r = f(self, delay).map(|_| ());
+        self.command = false;
+        r
     }
 
-    /// Query the notecard for available bytes.
-    pub fn data_query(&mut self) -> Result<usize, NoteError> {
-        trace!("note: data_query: {:?}", self.state);
-        if !matches!(self.state, NoteState::Response(_)) {
-            // Ask for reading, but with zero bytes allocated.
-            self.i2c
-                .write(self.addr, &[0, 0])
-                .map_err(|_| NoteError::I2cWriteError)?;
-
-            let mut buf = [0u8; 2];
-
-            // Read available bytes to read
-            self.i2c
-                .read(self.addr, &mut buf)
-                .map_err(|_| NoteError::I2cReadError)?;
-
-            let available = buf[0] as usize;
-            let sent = buf[1] as usize;
+    /// The raw JSON of the last response, including responses that could not be deserialized,
+    /// until the next request is made. E.g. to log a response that failed with
+    /// [`NoteError::DeserError`] or [`NoteError::FieldOverflow`].
+    pub fn last_response(&self) -> Option<&[u8]> {
+        self.response.then(|| &self.buf[..])
+    }
 
-            if available > 0 {
-                self.buf.clear();
-                self.state = NoteState::Response(available);
+    /// Read a response that did not fit in the buffer of the driver into `out`, after waiting for
+    /// it failed with [`NoteError::ResponseTooLarge`]. Returns the raw JSON of the response:
+    ///
+    /// ```ignore
+    /// let status = match note.hub().sync_status(&mut delay, false)?.wait(&mut delay) {
+    ///     Err(NoteError::ResponseTooLarge { .. }) => {
+    ///         let mut out = [0u8; 4096];
+    ///         let raw = note.read_response_into(&mut out)?;
+    ///         serde_json_core::from_slice::<hub::res::SyncStatus>(raw)?.0
+    ///     }
+    ///     r => r?,
+    /// };
+    /// ```
+    ///
+    /// Fails with [`NoteError::ResponseTooLarge`] if the response does not fit in `out` either.
+    /// If nothing was read yet it can be tried again with a larger buffer, otherwise the rest of
+    /// the response is discarded before the next request.
+    pub fn read_response_into<'b>(&mut self, out: &'b mut [u8]) -> Result<&'b [u8], NoteError> {
+        let mut avail = match self.state {
+            NoteState::Response(avail) => avail,
+            _ => {
+                error!("read_response_into: no response is being read.");
+                return Err(NoteError::WrongState);
             }
+        };
+        self.release_request();
+
+        let mut len = self.buf.len();
+        if len.saturating_add(avail) > out.len() {
+            return Err(NoteError::ResponseTooLarge {
+                req: self.name.clone(),
+                needed: len.saturating_add(avail),
+                capacity: out.len(),
+            });
+        }
+        out[..len].copy_from_slice(&self.buf);
+        self.buf.clear();
 
-            trace!("avail = {}, sent = {}", available, sent);
-
-            if sent > 0 {
+        while avail > 0 {
+            let sz = avail.min(128).min(out.len() - len);
+            if sz == 0 {
                 error!(
-                    "data query: bytes sent when querying available bytes: {}",
-                    sent
+                    "read_response_into: response does not fit (>= {} bytes).",
+                    len.saturating_add(avail)
                 );
-                Err(NoteError::RemainingData)
-            } else {
-                Ok(available)
+                self.state = NoteState::Desync;
+                return Err(NoteError::ResponseTooLarge {
+                    req: self.name.clone(),
+                    needed: len.saturating_add(avail),
+                    capacity: out.len(),
+                });
             }
-        } else {
-            error!("note: data_query called while reading response.");
-            Err(NoteError::WrongState)
+
+            let (n, next) = self.transport.read(&mut out[len..len + sz]).inspect_err(|_| {
+                self.state = NoteState::Desync;
+            })?;
+            read_chunk(&out[len..len + sz], n).inspect_err(|_| {
+                self.state = NoteState::Desync;
+            })?;
+            self.stats.bytes_in = self.stats.bytes_in.wrapping_add(n as u32);
+
+            len += n;
+            avail = next;
         }
-    }
 
-    /// Read until empty.
-    fn read(&mut self) -> Result<usize, NoteError> {
-        if let NoteState::Response(avail) = self.state {
-            // Chunk to read + notecard header (2 bytes)
-            let mut bytes = Vec::<u8, 128>::new();
+        self.state = NoteState::Request;
+        self.stats.responses = self.stats.responses.wrapping_add(1);
 
-            let sz = (bytes.capacity() - 2).min(avail);
-            bytes.resize(sz + 2, 0).unwrap();
+        let body = &out[..len];
+        wire!("<", body);
 
-            debug!("asking to read: {} of available {} bytes", sz, avail);
+        if let Some(o) = self.observer.as_mut() {
+            o.response(&self.name, body);
+        }
 
-            // Ask for reading `sz` bytes
-            self.i2c
-                .write(self.addr, &[0, sz as u8])
-                .map_err(|_| NoteError::I2cWriteError)?;
+        #[cfg(feature = "crc")]
+        if let Some(seq) = self.crc.pending.take() {
+            crc::check(body, seq).map_err(|e| e.with_req(&self.name))?;
+        }
 
-            // Read bytes
-            self.i2c
-                .read(self.addr, &mut bytes)
-                .map_err(|_| NoteError::I2cReadError)?;
+        Ok(body)
+    }
 
-            let available = bytes[0] as usize;
-            let sent = bytes[1] as usize;
+    /// Pass a response to `sink` in chunks as it is read, instead of keeping the whole response,
+    /// after waiting for it failed with [`NoteError::ResponseTooLarge`]. E.g. to write a large
+    /// response to storage, or to decode its `payload` with [`payload::Decoder`]. Returns the
+    /// length of the response. See [`FutureResponse::wait_streaming`] to stream the response
+    /// from the start.
+    ///
+    /// The response is checked against its `crc` when the whole response has been passed to
+    /// `sink`. It is not passed to the [observer](Notecard::set_observer), nor kept for
+    /// [`Notecard::last_response`].
+    ///
+    /// If `sink` fails the rest of the response is discarded before the next request.
+    pub fn read_response_with(
+        &mut self,
+        mut sink: impl FnMut(&[u8]) -> Result<(), NoteError>,
+    ) -> Result<usize, NoteError> {
+        let mut avail = match self.state {
+            NoteState::Response(avail) => avail,
+            NoteState::ResponseReady => 0,
+            _ => {
+                error!("read_response_with: no response is being read.");
+                return Err(NoteError::WrongState);
+            }
+        };
+        self.release_request();
 
-            self.buf.extend_from_slice(&bytes[2..]).unwrap(); // XXX: check enough space
+        #[cfg(feature = "crc")]
+        let mut check = self.crc.pending.take().map(|seq| (seq, crc::Stream::new()));
 
-            trace!("read:  {}", unsafe {
-                core::str::from_utf8_unchecked(&bytes)
-            });
+        // Whatever was read into the buffer before it overflowed goes first.
+        let mut len = self.buf.len();
+        if len > 0 {
+            wire!("<", &self.buf);
 
-            trace!("avail = {}, sent = {}", available, sent);
+            #[cfg(feature = "crc")]
+            if let Some((_, check)) = check.as_mut() {
+                check.update(&self.buf);
+            }
 
-            if available > 0 {
-                self.state = NoteState::Response(available);
-            } else {
-                self.state = NoteState::ResponseReady;
+            let r = sink(&self.buf);
+            self.buf.clear();
+            self.response = false;
+            r.inspect_err(|_| {
+                self.state = NoteState::Desync;
+            })?;
+        }
+
+        let mut bytes = [0u8; 128];
+
+        while avail > 0 {
+            let sz = avail.min(bytes.len());
+
+            let (n, next) = self.transport.read(&mut bytes[..sz]).map_err(|e| {
+                self.state = NoteState::Desync;
+                e.with_req(&self.name)
+            })?;
+            let chunk = read_chunk(&bytes[..sz], n).inspect_err(|_| {
+                self.state = NoteState::Desync;
+            })?;
+            self.stats.bytes_in = self.stats.bytes_in.wrapping_add(n as u32);
+            wire!("<", chunk);
+
+            #[cfg(feature = "crc")]
+            if let Some((_, check)) = check.as_mut() {
+                check.update(chunk);
             }
 
-            Ok(available)
-        } else {
-            error!("read: called when not waiting for response");
-            Err(NoteError::WrongState)
+            sink(chunk).inspect_err(|_| {
+                self.state = NoteState::Desync;
+            })?;
+
+            len += n;
+            avail = next;
+        }
+
+        self.state = NoteState::Request;
+        self.stats.responses = self.stats.responses.wrapping_add(1);
+
+        #[cfg(feature = "crc")]
+        if let Some((seq, check)) = check {
+            check.finish(seq).map_err(|e| e.with_req(&self.name))?;
+        }
+
+        Ok(len)
+    }
+
+    /// Query the notecard for available bytes.
+    pub fn data_query(&mut self) -> Result<usize, NoteError> {
+        trace!("note: data_query: {:?}", self.state);
+        if !matches!(self.state, NoteState::Response(_)) {
+            let available = self.transport.available()?;
+
+            if available > 0 {
+                // The response is read after the request if it is kept for retransmission.
+                #[cfg(feature = "crc")]
+                self.buf.truncate(self.crc.kept);
+                #[cfg(not(feature = "crc"))]
+                self.buf.clear();
+                self.state = NoteState::Response(available);
+            }
+
+            Ok(available)
+        } else {
+            error!("note: data_query called while reading response.");
+            Err(NoteError::WrongState)
+        }
+    }
+
+    /// Read until empty.
+    fn read(&mut self) -> Result<usize, NoteError> {
+        if let NoteState::Response(avail) = self.state {
+            let mut bytes = [0u8; 128];
+
+            // Make room for the response by giving up the request kept for retransmission.
+            if self.buf.len() == self.buf.capacity() {
+                self.release_request();
+            }
+
+            // Stop before the buffer overflows, without reading anything that can not be kept.
+            let space = self.buf.capacity() - self.buf.len();
+            if space == 0 {
+                let needed = self.buf.len().saturating_add(avail);
+                error!("read: response does not fit in buffer (>= {} bytes).", needed);
+                return Err(NoteError::ResponseTooLarge {
+                    req: self.name.clone(),
+                    needed,
+                    capacity: self.buf.capacity(),
+                });
+            }
+
+            let sz = bytes.len().min(avail).min(space);
+
+            debug!("asking to read: {} of available {} bytes", sz, avail);
+
+            let (n, available) = self.transport.read(&mut bytes[..sz]).map_err(|e| {
+                self.state = NoteState::Desync;
+                e.with_req(&self.name)
+            })?;
+            let chunk = read_chunk(&bytes[..sz], n).inspect_err(|_| {
+                self.state = NoteState::Desync;
+            })?;
+
+            self.stats.bytes_in = self.stats.bytes_in.wrapping_add(n as u32);
+            self.buf
+                .extend_from_slice(chunk)
+                .map_err(|_| NoteError::BufOverflow)?;
+
+            if available > 0 {
+                self.state = NoteState::Response(available);
+            } else {
+                self.state = NoteState::ResponseReady;
+            }
+
+            Ok(available)
+        } else {
+            error!("read: called when not waiting for response");
+            Err(NoteError::WrongState)
         }
     }
 
@@ -332,6 +1187,13 @@ impl<IOM: Write<SevenBitAddress> + Read<SevenBitAddress>, const BUF_SIZE: usize>
     fn take_response(&mut self) -> Result<&[u8], NoteError> {
         if matches!(self.state, NoteState::ResponseReady) {
             self.state = NoteState::Request;
+            self.response = true;
+            self.stats.responses = self.stats.responses.wrapping_add(1);
+            wire!("<", &self.buf);
+
+            if let Some(o) = self.observer.as_mut() {
+                o.response(&self.name, &self.buf);
+            }
 
             Ok(&self.buf)
         } else {
@@ -344,30 +1206,57 @@ impl<IOM: Write<SevenBitAddress> + Read<SevenBitAddress>, const BUF_SIZE: usize>
     fn poll(&mut self) -> Result<Option<&[u8]>, NoteError> {
         trace!("note: poll: {:?}", self.state);
         match self.state {
-            NoteState::Poll(_) => {
-                // 1. Check for available data
+            NoteState::Poll(tries) => {
+                // 1. Check for available data, this is a cheap query of the number of bytes
+                // ready. The response is only read once it is ready.
                 let sz = self.data_query()?;
                 if sz > 0 {
-                    debug!("response ready: {} bytes..", sz);
+                    debug!("response ready: {} bytes (after {} polls)..", sz, tries);
 
                     self.poll()
                 } else {
                     // sleep and wait for ready.
+                    self.state = NoteState::Poll(tries + 1);
                     Ok(None)
                 }
             }
             NoteState::Response(_) => {
-                let avail = self.read()?;
-                if avail == 0 {
-                    self.poll()
-                } else {
-                    // sleep and wait for more data.
-                    Ok(None)
-                }
+                // 2. Read all of the response that is ready without waiting in between chunks.
+                while self.read()? > 0 {}
+                self.poll()
             }
             NoteState::ResponseReady => {
                 debug!("response read, deserializing.");
-                Ok(Some(self.take_response()?))
+
+                #[cfg(feature = "crc")]
+                let seq = self.crc.pending.take();
+
+                // A corrupted response is discarded, and the request is left in the buffer to be
+                // retransmitted by `poll_resend`.
+                #[cfg(feature = "crc")]
+                if let Some(seq) = seq {
+                    let kept = self.crc.kept.min(self.buf.len());
+                    if kept > 0
+                        && self.crc.resent < crc::RESENDS
+                        && crc::check(&self.buf[kept..], seq).is_err()
+                    {
+                        self.buf.truncate(kept);
+                        self.state = NoteState::Request;
+                        return Err(NoteError::CrcMismatch {
+                            req: self.name.clone(),
+                        });
+                    }
+                }
+
+                self.release_request();
+                self.take_response()?;
+
+                #[cfg(feature = "crc")]
+                if let Some(seq) = seq {
+                    crc::check(&self.buf, seq).map_err(|e| e.with_req(&self.name))?;
+                }
+
+                Ok(Some(&self.buf))
             }
             _ => {
                 error!("poll called when not receiving response");
@@ -376,6 +1265,56 @@ impl<IOM: Write<SevenBitAddress> + Read<SevenBitAddress>, const BUF_SIZE: usize>
         }
     }
 
+    /// [`Notecard::poll`], retransmitting the request with a new sequence number if the response
+    /// failed the `crc` check, up to [`crc::RESENDS`] times. Returns `true` when the response
+    /// is ready.
+    #[cfg_attr(not(feature = "crc"), allow(unused_variables))]
+    fn poll_resend(&mut self, delay: &mut impl DelayMs<u16>) -> Result<bool, NoteError> {
+        match self.poll().map(|r| r.is_some()) {
+            #[cfg(feature = "crc")]
+            Err(NoteError::CrcMismatch { .. })
+                if self.crc.kept > 0 && matches!(self.state, NoteState::Request) =>
+            {
+                self.resend(delay)?;
+                Ok(false)
+            }
+            r => r,
+        }
+    }
+
+    /// Retransmit the request kept in the buffer, with a new sequence number.
+    #[cfg(feature = "crc")]
+    fn resend(&mut self, delay: &mut impl DelayMs<u16>) -> Result<(), NoteError> {
+        let seq = self.crc.seq;
+        self.crc.seq = self.crc.seq.wrapping_add(1);
+        self.crc.resent += 1;
+        self.stats.retries = self.stats.retries.wrapping_add(1);
+        warn!(
+            "note: response failed the crc check, retransmitting ({})..",
+            self.crc.resent
+        );
+
+        self.buf.truncate(self.crc.kept);
+        crc::renumber(&mut self.buf, seq)?;
+        self.crc.pending = Some(seq);
+
+        self.transmit(delay)
+    }
+
+    /// Give up the request kept at the start of the buffer for retransmission, leaving only the
+    /// response.
+    fn release_request(&mut self) {
+        #[cfg(feature = "crc")]
+        {
+            let kept = core::mem::take(&mut self.crc.kept).min(self.buf.len());
+            if kept > 0 {
+                let len = self.buf.len();
+                self.buf.copy_within(kept.., 0);
+                self.buf.truncate(len - kept);
+            }
+        }
+    }
+
     /// Read any remaining data from the Notecarrier. This will cancel any waiting responses, and
     /// waiting for a response after this call will time-out.
     unsafe fn consume_response(&mut self, delay: &mut impl DelayMs<u16>) -> Result<(), NoteError> {
@@ -383,19 +1322,29 @@ impl<IOM: Write<SevenBitAddress> + Read<SevenBitAddress>, const BUF_SIZE: usize>
         let mut waited = 0;
 
         while waited < self.response_timeout {
-            if matches!(self.poll()?, Some(_)) {
-                self.buf.clear();
-                return Ok(());
+            match self.poll() {
+                Ok(Some(_)) => {
+                    self.buf.clear();
+                    return Ok(());
+                }
+                Ok(None) => (),
+                // Discarded anyway, continue reading into an empty buffer.
+                Err(NoteError::ResponseTooLarge { .. }) => {
+                    self.buf.clear();
+                    continue;
+                }
+                Err(e) => return Err(e),
             }
 
-            delay.delay_ms(RESPONSE_DELAY);
-            waited += RESPONSE_DELAY;
+            delay.delay_ms(self.poll_interval);
+            waited += self.poll_interval as u32;
         }
 
         self.buf.clear();
+        self.stats.timeouts = self.stats.timeouts.wrapping_add(1);
 
         error!("response timed out (>= {}).", self.response_timeout);
-        Err(NoteError::TimeOut)
+        Err(NoteError::timeout(&self.name))
     }
 
     /// Reset notecard driver and state. Any waiting responses will be invalidated
@@ -403,81 +1352,238 @@ impl<IOM: Write<SevenBitAddress> + Read<SevenBitAddress>, const BUF_SIZE: usize>
     /// dropped the `FutureResponse`.
     pub fn reset(&mut self, delay: &mut impl DelayMs<u16>) -> Result<(), NoteError> {
         warn!("resetting: consuming any left-over response and perform a new handshake.");
+        self.sync_transport(delay)
+    }
 
-        self.buf.clear(); // clear in case data_query() is 0.
+    /// Discard anything the Notecard has pending until nothing more arrives, e.g. the response
+    /// to a request made before a warm reboot of the host, and make the driver ready for a new
+    /// request. This is done by [`Notecard::initialize`].
+    pub fn sync_transport(&mut self, delay: &mut impl DelayMs<u16>) -> Result<(), NoteError> {
+        self.buf.clear();
+        self.response = false;
         self.state = NoteState::Handshake;
-        self.handshake(delay)
+
+        self.discard_pending(delay)?;
+
+        self.state = NoteState::Request;
+        Ok(())
     }
 
-    fn handshake(&mut self, delay: &mut impl DelayMs<u16>) -> Result<(), NoteError> {
-        if matches!(self.state, NoteState::Handshake) {
-            debug!("note: handshake");
-            if self.data_query()? > 0 {
-                error!("note: handshake: remaining data in queue, consuming..");
-                unsafe { self.consume_response(delay)? };
+    /// Read and discard data from the Notecard until it has been idle for [`DRAIN_IDLE`] ms.
+    fn discard_pending(&mut self, delay: &mut impl DelayMs<u16>) -> Result<(), NoteError> {
+        let mut bytes = [0u8; 128];
+        let mut idle = 0;
+
+        while idle < DRAIN_IDLE {
+            let avail = self.transport.available()?;
+
+            if avail > 0 {
+                let sz = bytes.len().min(avail);
+                let (n, _) = self.transport.read(&mut bytes[..sz])?;
+                read_chunk(&bytes[..sz], n)?;
+                self.stats.bytes_in = self.stats.bytes_in.wrapping_add(n as u32);
+                debug!("note: discarded {} bytes.", n);
+                idle = 0;
+            } else {
+                delay.delay_ms(RESPONSE_DELAY);
+                idle += RESPONSE_DELAY;
             }
-
-            self.state = NoteState::Request;
         }
+
         Ok(())
     }
 
-    /// Sends request from buffer.
-    fn send_request(&mut self, delay: &mut impl DelayMs<u16>) -> Result<(), NoteError> {
-        // This is presumably limited by the notecard firmware.
-        const CHUNK_LENGTH_MAX: usize = 127;
-        // This is a limit that was required on some Arduinos. Can probably be increased up to
-        // `CHUNK_LENGTH_MAX`. Should maybe be configurable.
-        const CHUNK_LENGTH_I: usize = 30;
-        const CHUNK_LENGTH: usize = if CHUNK_LENGTH_I < CHUNK_LENGTH_MAX {
-            CHUNK_LENGTH_I
-        } else {
-            CHUNK_LENGTH_MAX
-        };
+    /// Resynchronize with the Notecard after a failed transfer: the transport is reset, any
+    /// partially sent request is terminated, and any pending response is discarded. This is done
+    /// automatically before the next request if a transfer fails.
+    pub fn recover(&mut self, delay: &mut impl DelayMs<u16>) -> Result<(), NoteError> {
+        warn!("note: recovering: resynchronizing with notecard.");
+
+        self.buf.clear();
+        self.response = false;
+        self.state = NoteState::Desync;
+        self.transport.recover();
 
-        // `note-c` uses `250` for `SEGMENT_LENGTH`. Round to closest divisible
-        // by CHUNK_LENGTH so that we don't end up with unnecessarily fragmented
-        // chunks. https://github.com/blues/note-c/blob/master/n_lib.h#L40 .
-        const SEGMENT_LENGTH: usize = (250 / CHUNK_LENGTH) * CHUNK_LENGTH;
+        // Terminate any partially sent request, the Notecard will respond to it with an error.
+        self.transport.write(b"\n")?;
+        self.stats.bytes_out = self.stats.bytes_out.wrapping_add(1);
+        delay.delay_ms(self.segment_delay);
 
-        if !matches!(self.state, NoteState::Request) {
-            warn!("note: request: wrong-state, resetting before new request.");
-            self.reset(delay)?;
+        // Discard responses until nothing more arrives.
+        self.discard_pending(delay)?;
+
+        self.state = NoteState::Request;
+        Ok(())
+    }
+
+    /// Discard the response to a request when its [`FutureResponse`] was dropped before the
+    /// response was read (e.g. by returning early with `?`), waiting up to the response timeout
+    /// for the response to arrive. This is done automatically before the next request.
+    pub fn drain(&mut self, delay: &mut impl DelayMs<u16>) -> Result<(), NoteError> {
+        self.response = false;
+
+        match self.state {
+            NoteState::Poll(_) | NoteState::Response(_) => {
+                warn!("note: discarding response to abandoned request.");
+
+                match unsafe { self.consume_response(delay) } {
+                    Ok(()) => Ok(()),
+                    // The response was read, but did not pass the `crc` check.
+                    Err(_) if matches!(self.state, NoteState::Request) => Ok(()),
+                    Err(e) => {
+                        self.state = NoteState::Desync;
+                        Err(e)
+                    }
+                }
+            }
+            NoteState::ResponseReady => {
+                self.buf.clear();
+                self.state = NoteState::Request;
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Make the driver ready for a new request, before the request is written to `buf`.
+    fn prepare(&mut self, delay: &mut impl DelayMs<u16>) -> Result<(), NoteError> {
+        self.response = false;
+
+        match self.state {
+            NoteState::Request => Ok(()),
+            NoteState::Desync => self.recover(delay),
+            NoteState::Poll(_) | NoteState::Response(_) | NoteState::ResponseReady => {
+                self.drain(delay)
+            }
+            NoteState::Handshake => {
+                warn!("note: request: wrong-state, resetting before new request.");
+                self.reset(delay)
+            }
         }
+    }
 
+    /// Sends request from buffer.
+    fn send_request(&mut self, delay: &mut impl DelayMs<u16>) -> Result<(), NoteError> {
         if self.buf.last() != Some(&b'\n') {
             return Err(NoteError::InvalidRequest);
         }
 
-        trace!("note: making request: {}", unsafe {
-            core::str::from_utf8_unchecked(&self.buf)
-        });
+        self.begin_request()?;
+
+        // Binary data following the request is not covered by the `crc`.
+        #[cfg(feature = "crc")]
+        if self.crc.enabled
+            && !self.command
+            && self.buf.iter().filter(|c| **c == b'\n').count() == 1
+        {
+            crc::add(&mut self.buf, self.crc.seq)?;
+            self.crc.pending = Some(self.crc.seq);
+            self.crc.seq = self.crc.seq.wrapping_add(1);
+            self.crc.kept = self.buf.len();
+        }
+
+        self.transmit(delay)
+    }
 
-        let mut buf = Vec::<u8, { CHUNK_LENGTH + 1 }>::new();
-        for segment in self.buf.chunks(SEGMENT_LENGTH) {
-            for c in segment.chunks(buf.capacity() - 1) {
-                buf.push(c.len() as u8).unwrap();
-                buf.extend_from_slice(c).unwrap();
+    /// Write the request in `buf`.
+    fn transmit(&mut self, delay: &mut impl DelayMs<u16>) -> Result<(), NoteError> {
+        self.announce_request();
+        wire!(">", &self.buf);
 
-                trace!("note: sending chunk: {} => {}", &buf, unsafe {
-                    core::str::from_utf8_unchecked(&buf)
-                });
+        let mut sent = 0;
+        while sent < self.buf.len() {
+            let sz = self.chunk_length.min(self.buf.len() - sent);
+            self.write_chunk(delay, sent, sz, &mut sent)?;
+        }
+
+        self.end_request(delay, sent);
+        Ok(())
+    }
 
-                self.i2c
-                    .write(self.addr, &buf)
-                    .map_err(|_| NoteError::I2cWriteError)?;
+    /// Check that a request can be sent, before sending the request in `buf`.
+    fn begin_request(&mut self) -> Result<(), NoteError> {
+        // `prepare` must have been called before the request was written to `buf`.
+        if !matches!(self.state, NoteState::Request) {
+            error!("note: request: wrong-state: {:?}", self.state);
+            return Err(NoteError::WrongState);
+        }
 
-                buf.clear();
-                delay.delay_ms(self.chunk_delay);
+        if self.command {
+            if !self.buf.starts_with(br#"{"req":"#) {
+                return Err(NoteError::InvalidRequest);
             }
-            delay.delay_ms(self.segment_delay);
+            self.buf[2..5].copy_from_slice(b"cmd");
+        }
+
+        #[cfg(feature = "crc")]
+        {
+            self.crc.kept = 0;
+            self.crc.resent = 0;
+        }
+
+        Ok(())
+    }
+
+    /// Keep the name of the request in `buf`, and pass the request to the observer.
+    fn announce_request(&mut self) {
+        self.name.clear();
+        for c in observer::request_name(&self.buf).chars() {
+            if self.name.push(c).is_err() {
+                break;
+            }
+        }
+
+        if let Some(o) = self.observer.as_mut() {
+            o.request(&self.name, &self.buf);
         }
+    }
 
-        self.state = NoteState::Poll(0);
+    /// Write `sz` bytes of `buf` starting at `start` as one chunk. `sent` is the number of bytes
+    /// of the request written so far, a segment is complete every `segment_length` bytes.
+    fn write_chunk(
+        &mut self,
+        delay: &mut impl DelayMs<u16>,
+        start: usize,
+        sz: usize,
+        sent: &mut usize,
+    ) -> Result<(), NoteError> {
+        trace!("note: sending chunk: {} bytes", sz);
+
+        let c = self
+            .buf
+            .get(start..start + sz)
+            .ok_or(NoteError::BufOverflow)?;
+        self.transport.write(c).inspect_err(|_| {
+            self.state = NoteState::Desync;
+        })?;
+        self.stats.bytes_out = self.stats.bytes_out.wrapping_add(sz as u32);
+        delay.delay_ms(self.chunk_delay);
+
+        *sent += sz;
+        if (*sent).is_multiple_of(self.segment_length) {
+            delay.delay_ms(self.segment_delay);
+        }
 
         Ok(())
     }
 
+    /// The whole request of `sent` bytes has been written.
+    fn end_request(&mut self, delay: &mut impl DelayMs<u16>, sent: usize) {
+        // The last segment may be partial.
+        if !sent.is_multiple_of(self.segment_length) {
+            delay.delay_ms(self.segment_delay);
+        }
+
+        self.stats.requests = self.stats.requests.wrapping_add(1);
+
+        // There is no response to commands.
+        self.state = if self.command {
+            NoteState::Request
+        } else {
+            NoteState::Poll(0)
+        };
+    }
+
     /// Make a raw request. The byte slice must end with `\n`. After making a request a
     /// [FutureResponse] must be created and consumed.
     pub(crate) fn request_raw(
@@ -485,12 +1591,12 @@ impl<IOM: Write<SevenBitAddress> + Read<SevenBitAddress>, const BUF_SIZE: usize>
         delay: &mut impl DelayMs<u16>,
         cmd: &[u8],
     ) -> Result<(), NoteError> {
+        self.prepare(delay)?;
+
         self.buf.clear();
         self.buf
-            .resize(cmd.len(), 0)
-            .map_err(|_| NoteError::BufOverflow)?;
-        let buf: &mut [u8] = self.buf.as_mut();
-        buf.copy_from_slice(cmd);
+            .extend_from_slice(cmd)
+            .map_err(|_| NoteError::RequestTooLarge(cmd.len()))?;
         self.send_request(delay)
     }
 
@@ -502,138 +1608,1635 @@ impl<IOM: Write<SevenBitAddress> + Read<SevenBitAddress>, const BUF_SIZE: usize>
         delay: &mut impl DelayMs<u16>,
         cmd: T,
     ) -> Result<(), NoteError> {
-        self.buf.clear();
-        self.buf.resize(self.buf.capacity(), 0).unwrap(); // unsafe { set_len } ?
+        self.prepare(delay)?;
 
-        let sz = serde_json_core::to_slice(&cmd, &mut self.buf).map_err(|_| NoteError::SerError)?;
-        self.buf.truncate(sz);
+        // Serialized in place, and sent from the buffer without any further copies.
+        self.buf.clear();
+        let capacity = self.buf.capacity();
+        self.buf
+            .append_with(|buf| serde_json_core::to_slice(&cmd, buf))
+            .map_err(|e| NoteError::from_ser(e, capacity))?;
 
         // Add new-line, this separator tells the Notecard that the request is done.
-        self.buf.push(b'\n').map_err(|_| NoteError::SerError)?;
+        let sz = self.buf.len();
+        self.buf
+            .push(b'\n')
+            .map_err(|_| NoteError::RequestTooLarge(sz + 1))?;
         self.send_request(delay)
     }
 
-    /// [card Requests](https://dev.blues.io/reference/notecard-api/card-requests/)
-    pub fn card(&mut self) -> card::Card<IOM, BUF_SIZE> {
-        card::Card::from(self)
+    /// Make a request that is not (yet) wrapped by this crate, and deserialize the response into
+    /// `Res`. The request must serialize to a JSON object with a `req` field.
+    ///
+    /// The request types in the `req` module of each request group can be sent this way too, e.g.
+    /// to set fields that the methods do not take. Their `new` constructors fill in the `req`
+    /// field and check the arguments the methods check:
+    ///
+    /// ```ignore
+    /// let req = hub::req::HubSet {
+    ///     mode: Some(hub::req::HubMode::Periodic),
+    ///     vinbound: Some("usb:60;high:120;normal:240"),
+    ///     ..hub::req::HubSet::new()
+    /// };
+    /// note.request_with_response::<_, hub::res::Empty>(&mut delay, req)?.wait(&mut delay)?;
+    /// ```
+    ///
+    /// ```ignore
+    /// #[derive(Serialize)]
+    /// struct Request {
+    ///     req: &'static str,
+    ///     mode: &'static str,
+    /// }
+    ///
+    /// #[derive(Deserialize)]
+    /// struct Response {
+    ///     mode: Option<heapless::String<20>>,
+    /// }
+    ///
+    /// let r: Response = note
+    ///     .request_with_response(&mut delay, Request { req: "card.new", mode: "on" })?
+    ///     .wait(&mut delay)?;
+    /// ```
+    pub fn request_with_response<Req: Serialize, Res: DeserializeOwned>(
+        &mut self,
+        delay: &mut impl DelayMs<u16>,
+        req: Req,
+    ) -> Result<FutureResponse<'_, Res, IOM, BUF_SIZE>, NoteError> {
+        self.request(delay, req)?;
+        Ok(FutureResponse::from(self))
     }
 
-    /// [note Requests](https://dev.blues.io/reference/notecard-api/note-requests/)
-    pub fn note(&mut self) -> note::Note<IOM, BUF_SIZE> {
-        note::Note::from(self)
-    }
+    /// Make a request followed by binary data (e.g. `card.binary.put`). The data is COBS encoded
+    /// and sent after the request, terminated by a new-line.
+    pub(crate) fn request_binary<T: Serialize>(
+        &mut self,
+        delay: &mut impl DelayMs<u16>,
+        cmd: T,
+        data: &[u8],
+    ) -> Result<(), NoteError> {
+        self.prepare(delay)?;
 
-    /// [hub Requests](https://dev.blues.io/reference/notecard-api/hub-requests/)
-    pub fn hub(&mut self) -> hub::Hub<IOM, BUF_SIZE> {
-        hub::Hub::from(self)
+        self.buf.clear();
+        let capacity = self.buf.capacity();
+        self.buf
+            .append_with(|buf| serde_json_core::to_slice(&cmd, buf))
+            .map_err(|e| NoteError::from_ser(e, capacity))?;
+
+        // The request is terminated by a new-line, then the encoded data follows with another
+        // new-line.
+        let size = self.buf.len() + 1 + cobs::encoded_len(data) + 1;
+        if size > capacity {
+            return Err(NoteError::RequestTooLarge(size));
+        }
+
+        self.buf.push(b'\n').map_err(|_| NoteError::BufOverflow)?;
+        self.buf
+            .append_with(|buf| cobs::encode(data, cobs::EOP, buf))?;
+        self.buf.push(b'\n').map_err(|_| NoteError::BufOverflow)?;
+        self.send_request(delay)
     }
-}
 
-/// A future response.
-///
-/// It will not be possible to make any new requests before this has been consumed. If you drop
-/// this future before consuming the response the Notecard and driver will be left in inconsistent
-/// state. It is not safe to make new requests to the Notecard before the previous response has
-/// been read.
-#[must_use = "The response must be waited for and consumed, otherwise the notecard is left in an inconsistent state"]
-pub struct FutureResponse<
-    'a,
-    T: DeserializeOwned,
-    IOM: Write<SevenBitAddress> + Read<SevenBitAddress>,
-    const BUF_SIZE: usize,
-> {
-    note: &'a mut Notecard<IOM, BUF_SIZE>,
-    _r: PhantomData<T>,
-}
+    /// Make a request with a `payload` field that is base64 encoded from `source` (e.g.
+    /// `note.add`). The request is serialized without the payload, and the payload is encoded
+    /// while the request is written to the transport. Only a chunk at a time is kept in the
+    /// buffer, so the request may be larger than the buffer.
+    pub(crate) fn request_payload<T: Serialize>(
+        &mut self,
+        delay: &mut impl DelayMs<u16>,
+        cmd: T,
+        source: &mut impl binary::BinarySource,
+    ) -> Result<(), NoteError> {
+        const FIELD: &[u8] = br#""payload":""#;
 
-impl<
-        'a,
-        T: DeserializeOwned,
-        IOM: Write<SevenBitAddress> + Read<SevenBitAddress>,
-        const BUF_SIZE: usize,
-    > FutureResponse<'a, T, IOM, BUF_SIZE>
-{
-    fn from(note: &'a mut Notecard<IOM, BUF_SIZE>) -> FutureResponse<'a, T, IOM, BUF_SIZE> {
-        FutureResponse {
-            note,
-            _r: PhantomData,
+        self.prepare(delay)?;
+
+        self.buf.clear();
+        let capacity = self.buf.capacity();
+        self.buf
+            .append_with(|buf| serde_json_core::to_slice(&cmd, buf))
+            .map_err(|e| NoteError::from_ser(e, capacity))?;
+
+        if self.buf.last() != Some(&b'}') {
+            return Err(NoteError::InvalidRequest);
         }
-    }
 
-    /// Reads remaining data and returns the deserialized object if it is ready.
-    pub fn poll(&mut self) -> Result<Option<T>, NoteError> {
-        match self.note.poll()? {
-            Some(body) if body.starts_with(br##"{"err":"##) => {
-                debug!(
-                    "response is error response, parsing error..: {}",
-                    core::str::from_utf8(&body).unwrap_or("[invalid utf-8]")
-                );
-                Err(serde_json_core::from_slice::<NotecardError>(body).map_or_else(
-                    |_| {
-                        error!(
-                            "failed to deserialize: {}",
-                            core::str::from_utf8(&body).unwrap_or("[invalid utf-8]")
-                        );
-                        NoteError::new_desererror(&body)
-                    },
-                    |(e, _)| NoteError::from(e),
-                ))
-            }
-            Some(body) => {
-                trace!("response is regular, parsing..");
-                Ok(Some(
-                    serde_json_core::from_slice::<T>(body)
-                        .map_err(|_| {
-                            error!(
-                                "failed to deserialize: {}",
-                                core::str::from_utf8(&body).unwrap_or("[invalid utf-8]")
-                            );
-                            NoteError::new_desererror(&body)
-                        })?
-                        .0,
-                ))
-            }
-            None => Ok(None),
+        // The payload is the last field, the closing brace follows it.
+        let comma = self.buf.len() > 2;
+        self.buf.truncate(self.buf.len() - 1);
+        if comma {
+            self.buf
+                .push(b',')
+                .map_err(|_| NoteError::RequestTooLarge(capacity + 1))?;
         }
-    }
+        self.buf
+            .extend_from_slice(FIELD)
+            .map_err(|_| NoteError::RequestTooLarge(capacity + FIELD.len()))?;
 
-    /// Wait for response and return raw bytes. These may change on next response,
-    /// so this method is probably not staying as it is.
-    pub fn wait_raw(mut self, delay: &mut impl DelayMs<u16>) -> Result<&'a [u8], NoteError> {
-        let mut waited = 0;
+        self.begin_request()?;
+        self.announce_request();
 
-        while waited < self.note.response_timeout {
-            match self.poll()? {
-                Some(_) => return Ok(self.note.take_response()?),
-                None => (),
-            }
+        #[cfg(feature = "crc")]
+        let mut crc = (self.crc.enabled && !self.command).then(|| {
+            let seq = self.crc.seq;
+            self.crc.seq = self.crc.seq.wrapping_add(1);
+            (seq, crc32fast::Hasher::new())
+        });
+
+        #[cfg(feature = "crc")]
+        if let Some((_, hasher)) = crc.as_mut() {
+            hasher.update(&self.buf);
+        }
+
+        wire!(">", &self.buf);
+
+        // Anything that fails from here on leaves a partial request with the Notecard.
+        let mut sent = 0;
+        let r = self.send_chunks(delay, &mut sent, b"");
+        let r = r.and_then(|_| {
+            payload::encode_from(source, |s| {
+                wire!(">", s.as_bytes());
 
-            delay.delay_ms(RESPONSE_DELAY);
-            waited += RESPONSE_DELAY;
+                #[cfg(feature = "crc")]
+                if let Some((_, hasher)) = crc.as_mut() {
+                    hasher.update(s.as_bytes());
+                }
+
+                self.send_chunks(delay, &mut sent, s.as_bytes())
+            })
+        });
+        if let Err(e) = r {
+            self.state = NoteState::Desync;
+            return Err(e);
+        }
+
+        // Close the payload and the request, with the `crc` of the request.
+        let mut end: heapless::Vec<u8, 32> = heapless::Vec::new();
+        end.push(b'"').map_err(|_| NoteError::BufOverflow)?;
+
+        #[cfg(feature = "crc")]
+        if let Some((seq, mut hasher)) = crc {
+            hasher.update(b"\"}");
+            end.push(b',').map_err(|_| NoteError::BufOverflow)?;
+            end.extend_from_slice(&crc::field(seq, hasher.finalize()))
+                .map_err(|_| NoteError::BufOverflow)?;
+            self.crc.pending = Some(seq);
+        }
+
+        end.extend_from_slice(b"}\n")
+            .map_err(|_| NoteError::BufOverflow)?;
+
+        wire!(">", &end);
+        let r = self
+            .send_chunks(delay, &mut sent, &end)
+            .and_then(|_| self.flush_chunks(delay, &mut sent));
+        if let Err(e) = r {
+            self.state = NoteState::Desync;
+            return Err(e);
         }
 
-        error!("response timed out (>= {}).", self.note.response_timeout);
-        Err(NoteError::TimeOut)
+        self.end_request(delay, sent);
+        Ok(())
     }
 
-    /// Wait for response and return deserialized object.
-    pub fn wait(mut self, delay: &mut impl DelayMs<u16>) -> Result<T, NoteError> {
-        let mut waited = 0;
+    /// Append `data` to the part of the request in `buf` that has not been written yet, and write
+    /// every complete chunk.
+    fn send_chunks(
+        &mut self,
+        delay: &mut impl DelayMs<u16>,
+        sent: &mut usize,
+        mut data: &[u8],
+    ) -> Result<(), NoteError> {
+        let chunk = self.chunk_length.min(self.buf.capacity());
+
+        loop {
+            while self.buf.len() >= chunk {
+                self.write_chunk(delay, 0, chunk, sent)?;
+                let len = self.buf.len();
+                self.buf.copy_within(chunk.., 0);
+                self.buf.truncate(len - chunk);
+            }
 
-        while waited < self.note.response_timeout {
-            match self.poll()? {
-                Some(r) => return Ok(r),
-                None => (),
+            if data.is_empty() {
+                return Ok(());
             }
 
-            delay.delay_ms(RESPONSE_DELAY);
-            waited += RESPONSE_DELAY;
+            let sz = data.len().min(self.buf.capacity() - self.buf.len());
+            self.buf
+                .extend_from_slice(&data[..sz])
+                .map_err(|_| NoteError::BufOverflow)?;
+            data = &data[sz..];
+        }
+    }
+
+    /// Write the rest of the request in `buf`.
+    fn flush_chunks(
+        &mut self,
+        delay: &mut impl DelayMs<u16>,
+        sent: &mut usize,
+    ) -> Result<(), NoteError> {
+        self.send_chunks(delay, sent, b"")?;
+
+        if !self.buf.is_empty() {
+            self.write_chunk(delay, 0, self.buf.len(), sent)?;
+            self.buf.clear();
         }
 
-        error!("response timed out (>= {}).", self.note.response_timeout);
-        Err(NoteError::TimeOut)
+        Ok(())
     }
-}
 
-#[cfg(test)]
-mod tests {}
+    /// [card Requests](https://dev.blues.io/reference/notecard-api/card-requests/)
+    pub fn card(&mut self) -> card::Card<'_, IOM, BUF_SIZE> {
+        card::Card::from(self)
+    }
+
+    /// [note Requests](https://dev.blues.io/reference/notecard-api/note-requests/)
+    pub fn note(&mut self) -> note::Note<'_, IOM, BUF_SIZE> {
+        note::Note::from(self)
+    }
+
+    /// [hub Requests](https://dev.blues.io/reference/notecard-api/hub-requests/)
+    pub fn hub(&mut self) -> hub::Hub<'_, IOM, BUF_SIZE> {
+        hub::Hub::from(self)
+    }
+
+    /// [dfu Requests](https://dev.blues.io/reference/notecard-api/dfu-requests/)
+    pub fn dfu(&mut self) -> dfu::Dfu<'_, IOM, BUF_SIZE> {
+        dfu::Dfu::from(self)
+    }
+
+    /// [ntn Requests](https://dev.blues.io/api-reference/notecard-api/ntn-requests/)
+    pub fn ntn(&mut self) -> ntn::Ntn<'_, IOM, BUF_SIZE> {
+        ntn::Ntn::from(self)
+    }
+
+    /// [var Requests](https://dev.blues.io/api-reference/notecard-api/var-requests/)
+    pub fn var(&mut self) -> var::Var<'_, IOM, BUF_SIZE> {
+        var::Var::from(self)
+    }
+
+    /// [web Requests](https://dev.blues.io/reference/notecard-api/web-requests/)
+    pub fn web(&mut self) -> web::Web<'_, IOM, BUF_SIZE> {
+        web::Web::from(self)
+    }
+}
+
+/// A delay from a closure, e.g. to yield to a scheduler or enter a low-power mode while
+/// waiting for the Notecard:
+///
+/// ```ignore
+/// let mut delay = DelayFn(|ms| rtos::sleep_ms(ms as u32));
+/// note.card().time(&mut delay)?.wait(&mut delay)?;
+/// ```
+pub struct DelayFn<F: FnMut(u16)>(pub F);
+
+impl<F: FnMut(u16)> DelayMs<u16> for DelayFn<F> {
+    fn delay_ms(&mut self, ms: u16) {
+        (self.0)(ms)
+    }
+}
+
+/// Deserialize a response from the Notecard, or the error if it is an error response.
+pub(crate) fn deserialize_response<'de, T: Deserialize<'de>>(
+    body: &'de [u8],
+) -> Result<T, NoteError> {
+    /// The `err` field of a response.
+    #[derive(Deserialize)]
+    struct ErrorField<'a> {
+        err: Option<&'a str>,
+    }
+
+    // Any response with an `err` field is an error response, wherever the field is (as in
+    // `note-c`).
+    if body.windows(5).any(|w| w == br#""err""#) {
+        if let Ok((ErrorField { err: Some(err) }, _)) =
+            serde_json_core::from_slice::<ErrorField>(body)
+        {
+            debug!("response is error response: {}", err);
+            return Err(NotecardError::new(err).into());
+        }
+    }
+
+    trace!("response is regular, parsing..");
+    de::from_slice::<T>(body).map_err(|e| {
+        error!(
+            "failed to deserialize: {}",
+            core::str::from_utf8(body).unwrap_or("[invalid utf-8]")
+        );
+
+        match e {
+            de::Error::Overflow => NoteError::FieldOverflow { req: String::new() },
+            de::Error::Other => NoteError::new_desererror(body),
+        }
+    })
+}
+
+/// The part of `bytes` filled by a transport read that returned `n`, which must not be more than
+/// was asked for.
+pub(crate) fn read_chunk(bytes: &[u8], n: usize) -> Result<&[u8], NoteError> {
+    bytes.get(..n).ok_or_else(|| {
+        error!("read: got {} bytes, asked for {}", n, bytes.len());
+        NoteError::BufOverflow
+    })
+}
+
+/// The start of `s` that fits in `N` bytes.
+pub(crate) fn truncated<const N: usize>(s: &str) -> String<N> {
+    let mut end = s.len().min(N);
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+
+    let mut t = String::new();
+    t.push_str(&s[..end]).ok();
+    t
+}
+
+/// Copy the argument `s` into a bounded string, failing with [`NoteError::ArgumentTooLong`] if it
+/// does not fit.
+pub(crate) fn to_string<const N: usize>(s: &str) -> Result<String<N>, NoteError> {
+    let mut out = String::new();
+    out.push_str(s).map_err(|_| {
+        error!("argument too long: {} > {} bytes", s.len(), N);
+        NoteError::ArgumentTooLong {
+            len: s.len(),
+            capacity: N,
+        }
+    })?;
+    Ok(out)
+}
+
+/// A future response.
+///
+/// It will not be possible to make any new requests before this has been consumed. If you drop
+/// this future before consuming the response the Notecard and driver will be left in inconsistent
+/// state. It is not safe to make new requests to the Notecard before the previous response has
+/// been read.
+#[must_use = "The response must be waited for and consumed, otherwise the notecard is left in an inconsistent state"]
+pub struct FutureResponse<
+    'a,
+    T: DeserializeOwned,
+    IOM: NotecardTransport,
+    const BUF_SIZE: usize,
+> {
+    note: &'a mut Notecard<IOM, BUF_SIZE>,
+    _r: PhantomData<fn() -> T>,
+}
+
+impl<
+        'a,
+        T: DeserializeOwned,
+        IOM: NotecardTransport,
+        const BUF_SIZE: usize,
+    > FutureResponse<'a, T, IOM, BUF_SIZE>
+{
+    fn from(note: &'a mut Notecard<IOM, BUF_SIZE>) -> FutureResponse<'a, T, IOM, BUF_SIZE> {
+        FutureResponse {
+            note,
+            _r: PhantomData,
+        }
+    }
+
+    /// Reads remaining data and returns the deserialized object if it is ready, otherwise
+    /// [`nb::Error::WouldBlock`]. Call again (e.g. from a superloop) until the response is
+    /// ready, no new requests can be made in the meantime.
+    ///
+    /// ```ignore
+    /// let mut time = note.card().time(&mut delay)?;
+    ///
+    /// loop {
+    ///     match time.poll() {
+    ///         Ok(t) => break t,
+    ///         Err(nb::Error::WouldBlock) => do_other_work(),
+    ///         Err(nb::Error::Other(e)) => return Err(e),
+    ///     }
+    /// }
+    /// ```
+    pub fn poll(&mut self) -> nb::Result<T, NoteError> {
+        match self.note.poll()? {
+            Some(_) => Ok(self.deserialize()?),
+            None => Err(nb::Error::WouldBlock),
+        }
+    }
+
+    /// [`FutureResponse::poll`], retransmitting the request if the response is corrupted.
+    fn poll_resend(&mut self, delay: &mut impl DelayMs<u16>) -> nb::Result<T, NoteError> {
+        match self.note.poll_resend(delay)? {
+            true => Ok(self.deserialize()?),
+            false => Err(nb::Error::WouldBlock),
+        }
+    }
+
+    /// Deserialize the response in the buffer.
+    fn deserialize(&mut self) -> Result<T, NoteError> {
+        let Notecard {
+            buf, stats, name, ..
+        } = &mut *self.note;
+        let r = deserialize_response(buf).map_err(|e| e.with_req(name));
+        stats.deserialized(&r);
+        r
+    }
+
+    /// Abandon the request, and discard the response when it arrives.
+    pub fn abandon(self, delay: &mut impl DelayMs<u16>) -> Result<(), NoteError> {
+        self.note.drain(delay)
+    }
+
+    /// Check once if the response is ready without blocking or consuming the future, returns
+    /// `Ok(None)` if it is not ready yet. Call again (e.g. once per iteration of a superloop)
+    /// until the response is returned, no new requests can be made in the meantime.
+    pub fn try_wait(&mut self) -> Result<Option<T>, NoteError> {
+        match self.poll() {
+            Ok(r) => Ok(Some(r)),
+            Err(nb::Error::WouldBlock) => Ok(None),
+            Err(nb::Error::Other(e)) => Err(e),
+        }
+    }
+
+    /// Wait for response and return raw bytes. These may change on next response,
+    /// so this method is probably not staying as it is.
+    pub fn wait_raw(self, delay: &mut impl DelayMs<u16>) -> Result<&'a [u8], NoteError> {
+        let note = self.wait_ready(delay)?;
+        Ok(&note.buf)
+    }
+
+    /// Wait for the response to be read into the buffer of the driver.
+    fn wait_ready(
+        self,
+        delay: &mut impl DelayMs<u16>,
+    ) -> Result<&'a mut Notecard<IOM, BUF_SIZE>, NoteError> {
+        let note = self.note;
+        let mut waited = 0;
+
+        while waited < note.response_timeout {
+            if note.poll_resend(delay)? {
+                return Ok(note);
+            }
+
+            delay.delay_ms(note.poll_interval);
+            waited += note.poll_interval as u32;
+        }
+
+        note.stats.timeouts = note.stats.timeouts.wrapping_add(1);
+
+        error!("response timed out (>= {}).", note.response_timeout);
+        Err(NoteError::timeout(&note.name))
+    }
+
+    /// Wait for the response and pass it to `sink` in chunks as it is read, without reading it
+    /// into the buffer of the driver, e.g. for responses that are larger than the buffer. The
+    /// response is not deserialized. Returns the length of the response, see
+    /// [`Notecard::read_response_with`].
+    ///
+    /// ```ignore
+    /// let mut decoder = payload::Decoder::new(|chunk| flash.write(chunk));
+    /// note.dfu()
+    ///     .get(&mut delay, 4096, Some(offset))?
+    ///     .wait_streaming(&mut delay, |chunk| decoder.write(chunk))?;
+    /// let written = decoder.finish()?;
+    /// ```
+    pub fn wait_streaming(
+        self,
+        delay: &mut impl DelayMs<u16>,
+        sink: impl FnMut(&[u8]) -> Result<(), NoteError>,
+    ) -> Result<usize, NoteError> {
+        let note = self.note;
+        let mut waited = 0;
+
+        while waited < note.response_timeout {
+            if let NoteState::Poll(tries) = note.state {
+                if note.data_query()? == 0 {
+                    note.state = NoteState::Poll(tries + 1);
+                }
+            }
+
+            if !matches!(note.state, NoteState::Poll(_)) {
+                return note.read_response_with(sink);
+            }
+
+            delay.delay_ms(note.poll_interval);
+            waited += note.poll_interval as u32;
+        }
+
+        note.stats.timeouts = note.stats.timeouts.wrapping_add(1);
+
+        error!("response timed out (>= {}).", note.response_timeout);
+        Err(NoteError::timeout(&note.name))
+    }
+
+    /// Wait for the response and return the deserialized object along with the raw JSON of the
+    /// response, e.g. to log fields that are not part of the response type.
+    pub fn wait_with_raw(self, delay: &mut impl DelayMs<u16>) -> Result<(T, &'a [u8]), NoteError> {
+        let Notecard {
+            buf, stats, name, ..
+        } = self.wait_ready(delay)?;
+        let r = deserialize_response(buf).map_err(|e| e.with_req(name));
+        stats.deserialized(&r);
+        Ok((r?, buf))
+    }
+
+    /// Wait for the response and deserialize it into `R`, which may borrow strings from the
+    /// receive buffer instead of copying them. Response types with string fields that can be
+    /// borrowed take the string type as a parameter, e.g. `card::res::Time<&str>`. The response
+    /// borrows the driver, so no new requests can be made while it is in use.
+    ///
+    /// ```ignore
+    /// let t: card::res::Time<&str> = note.card().time(&mut delay)?.wait_borrowed(&mut delay)?;
+    /// info!("zone: {:?}", t.zone);
+    /// ```
+    ///
+    /// Escape sequences (e.g. `\"`) in borrowed strings are not decoded.
+    pub fn wait_borrowed<R: Deserialize<'a>>(
+        self,
+        delay: &mut impl DelayMs<u16>,
+    ) -> Result<R, NoteError> {
+        let Notecard {
+            buf, stats, name, ..
+        } = self.wait_ready(delay)?;
+        let buf: &'a [u8] = buf;
+        let r = deserialize_response(buf).map_err(|e| e.with_req(name));
+        stats.deserialized(&r);
+        r
+    }
+
+    /// Wait for response and return deserialized object.
+    pub fn wait(self, delay: &mut impl DelayMs<u16>) -> Result<T, NoteError> {
+        let timeout = self.note.response_timeout;
+        self.wait_timeout(delay, timeout)
+    }
+
+    /// Wait up to `timeout` ms for the response and return deserialized object, regardless of
+    /// the response timeout of the driver. Fails with [`NoteError::TimeOut`] if the response
+    /// is not ready in time.
+    pub fn wait_timeout(
+        mut self,
+        delay: &mut impl DelayMs<u16>,
+        timeout: u32,
+    ) -> Result<T, NoteError> {
+        let mut waited = 0;
+
+        loop {
+            match self.poll_resend(delay) {
+                Ok(r) => return Ok(r),
+                Err(nb::Error::WouldBlock) => (),
+                Err(nb::Error::Other(e)) => return Err(e),
+            }
+
+            if waited >= timeout {
+                break;
+            }
+
+            delay.delay_ms(self.note.poll_interval);
+            waited += self.note.poll_interval as u32;
+        }
+
+        self.note.stats.timeouts = self.note.stats.timeouts.wrapping_add(1);
+
+        error!("response timed out (>= {}).", timeout);
+        Err(NoteError::timeout(&self.note.name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Default config, but without `crc` so that requests are predictable.
+    pub(crate) fn config() -> NotecardConfig {
+        NotecardConfig {
+            #[cfg(feature = "crc")]
+            crc: false,
+            ..NotecardConfig::default()
+        }
+    }
+
+    #[test]
+    fn chunking() {
+        assert_eq!(NotecardConfig::default().chunking(), (30, 240));
+
+        let c = NotecardConfig {
+            chunk_length: 200,
+            segment_length: 250,
+            ..NotecardConfig::default()
+        };
+        assert_eq!(c.chunking(), (127, 127));
+
+        let c = NotecardConfig {
+            chunk_length: 0,
+            segment_length: 0,
+            ..NotecardConfig::default()
+        };
+        assert_eq!(c.chunking(), (1, 1));
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn delay_fn() {
+        let mock = mock::MockTransport::new().expect_req("card.time", r#"{"time":1599769214}"#);
+        let mut waited = 0u32;
+        let mut delay = DelayFn(|ms| waited += ms as u32);
+
+        let mut note: Notecard<_, 1024> = Notecard::new_with_transport(mock, config())
+            .initialize(&mut delay)
+            .unwrap();
+
+        note.card().time(&mut delay).unwrap().wait(&mut delay).unwrap();
+
+        // Draining the transport when initializing, one chunk and segment delay for the request,
+        // the response is ready immediately.
+        assert_eq!(waited, DRAIN_IDLE as u32 + 20 + 250);
+    }
+
+    #[test]
+    fn response_timeout() {
+        // The Notecard never responds.
+        let replay = transport::record::ReplayTransport::new(b"> {\"req\":\"card.time\"}\n");
+        let mut waited = 0u32;
+        let mut delay = DelayFn(|ms| waited += ms as u32);
+
+        let mut note: Notecard<_, 1024> = Notecard::new_with_transport(replay, config())
+            .initialize(&mut delay)
+            .unwrap();
+
+        note.set_response_timeout(100_000);
+
+        assert!(matches!(
+            note.card().time(&mut delay).unwrap().wait(&mut delay),
+            Err(NoteError::TimeOut { .. })
+        ));
+        assert_eq!(waited, DRAIN_IDLE as u32 + 20 + 250 + 100_000);
+        assert_eq!(note.stats().timeouts, 1);
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn retry() {
+        let mut note: Notecard<_, 1024> = mock::MockTransport::new()
+            .expect_req("card.time", r#"{"err":"i2c: timeout {io}"}"#)
+            .expect_req("card.time", r#"{"err":"i2c: timeout {io}"}"#)
+            .expect_req("card.time", r#"{"time":1599769214}"#)
+            .expect_req("card.time", r#"{"err":"time is not yet set {no-time}"}"#)
+            .notecard();
+
+        let mut waited = 0u32;
+        let mut delay = DelayFn(|ms| waited += ms as u32);
+
+        let t = note
+            .retry(&mut delay, |note, delay| note.card().time(delay)?.wait(delay))
+            .unwrap();
+        assert_eq!(t.time, Some(1599769214));
+
+        // Not retried.
+        assert!(matches!(
+            note.retry(&mut delay, |note, delay| note.card().time(delay)?.wait(delay)),
+            Err(NoteError::NotecardErr(_))
+        ));
+
+        note.transport().done();
+        assert_eq!(waited, 100 + 200);
+    }
+
+    /// Fails the first write.
+    #[cfg(feature = "mock")]
+    struct Flaky<T: NotecardTransport>(T, bool);
+
+    #[cfg(feature = "mock")]
+    impl<T: NotecardTransport> NotecardTransport for Flaky<T> {
+        fn ping(&mut self) -> bool {
+            self.0.ping()
+        }
+
+        fn write(&mut self, data: &[u8]) -> Result<(), NoteError> {
+            if !self.1 {
+                self.1 = true;
+                return Err(NoteError::I2cWriteError);
+            }
+            self.0.write(data)
+        }
+
+        fn available(&mut self) -> Result<usize, NoteError> {
+            self.0.available()
+        }
+
+        fn read(&mut self, buf: &mut [u8]) -> Result<(usize, usize), NoteError> {
+            self.0.read(buf)
+        }
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn recover_after_failed_write() {
+        let mock = mock::MockTransport::new().expect_req("card.time", r#"{"time":1599769214}"#);
+        let d = &mut mock::NoDelay;
+
+        let mut note: Notecard<_, 1024> =
+            Notecard::new_with_transport(Flaky(mock, false), NotecardConfig::default())
+                .initialize(d)
+                .unwrap();
+
+        assert!(matches!(
+            note.card().time(d),
+            Err(NoteError::I2cWriteError)
+        ));
+        assert!(matches!(note.state, NoteState::Desync));
+
+        let t = note.card().time(d).unwrap().wait(d).unwrap();
+        assert_eq!(t.time, Some(1599769214));
+
+        note.transport().0.done();
+    }
+
+    /// Has a response left over from before the host was reset.
+    #[cfg(feature = "mock")]
+    struct Stale<T: NotecardTransport>(T, &'static [u8]);
+
+    #[cfg(feature = "mock")]
+    impl<T: NotecardTransport> NotecardTransport for Stale<T> {
+        fn ping(&mut self) -> bool {
+            self.0.ping()
+        }
+
+        fn write(&mut self, data: &[u8]) -> Result<(), NoteError> {
+            self.0.write(data)
+        }
+
+        fn available(&mut self) -> Result<usize, NoteError> {
+            match self.1.len() {
+                0 => self.0.available(),
+                n => Ok(n),
+            }
+        }
+
+        fn read(&mut self, buf: &mut [u8]) -> Result<(usize, usize), NoteError> {
+            if self.1.is_empty() {
+                return self.0.read(buf);
+            }
+
+            let sz = buf.len().min(self.1.len());
+            buf[..sz].copy_from_slice(&self.1[..sz]);
+            self.1 = &self.1[sz..];
+            Ok((sz, self.1.len()))
+        }
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn drain_stale_response() {
+        let mock = mock::MockTransport::new().expect_req("card.time", r#"{"time":1599769214}"#);
+        let stale = Stale(mock, b"{\"status\":\"stale\"}\r\n{\"time\":1}\r\n");
+        let d = &mut mock::NoDelay;
+
+        let mut note: Notecard<_, 1024> = Notecard::new_with_transport(stale, config())
+            .initialize(d)
+            .unwrap();
+
+        assert!(note.transport().1.is_empty());
+
+        let t = note.card().time(d).unwrap().wait(d).unwrap();
+        assert_eq!(t.time, Some(1599769214));
+
+        note.transport().0.done();
+    }
+
+    /// Counts the queries for available bytes.
+    #[cfg(feature = "mock")]
+    struct Counting<T: NotecardTransport>(T, usize);
+
+    #[cfg(feature = "mock")]
+    impl<T: NotecardTransport> NotecardTransport for Counting<T> {
+        fn ping(&mut self) -> bool {
+            self.0.ping()
+        }
+
+        fn write(&mut self, data: &[u8]) -> Result<(), NoteError> {
+            self.0.write(data)
+        }
+
+        fn available(&mut self) -> Result<usize, NoteError> {
+            self.1 += 1;
+            self.0.available()
+        }
+
+        fn read(&mut self, buf: &mut [u8]) -> Result<(usize, usize), NoteError> {
+            self.0.read(buf)
+        }
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn read_response_at_once() {
+        let long = format!(r#"{{"err":"{}"}}"#, "x".repeat(200));
+        let mock = mock::MockTransport::new().expect_req("card.time", &long);
+        let d = &mut mock::NoDelay;
+
+        let mut note: Notecard<_, 1024> =
+            Notecard::new_with_transport(Counting(mock, 0), NotecardConfig::default())
+                .initialize(d)
+                .unwrap();
+
+        note.transport().1 = 0;
+
+        let mut r = note.card().time(d).unwrap();
+        assert!(matches!(r.poll(), Err(nb::Error::Other(NoteError::NotecardErr(_)))));
+
+        // The response is larger than a chunk, but only queried for once.
+        assert_eq!(note.transport().1, 1);
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn command() {
+        let mut note: Notecard<_, 1024> = mock::MockTransport::new()
+            .expect(r#"{"cmd":"hub.sync"}"#, "")
+            .expect(r#"{"req":"hub.sync"}"#, "{}")
+            .notecard();
+        let d = &mut mock::NoDelay;
+
+        note.command(d, |note, d| note.hub().sync(d, false)).unwrap();
+
+        note.hub().sync(d, false).unwrap().wait(d).unwrap();
+        note.transport().done();
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn custom_request() {
+        #[derive(Serialize)]
+        struct Request {
+            req: &'static str,
+            mode: &'static str,
+        }
+
+        #[derive(Deserialize)]
+        struct Response {
+            mode: Option<String<20>>,
+        }
+
+        let mut note: Notecard<_, 1024> = mock::MockTransport::new()
+            .expect(r#"{"req":"card.new","mode":"on"}"#, r#"{"mode":"on"}"#)
+            .notecard();
+        let d = &mut mock::NoDelay;
+
+        let r: Response = note
+            .request_with_response(
+                d,
+                Request {
+                    req: "card.new",
+                    mode: "on",
+                },
+            )
+            .unwrap()
+            .wait(d)
+            .unwrap();
+        assert_eq!(r.mode.as_deref(), Some("on"));
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn compose_request() {
+        let mut note: Notecard<_, 1024> = mock::MockTransport::new()
+            .expect(r#"{"req":"card.temp","minutes":5}"#, r#"{"value":21.5}"#)
+            .expect(
+                r#"{"req":"note.get","file":"settings.db","note":"a","delete":true,"deleted":false}"#,
+                r#"{"note":"a"}"#,
+            )
+            .notecard();
+        let d = &mut mock::NoDelay;
+
+        let req = card::req::Temp {
+            minutes: Some(5),
+            ..card::req::Temp::new()
+        };
+        let t: card::res::Temp = note.request_with_response(d, req).unwrap().wait(d).unwrap();
+        assert_eq!(t.value, 21.5);
+
+        let req = note::req::Get {
+            delete: true,
+            ..note::req::Get::new("settings.db", "a").unwrap()
+        };
+        note.request_with_response::<_, note::res::Get<()>>(d, req)
+            .unwrap()
+            .wait(d)
+            .unwrap();
+
+        assert!(matches!(
+            note::req::Get::new("settings", "a"),
+            Err(NoteError::InvalidArgument)
+        ));
+
+        // Requests borrow from the input when deserialized, which need not be `'static`.
+        let json = std::string::String::from(r#"{"req":"hub.set","product":"com.blues.test"}"#);
+        let (r, _) = serde_json_core::from_str::<hub::req::HubSet>(&json).unwrap();
+        assert_eq!(r.req, "hub.set");
+        assert_eq!(r.product, Some("com.blues.test"));
+
+        note.transport().done();
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn wait_borrowed() {
+        let mut note: Notecard<_, 1024> = mock::MockTransport::new()
+            .expect_req("card.time", r#"{"time":1599769214,"zone":"CEST,Europe/Oslo"}"#)
+            .notecard();
+        let d = &mut mock::NoDelay;
+
+        let t: card::res::Time<&str> = note.card().time(d).unwrap().wait_borrowed(d).unwrap();
+        assert_eq!(t.time, Some(1599769214));
+        assert_eq!(t.zone, Some("CEST,Europe/Oslo"));
+        assert!(t.is_valid());
+    }
+
+    #[test]
+    fn notecard_error_kind() {
+        let (e, _) = serde_json_core::from_str::<NotecardError>(
+            r#"{"err":"note-i2c: i2c timeout {io}"}"#,
+        )
+        .unwrap();
+        assert_eq!(e.message(), "note-i2c: i2c timeout {io}");
+        assert_eq!(e.kind(), NotecardErrorKind::Io);
+        assert!(e.is_transient());
+
+        assert_eq!(
+            NotecardErrorKind::from_err("firmware update {dfu-not-ready} {io}"),
+            NotecardErrorKind::DfuNotReady
+        );
+        assert_eq!(
+            NotecardErrorKind::from_err("{unknown} binary corrupt {bad-bin}"),
+            NotecardErrorKind::BadBin
+        );
+        assert_eq!(
+            NotecardErrorKind::from_err("unknown request"),
+            NotecardErrorKind::Other
+        );
+        assert!(!NotecardErrorKind::Other.is_transient());
+
+        assert!(NoteError::NotecardErr(NotecardError::new("busy {card-busy}")).is_transient());
+        assert!(!NoteError::NotecardErr(NotecardError::new("{not-supported}")).is_transient());
+        assert!(matches!(
+            NoteError::from(
+                serde_json_core::from_str::<NotecardError>(r#"{"err":"{dfu-in-progress}"}"#)
+                    .unwrap()
+                    .0
+            ),
+            NoteError::DFUInProgress
+        ));
+    }
+
+    #[test]
+    fn error_response() {
+        #[derive(Deserialize, Debug)]
+        struct Response {
+            status: Option<String<20>>,
+        }
+
+        let r: Response = deserialize_response(br#"{"status":"{normal}"}"#).unwrap();
+        assert_eq!(r.status.as_deref(), Some("{normal}"));
+
+        // The error does not have to be the first field.
+        for body in [
+            &br#"{"err":"i2c: timeout {io}"}"#[..],
+            br#"{"status":"{normal}","err":"i2c: timeout {io}"}"#,
+            br#" { "err" : "i2c: timeout {io}" }"#,
+        ] {
+            match deserialize_response::<Response>(body) {
+                Err(NoteError::NotecardErr(e)) => {
+                    assert_eq!(e.message(), "i2c: timeout {io}");
+                    assert_eq!(e.kind(), NotecardErrorKind::Io);
+                }
+                r => panic!("not an error: {:?}", r),
+            }
+        }
+
+        // The kind is kept when the message is truncated.
+        let long = std::format!(r#"{{"err":"{} {{io}}"}}"#, "x".repeat(300));
+        match deserialize_response::<Response>(long.as_bytes()) {
+            Err(NoteError::NotecardErr(e)) => {
+                assert_eq!(e.message().len(), 64);
+                assert_eq!(e.kind(), NotecardErrorKind::Io);
+            }
+            r => panic!("not an error: {:?}", r),
+        }
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn error_request_name() {
+        let mut note: Notecard<_, 1024> = mock::MockTransport::new()
+            .expect(r#"{"req":"hub.sync"}"#, r#"{"err":"hub: not configured {io}"}"#)
+            .expect_req("card.time", r#"{"time":"soon"}"#)
+            .notecard();
+        let d = &mut mock::NoDelay;
+
+        match note.hub().sync(d, false).unwrap().wait(d) {
+            Err(e @ NoteError::NotecardErr(_)) => assert_eq!(e.req(), "hub.sync"),
+            r => panic!("not an error: {:?}", r),
+        }
+
+        match note.card().time(d).unwrap().wait(d) {
+            Err(e @ NoteError::DeserError { .. }) => assert_eq!(e.req(), "card.time"),
+            r => panic!("not a deserialization error: {:?}", r),
+        }
+
+        assert_eq!(NoteError::InvalidArgument.req(), "");
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn escaped_strings() {
+        #[derive(Serialize, Default)]
+        struct Body<'a> {
+            text: &'a str,
+        }
+
+        // Quotes, backslashes and control characters must not end the string or the request.
+        let s = "a\"b\\c\td\ne\r\u{1}é";
+        let e = r#"a\"b\\c\td\ne\r\u0001é"#;
+
+        let mock = mock::MockTransport::new()
+            .expect(
+                &format!(r#"{{"req":"hub.set","sn":"{e}","voutbound":"{e}"}}"#),
+                "{}",
+            )
+            .expect(
+                &format!(r#"{{"req":"hub.log","text":"{e}","alert":false,"sync":false}}"#),
+                "{}",
+            )
+            .expect(
+                &format!(
+                    r#"{{"req":"note.add","file":"x.qo","note":"{e}","body":{{"text":"{e}"}},"sync":false}}"#
+                ),
+                "{}",
+            )
+            .expect(&format!(r#"{{"req":"var.set","name":"{e}","text":"{e}"}}"#), "{}");
+        let d = &mut mock::NoDelay;
+        let mut note: Notecard<_> = Notecard::new_with_transport(mock, config())
+            .initialize(d)
+            .unwrap();
+
+        note.hub()
+            .set_builder()
+            .sn(s)
+            .voutbound(s)
+            .send(d)
+            .unwrap()
+            .wait(d)
+            .unwrap();
+        note.hub().log(d, s, false, false).unwrap().wait(d).unwrap();
+        note.note()
+            .add(d, Some("x.qo"), Some(s), Some(Body { text: s }), None, false)
+            .unwrap()
+            .wait(d)
+            .unwrap();
+        note.var()
+            .set(d, s, None, var::Value::Text(s), false)
+            .unwrap()
+            .wait(d)
+            .unwrap();
+
+        note.transport().done();
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn request_too_large() {
+        #[derive(Serialize)]
+        struct Request {
+            req: &'static str,
+        }
+
+        let mock = mock::MockTransport::new()
+            .expect(r#"{"req":"card.time"}"#, r#"{"time":1599769214}"#);
+        let d = &mut mock::NoDelay;
+        let mut note: Notecard<_, 32> = Notecard::new_with_transport(mock, config())
+            .initialize(d)
+            .unwrap();
+
+        assert!(matches!(
+            note.request_raw(d, b"{\"req\":\"card.location.mode\",\"mode\":\"off\"}\n"),
+            Err(NoteError::RequestTooLarge(42))
+        ));
+        assert!(matches!(
+            note.request(d, Request { req: "card.location.mode.with.a.long.name" }),
+            Err(NoteError::RequestTooLarge(33))
+        ));
+        assert!(matches!(
+            note.request_binary(d, Request { req: "x" }, &[1u8; 20]),
+            Err(NoteError::RequestTooLarge(34))
+        ));
+
+        // Nothing was sent.
+        let t = note.card().time(d).unwrap().wait(d).unwrap();
+        assert_eq!(t.time, Some(1599769214));
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn response_too_large() {
+        let long = r#"{"time":1599769214,"zone":"CEST,Europe/Oslo","country":"NO","area":"Oslo"}"#;
+        let mock = mock::MockTransport::new()
+            .expect_req("card.time", long)
+            .expect_req("card.time", long)
+            .expect_req("card.time", r#"{"time":1599769215}"#);
+        let d = &mut mock::NoDelay;
+
+        let mut note: Notecard<_, 32> = Notecard::new_with_transport(mock, config())
+            .initialize(d)
+            .unwrap();
+
+        let needed = long.len() + 2;
+        assert!(matches!(
+            note.card().time(d).unwrap().wait(d),
+            Err(NoteError::ResponseTooLarge { needed: n, capacity: 32, .. }) if n == needed
+        ));
+
+        // Nothing is read if it does not fit, so it can be tried again.
+        let mut small = [0u8; 64];
+        assert!(matches!(
+            note.read_response_into(&mut small),
+            Err(NoteError::ResponseTooLarge { needed: n, capacity: 64, .. }) if n == needed
+        ));
+
+        let mut out = [0u8; 128];
+        let raw = note.read_response_into(&mut out).unwrap();
+        assert_eq!(raw, std::format!("{}\r\n", long).as_bytes());
+
+        let (t, _) = serde_json_core::from_slice::<card::res::Time>(raw).unwrap();
+        assert_eq!(t.time, Some(1599769214));
+
+        // Discarded before the next request.
+        assert!(matches!(
+            note.card().time(d).unwrap().wait(d),
+            Err(NoteError::ResponseTooLarge { .. })
+        ));
+        let t = note.card().time(d).unwrap().wait(d).unwrap();
+        assert_eq!(t.time, Some(1599769215));
+
+        note.transport().done();
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn stream_response() {
+        let data: std::vec::Vec<u8> = (0..600u32).map(|i| (i * 13) as u8).collect();
+        let mut b64 = [0u8; payload::encoded_len(600)];
+        let large = std::format!(r#"{{"payload":"{}"}}"#, payload::encode(&data, &mut b64).unwrap());
+
+        let mut note: Notecard<_, 256> = mock::MockTransport::new()
+            .expect_req("dfu.get", &large)
+            .expect_req("dfu.get", &large)
+            .expect_req("card.time", r#"{"time":1599769214}"#)
+            .expect_req("dfu.get", &large)
+            .expect_req("card.time", r#"{"time":1599769215}"#)
+            .notecard();
+        let d = &mut mock::NoDelay;
+
+        // Straight from the transport.
+        let mut raw = std::vec::Vec::new();
+        let len = note
+            .dfu()
+            .get(d, 600, None)
+            .unwrap()
+            .wait_streaming(d, |c| {
+                raw.extend_from_slice(c);
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(raw, std::format!("{}\r\n", large).as_bytes());
+        assert_eq!(len, raw.len());
+
+        // After it did not fit in the buffer.
+        assert!(matches!(
+            note.dfu().get(d, 600, None).unwrap().wait(d),
+            Err(NoteError::ResponseTooLarge { .. })
+        ));
+        let mut out = std::vec::Vec::new();
+        let mut decoder = payload::Decoder::new(|c| {
+            out.extend_from_slice(c);
+            Ok(())
+        });
+        note.read_response_with(|c| decoder.write(c)).unwrap();
+        assert_eq!(decoder.finish().unwrap(), 600);
+        assert_eq!(out, data);
+
+        let t = note.card().time(d).unwrap().wait(d).unwrap();
+        assert_eq!(t.time, Some(1599769214));
+
+        // The rest is discarded if the sink fails.
+        assert!(matches!(
+            note.dfu()
+                .get(d, 600, None)
+                .unwrap()
+                .wait_streaming(d, |_| Err(NoteError::BufOverflow)),
+            Err(NoteError::BufOverflow)
+        ));
+        let t = note.card().time(d).unwrap().wait(d).unwrap();
+        assert_eq!(t.time, Some(1599769215));
+
+        assert!(matches!(
+            note.read_response_with(|_| Ok(())),
+            Err(NoteError::WrongState)
+        ));
+        assert_eq!(note.stats().responses, 4);
+
+        note.transport().done();
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn last_response() {
+        let mut note: Notecard<_, 1024> = mock::MockTransport::new()
+            .expect_req("card.time", r#"{"time":1599769214,"new":true}"#)
+            .expect_req("card.time", r#"{"time":"soon"}"#)
+            .expect(r#"{"req":"hub.sync"}"#, "{}")
+            .notecard();
+        let d = &mut mock::NoDelay;
+
+        assert_eq!(note.last_response(), None);
+
+        let (t, raw) = note.card().time(d).unwrap().wait_with_raw(d).unwrap();
+        assert_eq!(t.time, Some(1599769214));
+        assert_eq!(raw, b"{\"time\":1599769214,\"new\":true}\r\n");
+
+        assert!(matches!(
+            note.card().time(d).unwrap().wait(d),
+            Err(NoteError::DeserError { .. })
+        ));
+        assert_eq!(note.last_response(), Some(&b"{\"time\":\"soon\"}\r\n"[..]));
+
+        // Cleared by the next request.
+        {
+            let _dropped = note.hub().sync(d, false).unwrap();
+        }
+        assert_eq!(note.last_response(), None);
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn observer() {
+        use std::{
+            string::String,
+            sync::{Arc, Mutex},
+            vec::Vec,
+        };
+
+        struct Log(Arc<Mutex<Vec<String>>>);
+
+        impl Observer for Log {
+            fn request(&mut self, name: &str, data: &[u8]) {
+                self.0.lock().unwrap().push(std::format!("> {}: {}", name, data.len()));
+            }
+
+            fn response(&mut self, name: &str, data: &[u8]) {
+                self.0.lock().unwrap().push(std::format!("< {}: {}", name, data.len()));
+            }
+        }
+
+        let mock = mock::MockTransport::new()
+            .expect_req("card.time", r#"{"time":1599769214}"#)
+            .expect(r#"{"req":"hub.sync"}"#, "{}")
+            .expect_req("card.time", r#"{"time":1599769215}"#);
+        let mut note: Notecard<_, 1024, Uninit> = Notecard::new_with_transport(mock, config());
+        let d = &mut mock::NoDelay;
+
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let observer = std::boxed::Box::leak(std::boxed::Box::new(Log(log.clone())));
+        assert!(note.set_observer(Some(observer)).is_none());
+
+        let mut note = note.initialize(d).unwrap();
+
+        note.card().time(d).unwrap().wait(d).unwrap();
+        note.hub().sync(d, false).unwrap().wait(d).unwrap();
+
+        assert_eq!(
+            *log.lock().unwrap(),
+            ["> card.time: 20", "< card.time: 21", "> hub.sync: 19", "< hub.sync: 4"]
+        );
+
+        assert!(note.set_observer(None).is_some());
+        note.card().time(d).unwrap().wait(d).unwrap();
+        assert_eq!(log.lock().unwrap().len(), 4);
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn send() {
+        fn send<T: Send>() {}
+
+        send::<Notecard<mock::MockTransport, 1024, Uninit>>();
+        send::<Notecard<mock::MockTransport, 1024>>();
+        send::<SuspendState<1024>>();
+        send::<card::Card<mock::MockTransport, 1024>>();
+        send::<hub::Hub<mock::MockTransport, 1024>>();
+        send::<FutureResponse<card::res::Time, mock::MockTransport, 1024>>();
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn stats() {
+        let mock = mock::MockTransport::new()
+            .expect_req("card.time", r#"{"time":1599769214}"#)
+            .expect_req("card.time", r#"{"time":"soon"}"#)
+            .expect_req("card.time", r#"{"err":"i2c: timeout {io}"}"#)
+            .expect_req("card.time", r#"{"time":1599769215}"#);
+        let d = &mut mock::NoDelay;
+
+        let mut note: Notecard<_, 1024> = Notecard::new_with_transport(mock, config())
+            .initialize(d)
+            .unwrap();
+
+        assert_eq!(note.stats(), Stats::default());
+
+        note.card().time(d).unwrap().wait(d).unwrap();
+        assert!(note.card().time(d).unwrap().wait(d).is_err());
+        note.retry(d, |note, d| note.card().time(d)?.wait(d)).unwrap();
+
+        assert_eq!(
+            note.stats(),
+            Stats {
+                requests: 4,
+                responses: 4,
+                deser_errors: 1,
+                retries: 1,
+                timeouts: 0,
+                bytes_out: 4 * 20,
+                bytes_in: 21 + 17 + 29 + 21,
+            }
+        );
+    }
+
+    #[test]
+    fn resize_buf() {
+        let replay = transport::record::ReplayTransport::new(
+            b"> {\"req\":\"card.time\"}\n< {\"time\":1599769214}\r\n",
+        );
+        let note: Notecard<_, 64, Uninit> = Notecard::new_with_transport(replay, config());
+        assert_eq!(note.buf_size(), 64);
+
+        let note = note.resize_buf::<1024>().unwrap();
+        assert_eq!(note.buf_size(), 1024);
+
+        let d = &mut DelayFn(|_| ());
+        let mut note = note.initialize(d).unwrap();
+
+        let t = note.card().time(d).unwrap().wait(d).unwrap();
+        assert_eq!(t.time, Some(1599769214));
+
+        // The response is still in the buffer.
+        assert!(note.resize_buf::<8>().is_err());
+    }
+
+    #[test]
+    fn caller_buffer() {
+        let replay = transport::record::ReplayTransport::new(
+            b"> {\"req\":\"card.time\"}\n< {\"time\":1599769214}\r\n\
+              > {\"req\":\"card.temp\",\"minutes\":5}\n< {\"value\":21.5}\r\n",
+        );
+        let buf = std::boxed::Box::leak(std::vec![0u8; 256].into_boxed_slice());
+        let note = Notecard::new_with_buffer(replay, config(), buf);
+        assert_eq!(note.buf_size(), 256);
+
+        let d = &mut DelayFn(|_| ());
+        let mut note = note.initialize(d).unwrap();
+
+        let t = note.card().time(d).unwrap().wait(d).unwrap();
+        assert_eq!(t.time, Some(1599769214));
+
+        // Serialized into the buffer of the caller.
+        let t = note.card().temp(d, Some(5)).unwrap().wait(d).unwrap();
+        assert_eq!(t.value, 21.5);
+    }
+
+    #[test]
+    fn wait_timeout() {
+        // The Notecard never responds.
+        let replay = transport::record::ReplayTransport::new(b"> {\"req\":\"card.time\"}\n");
+        let mut waited = 0u32;
+        let mut delay = DelayFn(|ms| waited += ms as u32);
+
+        let mut note: Notecard<_, 1024> = Notecard::new_with_transport(replay, config())
+            .initialize(&mut delay)
+            .unwrap();
+
+        match note.card().time(&mut delay).unwrap().wait_timeout(&mut delay, 1000) {
+            Err(NoteError::TimeOut { req }) => assert_eq!(req, "card.time"),
+            r => panic!("not a time-out: {:?}", r),
+        }
+        assert_eq!(waited, DRAIN_IDLE as u32 + 20 + 250 + 1000);
+    }
+
+    #[test]
+    fn poll_interval() {
+        // The Notecard never responds.
+        let replay = transport::record::ReplayTransport::new(b"> {\"req\":\"card.time\"}\n");
+        let mut polls = std::vec::Vec::new();
+        let mut delay = DelayFn(|ms| polls.push(ms));
+
+        let mut note: Notecard<_, 1024> = Notecard::new_with_transport(
+            replay,
+            NotecardConfig {
+                chunk_delay: 0,
+                segment_delay: 0,
+                poll_interval: 500,
+                ..config()
+            },
+        )
+        .initialize(&mut delay)
+        .unwrap();
+        assert_eq!(note.poll_interval(), 500);
+
+        note.set_poll_interval(0);
+        assert_eq!(note.poll_interval(), 1);
+
+        note.set_poll_interval(400);
+        note.set_response_timeout(1000);
+
+        assert!(matches!(
+            note.card().time(&mut delay).unwrap().wait(&mut delay),
+            Err(NoteError::TimeOut { .. })
+        ));
+
+        // Draining when initializing is not affected.
+        let drain = (DRAIN_IDLE / RESPONSE_DELAY) as usize;
+        assert!(polls[..drain].iter().all(|ms| *ms == RESPONSE_DELAY));
+        assert_eq!(polls[drain..], [0, 0, 400, 400, 400]);
+    }
+
+    #[test]
+    fn try_wait() {
+        let d = &mut DelayFn(|_| ());
+
+        // The Notecard does not respond.
+        let replay = transport::record::ReplayTransport::new(b"> {\"req\":\"card.time\"}\n");
+        let mut note: Notecard<_, 1024> = Notecard::new_with_transport(replay, config())
+            .initialize(d)
+            .unwrap();
+
+        let mut r = note.card().time(d).unwrap();
+        assert!(r.try_wait().unwrap().is_none());
+        assert!(r.try_wait().unwrap().is_none());
+
+        let replay = transport::record::ReplayTransport::new(
+            b"> {\"req\":\"card.time\"}\n< {\"time\":1599769214}\r\n",
+        );
+        let mut note: Notecard<_, 1024> = Notecard::new_with_transport(replay, config())
+            .initialize(d)
+            .unwrap();
+
+        let mut r = note.card().time(d).unwrap();
+        assert_eq!(r.try_wait().unwrap().unwrap().time, Some(1599769214));
+    }
+
+    #[test]
+    fn drain_abandoned() {
+        let replay = transport::record::ReplayTransport::new(
+            b"> {\"req\":\"card.time\"}\n< {\"time\":1599769214}\r\n\
+              > {\"req\":\"card.time\"}\n< {\"time\":1599769215}\r\n",
+        );
+        let d = &mut DelayFn(|_| ());
+
+        let mut note: Notecard<_, 1024> = Notecard::new_with_transport(replay, config())
+            .initialize(d)
+            .unwrap();
+
+        // Dropped without reading the response.
+        {
+            let _dropped = note.card().time(d).unwrap();
+        }
+
+        let t = note.card().time(d).unwrap().wait(d).unwrap();
+        assert_eq!(t.time, Some(1599769215));
+        assert!(note.transport().done());
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn poll_and_wait_raw() {
+        let mut note: Notecard<_, 1024> = mock::MockTransport::new()
+            .expect_req("card.time", r#"{"time":1599769214}"#)
+            .expect_req("card.time", r#"{"time":1599769215}"#)
+            .notecard();
+        let d = &mut mock::NoDelay;
+
+        let mut r = note.card().time(d).unwrap();
+        let t = loop {
+            match r.poll() {
+                Ok(t) => break t,
+                Err(nb::Error::WouldBlock) => (),
+                Err(nb::Error::Other(e)) => panic!("{:?}", e),
+            }
+        };
+        assert_eq!(t.time, Some(1599769214));
+
+        let raw = note.card().time(d).unwrap().wait_raw(d).unwrap();
+        assert_eq!(raw, b"{\"time\":1599769215}\r\n");
+    }
+
+    /// Answers every request with a corrupted response: truncated, with flipped bytes, too long or
+    /// just noise. It also misreports sizes, returns more bytes than asked for and fails reads.
+    struct Garbage {
+        seed: u32,
+        response: std::vec::Vec<u8>,
+    }
+
+    impl Garbage {
+        const RESPONSES: [&'static [u8]; 5] = [
+            b"{\"time\":1599769214,\"zone\":\"CEST,Europe/Oslo\"}\r\n",
+            b"{\"err\":\"{io} error {card-busy}\"}\r\n",
+            b"{\"status\":\"{normal}\",\"usb\":true,\"storage\":8,\"connected\":true}\r\n",
+            b"{\"time\":1,\"crc\":\"0001:DEADBEEF\"}\r\n",
+            b"{\"mode\":\"continuous\",\"product\":\"com.blues.test\",\"crc\":\"00",
+        ];
+
+        fn next(&mut self) -> usize {
+            // xorshift32
+            self.seed ^= self.seed << 13;
+            self.seed ^= self.seed >> 17;
+            self.seed ^= self.seed << 5;
+            self.seed as usize
+        }
+    }
+
+    impl NotecardTransport for Garbage {
+        fn ping(&mut self) -> bool {
+            true
+        }
+
+        fn write(&mut self, data: &[u8]) -> Result<(), NoteError> {
+            if !data.ends_with(b"\n") {
+                return Ok(());
+            }
+
+            let n = self.next();
+            let mut r = Self::RESPONSES[n % Self::RESPONSES.len()].to_vec();
+            match n / 8 % 5 {
+                0 => r.truncate(self.next() % r.len()),
+                1 => {
+                    let i = self.next() % r.len();
+                    r[i] = self.next() as u8;
+                }
+                2 => {
+                    let n = self.next() % 4096;
+                    r.splice(2..2, std::iter::repeat_n(b'a', n));
+                }
+                3 => r = (0..self.next() % 600).map(|_| self.next() as u8).collect(),
+                _ => (),
+            }
+            self.response = r;
+            Ok(())
+        }
+
+        fn available(&mut self) -> Result<usize, NoteError> {
+            let len = self.response.len();
+            Ok(match self.next() % 8 {
+                _ if len == 0 => 0,
+                0 => usize::MAX,
+                1 => 1,
+                _ => len,
+            })
+        }
+
+        fn read(&mut self, buf: &mut [u8]) -> Result<(usize, usize), NoteError> {
+            let sz = buf.len().min(self.response.len());
+            buf[..sz].copy_from_slice(&self.response[..sz]);
+            self.response.drain(..sz);
+
+            match self.next() % 16 {
+                0 => Err(NoteError::I2cReadError),
+                1 => Ok((buf.len() + 1, self.response.len())),
+                2 => Ok((sz, usize::MAX)),
+                _ => Ok((sz, self.response.len())),
+            }
+        }
+    }
+
+    /// The driver returns errors, and never panics, on any output from the Notecard.
+    #[test]
+    fn garbage_responses() {
+        let d = &mut DelayFn(|_| {});
+
+        for c in [config(), NotecardConfig::default()] {
+            let t = Garbage {
+                seed: 0x2545_f491,
+                response: std::vec::Vec::new(),
+            };
+            let mut note: Notecard<_, 512> =
+                Notecard::new_with_transport(t, c).initialize(d).unwrap();
+            let mut out = [0u8; 1024];
+
+            for i in 0..2000 {
+                let _ = match i % 5 {
+                    0 => note.card().time(d).and_then(|r| r.wait(d)).map(drop),
+                    1 => note.card().status(d).and_then(|r| r.wait(d)).map(drop),
+                    2 => note.hub().get(d).and_then(|r| r.wait(d)).map(drop),
+                    3 => note.card().time(d).and_then(|r| r.wait_raw(d)).map(drop),
+                    _ => match note.card().version(d).and_then(|r| r.wait(d)) {
+                        Err(NoteError::ResponseTooLarge { .. }) => {
+                            note.read_response_into(&mut out).map(drop)
+                        }
+                        r => r.map(drop),
+                    },
+                };
+            }
+        }
+    }
+}
@@ -0,0 +1,295 @@
+//! Mock Notecard for testing firmware using this driver on the host.
+//!
+//! Script the requests the firmware is expected to make together with the responses of the
+//! Notecard. The mock panics if a request does not match the next expected request:
+//!
+//! ```ignore
+//! let mut note: Notecard<_> = MockTransport::new()
+//!     .expect_req("card.time", r#"{"time":1599769214,"zone":"CEST,Europe/Oslo"}"#)
+//!     .expect(r#"{"req":"hub.sync"}"#, "{}")
+//!     .notecard();
+//!
+//! my_firmware::sync(&mut note, &mut NoDelay).unwrap();
+//!
+//! note.transport().done();
+//! ```
+
+use std::collections::VecDeque;
+use std::string::String;
+use std::vec::Vec;
+
+use embedded_hal::blocking::delay::DelayMs;
+
+use super::{Notecard, NotecardConfig, NotecardTransport};
+
+/// An expected request.
+enum Request {
+    /// The request must match exactly.
+    Exact(String),
+
+    /// Any request with this `req`.
+    Req(String),
+}
+
+/// A transport which checks requests against the expected requests, and responds with the scripted
+/// responses.
+pub struct MockTransport {
+    expected: VecDeque<(Request, String)>,
+
+    /// The request being received.
+    tx: Vec<u8>,
+
+    /// The response being sent.
+    rx: VecDeque<u8>,
+}
+
+impl Default for MockTransport {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MockTransport {
+    pub fn new() -> MockTransport {
+        MockTransport {
+            expected: VecDeque::new(),
+            tx: Vec::new(),
+            rx: VecDeque::new(),
+        }
+    }
+
+    /// Expect exactly `request` (without the trailing new-line), and respond with `response`. The
+    /// response is ignored for commands (`cmd`).
+    pub fn expect(mut self, request: &str, response: &str) -> MockTransport {
+        self.expected
+            .push_back((Request::Exact(request.into()), response.into()));
+        self
+    }
+
+    /// Expect a request with `req` (e.g. `card.time`) regardless of arguments, and respond with
+    /// `response`.
+    pub fn expect_req(mut self, req: &str, response: &str) -> MockTransport {
+        self.expected
+            .push_back((Request::Req(req.into()), response.into()));
+        self
+    }
+
+    /// Create an initialized [`Notecard`] driver using this transport, without any delays.
+    pub fn notecard<const BUF_SIZE: usize>(self) -> Notecard<MockTransport, BUF_SIZE> {
+        Notecard::new_with_transport(
+            self,
+            NotecardConfig {
+                chunk_delay: 0,
+                segment_delay: 0,
+                ..NotecardConfig::default()
+            },
+        )
+        .initialize(&mut NoDelay)
+        .expect("mock: initialize")
+    }
+
+    /// Number of expected requests that have not been made yet.
+    pub fn remaining(&self) -> usize {
+        self.expected.len()
+    }
+
+    /// Panics if not all the expected requests have been made.
+    pub fn done(&self) {
+        assert_eq!(
+            self.expected.len(),
+            0,
+            "mock: {} expected request(s) were not made",
+            self.expected.len()
+        );
+    }
+
+    fn respond(&mut self) {
+        let request = String::from_utf8(core::mem::take(&mut self.tx))
+            .expect("mock: request is not valid utf-8");
+        let request = strip_crc(request.trim_end());
+        let request = request.as_str();
+
+        let (expected, response) = self
+            .expected
+            .pop_front()
+            .unwrap_or_else(|| panic!("mock: unexpected request: {}", request));
+
+        match expected {
+            Request::Exact(e) => assert_eq!(request, e, "mock: request does not match"),
+            Request::Req(r) => assert!(
+                request.contains(&format!(r#""req":"{}""#, r)),
+                "mock: expected {} request, got: {}",
+                r,
+                request
+            ),
+        }
+
+        // The Notecard does not respond to commands.
+        if !request.starts_with(r#"{"cmd":"#) {
+            self.rx.extend(response.bytes());
+            self.rx.extend(b"\r\n");
+        }
+    }
+}
+
+impl NotecardTransport for MockTransport {
+    fn ping(&mut self) -> bool {
+        true
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<(), crate::NoteError> {
+        // An empty line is sent when recovering, and is ignored by the Notecard.
+        if data == b"\n" && self.tx.is_empty() {
+            return Ok(());
+        }
+
+        assert!(
+            self.rx.is_empty(),
+            "mock: request made before the previous response was read"
+        );
+
+        self.tx.extend_from_slice(data);
+
+        // Binary data (`card.binary.put`) follows the request after the first new-line, and is
+        // terminated by a second new-line.
+        let lines = self.tx.iter().filter(|c| **c == b'\n').count();
+        let binary = self.tx.starts_with(br#"{"req":"card.binary.put""#);
+
+        if lines == 2 || (lines == 1 && !binary) {
+            self.respond();
+        }
+
+        Ok(())
+    }
+
+    fn available(&mut self) -> Result<usize, crate::NoteError> {
+        Ok(self.rx.len())
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> Result<(usize, usize), crate::NoteError> {
+        let n = buf.len().min(self.rx.len());
+        for (b, c) in buf.iter_mut().zip(self.rx.drain(..n)) {
+            *b = c;
+        }
+
+        Ok((n, self.rx.len()))
+    }
+}
+
+/// Remove the `crc` field, the mock behaves like Notecard firmware without `crc` support.
+fn strip_crc(request: &str) -> String {
+    match request.rfind(r#""crc":""#) {
+        Some(p) => format!("{}}}", request[..p].trim_end_matches(',')),
+        None => request.into(),
+    }
+}
+
+/// A delay that returns immediately.
+pub struct NoDelay;
+
+impl DelayMs<u16> for NoDelay {
+    fn delay_ms(&mut self, _ms: u16) {}
+}
+
+#[cfg(feature = "async")]
+mod asynch {
+    use embedded_hal_async::delay::DelayNs;
+
+    use super::{MockTransport, NoDelay};
+    use crate::asynch::{AsyncNotecard, AsyncNotecardTransport};
+    use crate::{NoteError, NotecardConfig, NotecardTransport};
+
+    impl MockTransport {
+        /// Create an initialized [`AsyncNotecard`] driver using this transport, without any
+        /// delays.
+        pub async fn async_notecard<const BUF_SIZE: usize>(
+            self,
+        ) -> AsyncNotecard<MockTransport, BUF_SIZE> {
+            let mut note = AsyncNotecard::new_with_transport(
+                self,
+                NotecardConfig {
+                    chunk_delay: 0,
+                    segment_delay: 0,
+                    ..NotecardConfig::default()
+                },
+            );
+            note.initialize(&mut NoDelay)
+                .await
+                .expect("mock: initialize");
+            note
+        }
+    }
+
+    impl AsyncNotecardTransport for MockTransport {
+        async fn ping(&mut self) -> bool {
+            NotecardTransport::ping(self)
+        }
+
+        async fn write(&mut self, data: &[u8]) -> Result<(), NoteError> {
+            NotecardTransport::write(self, data)
+        }
+
+        async fn available(&mut self) -> Result<usize, NoteError> {
+            NotecardTransport::available(self)
+        }
+
+        async fn read(&mut self, buf: &mut [u8]) -> Result<(usize, usize), NoteError> {
+            NotecardTransport::read(self, buf)
+        }
+    }
+
+    impl DelayNs for NoDelay {
+        async fn delay_ns(&mut self, _ns: u32) {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scripted() {
+        let mut note: Notecard<_, 1024> = MockTransport::new()
+            .expect_req("card.time", r#"{"time":1599769214,"minutes":60}"#)
+            .expect(r#"{"req":"hub.sync"}"#, "{}")
+            .notecard();
+
+        let t = note
+            .card()
+            .time(&mut NoDelay)
+            .unwrap()
+            .wait(&mut NoDelay)
+            .unwrap();
+        assert_eq!(t.time, Some(1599769214));
+
+        note.hub()
+            .sync(&mut NoDelay, false)
+            .unwrap()
+            .wait(&mut NoDelay)
+            .unwrap();
+
+        note.transport().done();
+    }
+
+    #[test]
+    fn error_response() {
+        let mut note: Notecard<_, 1024> = MockTransport::new()
+            .expect_req("card.time", r#"{"err":"time is not yet set {no-time}"}"#)
+            .notecard();
+
+        assert!(matches!(
+            note.card().time(&mut NoDelay).unwrap().wait(&mut NoDelay),
+            Err(crate::NoteError::NotecardErr(_))
+        ));
+    }
+
+    #[test]
+    #[should_panic(expected = "does not match")]
+    fn unexpected_request() {
+        let mut note: Notecard<_, 1024> = MockTransport::new()
+            .expect(r#"{"req":"hub.sync"}"#, "{}")
+            .notecard();
+
+        let _ = note.card().time(&mut NoDelay);
+    }
+}
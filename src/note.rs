@@ -1,18 +1,21 @@
 //! <https://dev.blues.io/reference/notecard-api/note-requests/>
 
 #[allow(unused_imports)]
-use defmt::{debug, error, info, trace, warn};
+use crate::fmt::{debug, error, info, trace, warn};
 use embedded_hal::blocking::delay::DelayMs;
-use embedded_hal::blocking::i2c::{Read, SevenBitAddress, Write};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
-use super::{FutureResponse, NoteError, Notecard};
+#[cfg(feature = "alloc")]
+use super::payload;
+use super::binary::BinarySource;
+use super::notefile::{self, NotefileName};
+use super::{FutureResponse, NoteError, Notecard, NotecardTransport};
 
-pub struct Note<'a, IOM: Write<SevenBitAddress> + Read<SevenBitAddress>, const BS: usize> {
+pub struct Note<'a, IOM: NotecardTransport, const BS: usize> {
     note: &'a mut Notecard<IOM, BS>,
 }
 
-impl<'a, IOM: Write<SevenBitAddress> + Read<SevenBitAddress>, const BS: usize> Note<'a, IOM, BS> {
+impl<'a, IOM: NotecardTransport, const BS: usize> Note<'a, IOM, BS> {
     pub fn from(note: &mut Notecard<IOM, BS>) -> Note<'_, IOM, BS> {
         Note { note }
     }
@@ -36,13 +39,58 @@ impl<'a, IOM: Write<SevenBitAddress> + Read<SevenBitAddress>, const BS: usize> N
         self.note.request(
             delay,
             req::Add::<T> {
-                req: "note.add",
-                file: file.map(heapless::String::from),
-                note: note.map(heapless::String::from),
+                file: notefile::check(file)?,
+                note: note.map(crate::to_string).transpose()?,
                 body,
                 payload,
                 sync: Some(sync),
-                ..<req::Add<T> as Default>::default()
+                ..req::Add::<T>::new()
+            },
+        )?;
+        Ok(FutureResponse::from(self.note))
+    }
+
+    /// Adds a note like [`Note::add`], with the data from `source` as payload. The data is base64
+    /// encoded while the request is written to the Notecard, so neither the data nor the request
+    /// has to fit in a buffer: only a chunk at a time is kept in the buffer of the driver.
+    pub fn add_from<T: Serialize + Default>(
+        self,
+        delay: &mut impl DelayMs<u16>,
+        file: Option<&str>,
+        note: Option<&str>,
+        body: Option<T>,
+        source: &mut impl BinarySource,
+        sync: bool,
+    ) -> Result<FutureResponse<'a, res::Add, IOM, BS>, NoteError> {
+        self.note.request_payload(
+            delay,
+            req::Add::<T> {
+                file: notefile::check(file)?,
+                note: note.map(crate::to_string).transpose()?,
+                body,
+                sync: Some(sync),
+                ..req::Add::<T>::new()
+            },
+            source,
+        )?;
+        Ok(FutureResponse::from(self.note))
+    }
+
+    /// Adds a note with the contents of the binary storage area (see [`crate::card::Card::binary_put`])
+    /// as payload. The binary storage area is cleared once the note has been added.
+    pub fn add_binary(
+        self,
+        delay: &mut impl DelayMs<u16>,
+        file: Option<&str>,
+        sync: bool,
+    ) -> Result<FutureResponse<'a, res::Add, IOM, BS>, NoteError> {
+        self.note.request(
+            delay,
+            req::Add::<()> {
+                file: notefile::check(file)?,
+                sync: Some(sync),
+                binary: Some(true),
+                ..req::Add::<()>::new()
             },
         )?;
         Ok(FutureResponse::from(self.note))
@@ -61,12 +109,10 @@ impl<'a, IOM: Write<SevenBitAddress> + Read<SevenBitAddress>, const BS: usize> N
         self.note.request(
             delay,
             req::Update::<T> {
-                req: "note.update",
-                file: heapless::String::from(file),
-                note: heapless::String::from(note),
                 body,
                 payload,
                 verify,
+                ..req::Update::<T>::new(file, note)?
             },
         )?;
         Ok(FutureResponse::from(self.note))
@@ -89,11 +135,9 @@ impl<'a, IOM: Write<SevenBitAddress> + Read<SevenBitAddress>, const BS: usize> N
         self.note.request(
             delay,
             req::Get {
-                req: "note.get",
-                file: heapless::String::from(file),
-                note: heapless::String::from(note),
                 delete,
                 deleted,
+                ..req::Get::new(file, note)?
             },
         )?;
         Ok(FutureResponse::from(self.note))
@@ -106,15 +150,7 @@ impl<'a, IOM: Write<SevenBitAddress> + Read<SevenBitAddress>, const BS: usize> N
         file: &str,
         note: &str,
     ) -> Result<FutureResponse<'a, res::Empty, IOM, BS>, NoteError> {
-        self.note.request(
-            delay,
-            req::Delete {
-                req: "note.delete",
-                file: heapless::String::from(file),
-                note: heapless::String::from(note),
-                verify: false,
-            },
-        )?;
+        self.note.request(delay, req::Delete::new(file, note)?)?;
 
         Ok(FutureResponse::from(self.note))
     }
@@ -139,25 +175,25 @@ impl<'a, IOM: Write<SevenBitAddress> + Read<SevenBitAddress>, const BS: usize> N
         self.note.request(
             delay,
             req::Template::<T> {
-                req: "note.template",
-                file: file.map(heapless::String::from),
+                file: notefile::check(file)?,
                 body,
                 length,
+                ..req::Template::<T>::new()
             },
         )?;
         Ok(FutureResponse::from(self.note))
     }
 }
 
-mod req {
+pub mod req {
     use super::*;
 
-    #[derive(Deserialize, Serialize, Default)]
+    #[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
     pub struct Add<'a, T: Serialize + Default> {
-        pub req: &'static str,
+        pub req: &'a str,
 
         #[serde(skip_serializing_if = "Option::is_none")]
-        pub file: Option<heapless::String<20>>,
+        pub file: Option<&'a str>,
 
         #[serde(skip_serializing_if = "Option::is_none")]
         pub note: Option<heapless::String<20>>,
@@ -176,13 +212,26 @@ mod req {
 
         #[serde(skip_serializing_if = "Option::is_none")]
         pub verify: Option<bool>,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub binary: Option<bool>,
+    }
+
+    impl<'a, T: Serialize + Default> Add<'a, T> {
+        /// A `note.add` request with all optional fields unset.
+        pub fn new() -> Add<'a, T> {
+            Add {
+                req: "note.add",
+                ..Default::default()
+            }
+        }
     }
 
-    #[derive(Deserialize, Serialize, Default)]
+    #[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
     pub struct Update<'a, T: Serialize + Default> {
-        pub req: &'static str,
+        pub req: &'a str,
 
-        pub file: heapless::String<20>,
+        pub file: &'a str,
         pub note: heapless::String<20>,
 
         #[serde(skip_serializing_if = "Option::is_none")]
@@ -194,32 +243,69 @@ mod req {
         pub verify: bool,
     }
 
-    #[derive(Deserialize, Serialize, Default)]
-    pub struct Delete {
-        pub req: &'static str,
+    impl<'a, T: Serialize + Default> Update<'a, T> {
+        /// A `note.update` request for the Note `note` in the DB Notefile `file`.
+        pub fn new(file: &'a str, note: &str) -> Result<Update<'a, T>, NoteError> {
+            Ok(Update {
+                req: "note.update",
+                file: NotefileName::new(file)?.as_str(),
+                note: crate::to_string(note)?,
+                ..Default::default()
+            })
+        }
+    }
 
-        pub file: heapless::String<20>,
+    #[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+    pub struct Delete<'a> {
+        pub req: &'a str,
+
+        pub file: &'a str,
         pub note: heapless::String<20>,
         pub verify: bool,
     }
 
-    #[derive(Deserialize, Serialize, Default)]
-    pub struct Get {
-        pub req: &'static str,
+    impl<'a> Delete<'a> {
+        /// A `note.delete` request for the Note `note` in the DB Notefile `file`.
+        pub fn new(file: &'a str, note: &str) -> Result<Delete<'a>, NoteError> {
+            Ok(Delete {
+                req: "note.delete",
+                file: NotefileName::new(file)?.as_str(),
+                note: crate::to_string(note)?,
+                verify: false,
+            })
+        }
+    }
 
-        pub file: heapless::String<20>,
+    #[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+    pub struct Get<'a> {
+        pub req: &'a str,
+
+        pub file: &'a str,
         pub note: heapless::String<20>,
 
         pub delete: bool,
         pub deleted: bool,
     }
 
-    #[derive(Deserialize, Serialize, Default)]
-    pub struct Template<T: Serialize + Default> {
-        pub req: &'static str,
+    impl<'a> Get<'a> {
+        /// A `note.get` request for the Note `note` in the Notefile `file`.
+        pub fn new(file: &'a str, note: &str) -> Result<Get<'a>, NoteError> {
+            Ok(Get {
+                req: "note.get",
+                file: NotefileName::new(file)?.as_str(),
+                note: crate::to_string(note)?,
+                delete: false,
+                deleted: false,
+            })
+        }
+    }
+
+    #[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+    pub struct Template<'a, T: Serialize + Default> {
+        pub req: &'a str,
 
         #[serde(skip_serializing_if = "Option::is_none")]
-        pub file: Option<heapless::String<20>>,
+        pub file: Option<&'a str>,
 
         #[serde(skip_serializing_if = "Option::is_none")]
         pub body: Option<T>,
@@ -227,35 +313,60 @@ mod req {
         #[serde(skip_serializing_if = "Option::is_none")]
         pub length: Option<u32>,
     }
+
+    impl<'a, T: Serialize + Default> Template<'a, T> {
+        /// A `note.template` request with all optional fields unset.
+        pub fn new() -> Template<'a, T> {
+            Template {
+                req: "note.template",
+                ..Default::default()
+            }
+        }
+    }
 }
 
 pub mod res {
     use super::*;
 
-    #[derive(Deserialize, defmt::Format)]
+    #[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "defmt-format", derive(defmt::Format))]
     pub struct Empty {}
 
-    #[derive(Debug, Deserialize, defmt::Format)]
-    pub struct Get<T: Serialize> {
-        pub note: heapless::String<32>,
+    #[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+    #[cfg_attr(feature = "defmt-format", derive(defmt::Format))]
+    pub struct Get<T: Serialize, S = heapless::String<1024>> {
+        pub note: heapless::String<{ crate::NOTEFILE_NAME_LEN }>,
 
         #[serde(skip_serializing_if = "Option::is_none")]
         pub body: Option<T>,
 
         #[serde(skip_serializing_if = "Option::is_none")]
-        pub payload: Option<heapless::String<1024>>,
+        pub payload: Option<S>,
 
         #[serde(skip_serializing_if = "Option::is_none")]
-        pub time: Option<u32>,
+        pub time: Option<u64>,
+    }
+
+    #[cfg(feature = "alloc")]
+    impl<T: Serialize, S: AsRef<str>> Get<T, S> {
+        /// Decode the payload. Returns an empty vector if there is no payload.
+        pub fn decode_payload_vec(&self) -> Result<alloc::vec::Vec<u8>, NoteError> {
+            match &self.payload {
+                Some(p) => payload::decode_vec(p.as_ref()),
+                None => Ok(alloc::vec::Vec::new()),
+            }
+        }
     }
 
-    #[derive(Deserialize, defmt::Format)]
+    #[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "defmt-format", derive(defmt::Format))]
     pub struct Add {
         total: Option<u32>,
         template: Option<bool>,
     }
 
-    #[derive(Deserialize, defmt::Format)]
+    #[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "defmt-format", derive(defmt::Format))]
     pub struct Template {
         bytes: u32,
     }
@@ -266,6 +377,22 @@ mod tests {
     use super::*;
     use crate::DEFAULT_BUF_SIZE as BUF_SIZE;
 
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn get_alloc() {
+        use alloc::{format, string::String};
+
+        let data = [1u8; 3000];
+        let r = format!(
+            r#"{{"note":"sensors","payload":"{}","time":1599769214}}"#,
+            crate::payload::encode_string(&data)
+        );
+
+        let g: res::Get<res::Empty, String> = crate::deserialize_response(r.as_bytes()).unwrap();
+        assert_eq!(g.payload.as_ref().map(String::len), Some(4000));
+        assert_eq!(g.decode_payload_vec().unwrap(), data);
+    }
+
     #[test]
     fn add_with_template() {
         let r = br##"{"template":true}"##;
@@ -274,7 +401,8 @@ mod tests {
 
     #[test]
     fn get_note() {
-        #[derive(serde::Serialize, serde::Deserialize, Debug, defmt::Format)]
+        #[derive(serde::Serialize, serde::Deserialize, Debug)]
+        #[cfg_attr(feature = "defmt-format", derive(defmt::Format))]
         pub struct StorageIdInfo {
             #[serde(skip_serializing_if = "Option::is_none")]
             pub last_id: Option<u32>,
@@ -292,6 +420,128 @@ mod tests {
         println!("{:?}", si);
     }
 
+    #[cfg(feature = "mock")]
+    #[test]
+    fn invalid_notefile() {
+        use crate::mock::{MockTransport, NoDelay};
+
+        let mut note: crate::Notecard<_> = MockTransport::new().notecard();
+        let d = &mut NoDelay;
+        let long = "a".repeat(crate::NOTEFILE_NAME_LEN - 2) + ".qo";
+
+        assert!(matches!(
+            note.note().add::<()>(d, Some("sensors"), None, None, None, false),
+            Err(NoteError::InvalidArgument)
+        ));
+        assert!(matches!(
+            note.note().get::<()>(d, "my settings.db", "?", false, false),
+            Err(NoteError::InvalidArgument)
+        ));
+        assert!(matches!(
+            note.card().location_track(d, true, false, false, None, Some("track.qi"), None),
+            Err(NoteError::InvalidArgument)
+        ));
+        assert!(matches!(
+            note.card().location_track(d, true, false, false, None, Some(&long), None),
+            Err(NoteError::InvalidArgument)
+        ));
+
+        note.transport().done();
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn argument_too_long() {
+        use crate::mock::{MockTransport, NoDelay};
+
+        let mut note: crate::Notecard<_> = MockTransport::new().notecard();
+        let d = &mut NoDelay;
+        let long = "a".repeat(21);
+
+        assert!(matches!(
+            note.note().get::<()>(d, "settings.db", &long, false, false),
+            Err(NoteError::ArgumentTooLong { len: 21, capacity: 20 })
+        ));
+        assert!(matches!(
+            note.note().add::<()>(d, Some("settings.db"), Some(&long), None, None, false),
+            Err(NoteError::ArgumentTooLong { len: 21, capacity: 20 })
+        ));
+        assert!(matches!(
+            note.card().location_mode(d, None, None, Some(&long), None, None, None, None, None),
+            Err(NoteError::ArgumentTooLong { len: 21, capacity: 20 })
+        ));
+        note.transport().done();
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn add_from() {
+        use crate::mock::{MockTransport, NoDelay};
+        use crate::payload;
+
+        #[derive(serde::Serialize, Default)]
+        struct Capture {
+            rate: u32,
+        }
+
+        let capture: std::vec::Vec<u8> = (0..4096u32).map(|i| (i * 31) as u8).collect();
+        let mut b64 = [0u8; payload::encoded_len(4096)];
+        let encoded = payload::encode(&capture, &mut b64).unwrap();
+
+        // The encoded payload alone is larger than the buffer.
+        let mut note: crate::Notecard<_, 512> = MockTransport::new()
+            .expect(
+                &std::format!(
+                    r#"{{"req":"note.add","file":"axl.qo","body":{{"rate":100}},"sync":true,"payload":"{}"}}"#,
+                    encoded
+                ),
+                r#"{"total":1}"#,
+            )
+            .expect(r#"{"req":"note.add","file":"axl.qo","sync":false,"payload":""}"#, "{}")
+            .expect_req("note.add", r#"{"err":"{io} request terminated"}"#)
+            .expect_req("card.time", r#"{"time":1599769214}"#)
+            .notecard();
+        let d = &mut NoDelay;
+
+        note.note()
+            .add_from(d, Some("axl.qo"), None, Some(Capture { rate: 100 }), &mut &capture[..], true)
+            .unwrap()
+            .wait(d)
+            .unwrap();
+        note.note()
+            .add_from::<()>(d, Some("axl.qo"), None, None, &mut &[][..], false)
+            .unwrap()
+            .wait(d)
+            .unwrap();
+
+        // A source that fails leaves a partial request, which is terminated before the next.
+        struct Failing;
+
+        impl BinarySource for Failing {
+            fn size(&self) -> usize {
+                4096
+            }
+
+            fn read(&mut self, offset: usize, buf: &mut [u8]) -> Result<usize, NoteError> {
+                if offset < 1024 {
+                    buf.fill(1);
+                    Ok(buf.len())
+                } else {
+                    Err(NoteError::I2cReadError)
+                }
+            }
+        }
+
+        assert!(matches!(
+            note.note().add_from::<()>(d, Some("axl.qo"), None, None, &mut Failing, false),
+            Err(NoteError::I2cReadError)
+        ));
+        let t = note.card().time(d).unwrap().wait(d).unwrap();
+        assert_eq!(t.time, Some(1599769214));
+
+        note.transport().done();
+    }
+
     #[test]
     fn add_payload() {
         pub const AXL_OUTN: usize = { 3 * 1024 } * 4 * 4 / 3 + 4;
@@ -318,11 +568,11 @@ mod tests {
         let sz = base64::encode_config_slice(data, base64::STANDARD, &mut b64);
 
         let b64 = &b64[..sz];
-        let b64 = core::str::from_utf8(&b64).unwrap();
+        let b64 = core::str::from_utf8(b64).unwrap();
 
         let add = req::Add::<AxlPacket> {
             req: "note.add",
-            file: Some("axl.qo".into()),
+            file: Some("axl.qo"),
             note: Some("?".into()),
             body: Some(p),
             payload: Some(b64),
@@ -334,4 +584,88 @@ mod tests {
 
         println!("cmd size: {}", cmd.len());
     }
+
+    mod vectors {
+        use super::*;
+
+        #[derive(serde::Serialize, serde::Deserialize, Default)]
+        struct Temp {
+            temp: f32,
+        }
+
+        vector_res!(add, "note.add.res.json", res::Add);
+        vector_res!(get, "note.get.res.json", res::Get<Temp>);
+        vector_res!(template, "note.template.res.json", res::Template);
+
+        vector_req!(
+            add_req,
+            "note.add.req.json",
+            req::Add::<Temp> {
+                req: "note.add",
+                file: Some("sensors.qo"),
+                body: Some(Temp { temp: 72.22 }),
+                sync: Some(true),
+                ..req::Add::<Temp>::default()
+            }
+        );
+
+        vector_req!(
+            get_req,
+            "note.get.req.json",
+            req::Get {
+                req: "note.get",
+                file: "requests.qi",
+                note: "?".into(),
+                delete: true,
+                deleted: false,
+            }
+        );
+
+        vector_req!(
+            update_req,
+            "note.update.req.json",
+            req::Update::<Temp> {
+                req: "note.update",
+                file: "my-settings.db",
+                note: "measurements".into(),
+                body: Some(Temp { temp: 72.22 }),
+                payload: None,
+                verify: false,
+            }
+        );
+
+        vector_req!(
+            delete_req,
+            "note.delete.req.json",
+            req::Delete {
+                req: "note.delete",
+                file: "my-settings.db",
+                note: "measurements".into(),
+                verify: false,
+            }
+        );
+
+        vector_req!(
+            template_req,
+            "note.template.req.json",
+            req::Template::<Temp> {
+                req: "note.template",
+                file: Some("readings.qo"),
+                body: Some(Temp { temp: 14.1 }),
+                length: None,
+            }
+        );
+
+        vector_req!(
+            add_binary_req,
+            "note.add.binary.req.json",
+            req::Add::<()> {
+                req: "note.add",
+                file: Some("capture.qo"),
+                sync: Some(true),
+                binary: Some(true),
+                ..Default::default()
+            }
+        );
+    }
 }
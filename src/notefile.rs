@@ -0,0 +1,182 @@
+//! Notefile names.
+//!
+//! <https://dev.blues.io/api-reference/glossary/#notefile>
+
+#[allow(unused_imports)]
+use crate::fmt::{debug, error, info, trace, warn};
+use serde::{Serialize, Serializer};
+
+use super::{NoteError, NOTEFILE_NAME_LEN};
+
+/// The type of a Notefile, given by the suffix of its name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt-format", derive(defmt::Format))]
+pub enum NotefileKind {
+    /// `.qo`: outbound queue, synced to Notehub.
+    Outbound,
+
+    /// `.qos`: encrypted outbound queue.
+    OutboundSecure,
+
+    /// `.qi`: inbound queue, synced from Notehub.
+    Inbound,
+
+    /// `.qis`: encrypted inbound queue.
+    InboundSecure,
+
+    /// `.db`: database synced in both directions.
+    Db,
+
+    /// `.dbs`: database synced from Notehub, e.g. `_env.dbs`.
+    DbInbound,
+
+    /// `.dbx`: local database, not synced.
+    DbLocal,
+}
+
+impl NotefileKind {
+    const SUFFIXES: [(&'static str, NotefileKind); 7] = [
+        (".qo", NotefileKind::Outbound),
+        (".qos", NotefileKind::OutboundSecure),
+        (".qi", NotefileKind::Inbound),
+        (".qis", NotefileKind::InboundSecure),
+        (".db", NotefileKind::Db),
+        (".dbs", NotefileKind::DbInbound),
+        (".dbx", NotefileKind::DbLocal),
+    ];
+
+    fn from_name(name: &str) -> Option<NotefileKind> {
+        Self::SUFFIXES
+            .iter()
+            .find(|(s, _)| name.ends_with(s))
+            .map(|(_, k)| *k)
+    }
+
+    /// An outbound queue (`.qo` or `.qos`).
+    pub fn is_outbound(self) -> bool {
+        matches!(self, NotefileKind::Outbound | NotefileKind::OutboundSecure)
+    }
+
+    /// An inbound queue (`.qi` or `.qis`).
+    pub fn is_inbound(self) -> bool {
+        matches!(self, NotefileKind::Inbound | NotefileKind::InboundSecure)
+    }
+
+    /// A database (`.db`, `.dbs` or `.dbx`).
+    pub fn is_db(self) -> bool {
+        matches!(
+            self,
+            NotefileKind::Db | NotefileKind::DbInbound | NotefileKind::DbLocal
+        )
+    }
+}
+
+/// A valid Notefile name: letters, digits, `-`, `_` and `.`, followed by the suffix of one of the
+/// [kinds](NotefileKind) of Notefiles. The requests that take a Notefile name check it with
+/// [`NotefileName::new`], so that an invalid name fails with [`NoteError::InvalidArgument`]
+/// before it is sent to the Notecard.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt-format", derive(defmt::Format))]
+pub struct NotefileName<'a> {
+    name: &'a str,
+    kind: NotefileKind,
+}
+
+impl<'a> NotefileName<'a> {
+    pub fn new(name: &'a str) -> Result<NotefileName<'a>, NoteError> {
+        let kind = NotefileKind::from_name(name);
+
+        let valid = name.len() <= NOTEFILE_NAME_LEN
+            && name
+                .bytes()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, b'-' | b'_' | b'.'));
+
+        match kind {
+            Some(kind) if valid && !name.starts_with('.') => Ok(NotefileName { name, kind }),
+            _ => {
+                error!("notefile: invalid name: {}", name);
+                Err(NoteError::InvalidArgument)
+            }
+        }
+    }
+
+    pub fn as_str(&self) -> &'a str {
+        self.name
+    }
+
+    pub fn kind(&self) -> NotefileKind {
+        self.kind
+    }
+}
+
+impl<'a> TryFrom<&'a str> for NotefileName<'a> {
+    type Error = NoteError;
+
+    fn try_from(name: &'a str) -> Result<Self, Self::Error> {
+        NotefileName::new(name)
+    }
+}
+
+impl AsRef<str> for NotefileName<'_> {
+    fn as_ref(&self) -> &str {
+        self.name
+    }
+}
+
+impl Serialize for NotefileName<'_> {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_str(self.name)
+    }
+}
+
+/// Check the optional Notefile name `name`.
+pub(crate) fn check(name: Option<&str>) -> Result<Option<&str>, NoteError> {
+    name.map(|n| NotefileName::new(n).map(|n| n.as_str()))
+        .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names() {
+        for (name, kind) in [
+            ("sensors.qo", NotefileKind::Outbound),
+            ("_track.qos", NotefileKind::OutboundSecure),
+            ("requests.qi", NotefileKind::Inbound),
+            ("my-settings.db", NotefileKind::Db),
+            ("_env.dbs", NotefileKind::DbInbound),
+            ("local.dbx", NotefileKind::DbLocal),
+            ("v1.2.qis", NotefileKind::InboundSecure),
+        ] {
+            assert_eq!(NotefileName::new(name).unwrap().kind(), kind, "{}", name);
+        }
+
+        assert!(NotefileName::new("sensors.qo").unwrap().kind().is_outbound());
+        assert!(NotefileName::new("_env.dbs").unwrap().kind().is_db());
+
+        for name in [
+            "",
+            ".qo",
+            "sensors",
+            "sensors.txt",
+            "sensors.qo ",
+            "my file.db",
+            "\"x\".qo",
+            "sensors/a.qo",
+            "æøå.db",
+        ] {
+            assert!(
+                matches!(NotefileName::new(name), Err(NoteError::InvalidArgument)),
+                "{}",
+                name
+            );
+        }
+
+        let long = "a".repeat(NOTEFILE_NAME_LEN - 3) + ".qo";
+        assert!(NotefileName::new(&long).is_ok());
+        let long = "a".repeat(NOTEFILE_NAME_LEN - 2) + ".qo";
+        assert!(NotefileName::new(&long).is_err());
+    }
+}
@@ -0,0 +1,137 @@
+//! <https://dev.blues.io/api-reference/notecard-api/ntn-requests/>
+//!
+//! Requests for managing a Notecard paired with a Starnote for satellite (NTN) connectivity.
+
+#[allow(unused_imports)]
+use crate::fmt::{debug, error, info, trace, warn};
+use embedded_hal::blocking::delay::DelayMs;
+use serde::{Deserialize, Serialize};
+
+use super::{FutureResponse, NoteError, Notecard, NotecardTransport};
+
+pub struct Ntn<'a, IOM: NotecardTransport, const BS: usize> {
+    note: &'a mut Notecard<IOM, BS>,
+}
+
+impl<'a, IOM: NotecardTransport, const BS: usize> Ntn<'a, IOM, BS> {
+    pub fn from(note: &mut Notecard<IOM, BS>) -> Ntn<'_, IOM, BS> {
+        Ntn { note }
+    }
+
+    /// Resets the NTN configuration of the Notecard, so that it re-detects a connected Starnote.
+    pub fn reset(self, delay: &mut impl DelayMs<u16>) -> Result<FutureResponse<'a, res::Empty, IOM, BS>, NoteError> {
+        self.note.request_raw(delay, b"{\"req\":\"ntn.reset\"}\n")?;
+        Ok(FutureResponse::from(self.note))
+    }
+
+    /// Displays the status of the NTN (satellite) connection.
+    pub fn status(self, delay: &mut impl DelayMs<u16>) -> Result<FutureResponse<'a, res::Status, IOM, BS>, NoteError> {
+        self.note.request_raw(delay, b"{\"req\":\"ntn.status\"}\n")?;
+        Ok(FutureResponse::from(self.note))
+    }
+
+    /// Whether the Notecard should use its own GPS/GNSS module (`Some(true)`) or the one of the
+    /// Starnote (`Some(false)`). Retrieves the current setting when `None`.
+    pub fn gps(
+        self,
+        delay: &mut impl DelayMs<u16>,
+        on: Option<bool>,
+    ) -> Result<FutureResponse<'a, res::Gps, IOM, BS>, NoteError> {
+        self.note.request(
+            delay,
+            req::Gps {
+                on: on.and_then(|on| on.then_some(true)),
+                off: on.and_then(|on| (!on).then_some(true)),
+                ..req::Gps::new()
+            },
+        )?;
+        Ok(FutureResponse::from(self.note))
+    }
+}
+
+pub mod req {
+    use super::*;
+
+    #[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "defmt-format", derive(defmt::Format))]
+    pub struct Gps<'a> {
+        pub req: &'a str,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub on: Option<bool>,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub off: Option<bool>,
+    }
+
+    impl<'a> Gps<'a> {
+        /// A `ntn.gps` request with all optional fields unset.
+        pub fn new() -> Gps<'a> {
+            Gps {
+                req: "ntn.gps",
+                ..Default::default()
+            }
+        }
+    }
+}
+
+pub mod res {
+    use super::*;
+
+    #[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "defmt-format", derive(defmt::Format))]
+    pub struct Empty {}
+
+    #[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "defmt-format", derive(defmt::Format))]
+    pub struct Status {
+        pub status: Option<heapless::String<120>>,
+    }
+
+    #[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "defmt-format", derive(defmt::Format))]
+    pub struct Gps {
+        /// The GPS/GNSS module of the Notecard is used.
+        #[serde(default)]
+        pub on: bool,
+
+        /// The GPS/GNSS module of the Starnote is used.
+        #[serde(default)]
+        pub off: bool,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gps_query() {
+        assert_eq!(
+            &serde_json_core::to_string::<_, 64>(&req::Gps {
+                req: "ntn.gps",
+                ..Default::default()
+            })
+            .unwrap(),
+            r#"{"req":"ntn.gps"}"#
+        );
+    }
+
+    mod vectors {
+        use super::*;
+
+        vector_res!(reset, "ntn.reset.res.json", res::Empty);
+        vector_res!(status, "ntn.status.res.json", res::Status);
+        vector_res!(gps, "ntn.gps.res.json", res::Gps);
+
+        vector_req!(
+            gps_req,
+            "ntn.gps.req.json",
+            req::Gps {
+                req: "ntn.gps",
+                on: Some(true),
+                off: None,
+            }
+        );
+    }
+}
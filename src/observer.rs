@@ -0,0 +1,66 @@
+//! Observing the requests and responses exchanged with the Notecard.
+//!
+//! An [`Observer`] registered with [`Notecard::set_observer`](crate::Notecard::set_observer) is
+//! called with every request that is sent and every response that is received, e.g. to trace the
+//! exchange or to keep a log of the last requests for debugging:
+//!
+//! ```ignore
+//! struct Trace;
+//!
+//! impl Observer for Trace {
+//!     fn request(&mut self, name: &str, data: &[u8]) {
+//!         rprintln!("> {}: {} bytes", name, data.len());
+//!     }
+//!
+//!     fn response(&mut self, name: &str, data: &[u8]) {
+//!         rprintln!("< {}: {} bytes", name, data.len());
+//!     }
+//! }
+//!
+//! note.set_observer(Some(cortex_m::singleton!(: Trace = Trace).unwrap()));
+//! ```
+//!
+//! To record the exact bytes written to and read from the transport, see
+//! [`Recorder`](crate::transport::record::Recorder).
+
+/// Called with the requests and responses exchanged with the Notecard. Observers must be [`Send`],
+/// like the driver they are registered with.
+pub trait Observer: Send {
+    /// A request is about to be sent. `name` is the `req` (or `cmd`) of the request, and `data`
+    /// the whole request including the terminating new-line. For requests with a payload that is
+    /// encoded while it is sent (e.g. [`Note::add_from`](crate::note::Note::add_from)), `data` is
+    /// the request up to the payload.
+    fn request(&mut self, name: &str, data: &[u8]) {
+        let _ = (name, data);
+    }
+
+    /// A response has been received. `name` is the name of the request it responds to.
+    fn response(&mut self, name: &str, data: &[u8]) {
+        let _ = (name, data);
+    }
+}
+
+/// The name of the request in `buf`, or an empty string if it does not start with `req` or
+/// `cmd`.
+pub(crate) fn request_name(buf: &[u8]) -> &str {
+    let name = buf
+        .strip_prefix(br#"{"req":""#)
+        .or_else(|| buf.strip_prefix(br#"{"cmd":""#))
+        .unwrap_or(&[]);
+    let end = name.iter().position(|c| *c == b'"').unwrap_or(0);
+
+    core::str::from_utf8(&name[..end]).unwrap_or("")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names() {
+        assert_eq!(request_name(b"{\"req\":\"card.time\"}\n"), "card.time");
+        assert_eq!(request_name(b"{\"cmd\":\"hub.set\",\"mode\":\"off\"}\n"), "hub.set");
+        assert_eq!(request_name(b"{\"req\":\"card.ti"), "");
+        assert_eq!(request_name(b"{}\n"), "");
+    }
+}
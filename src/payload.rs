@@ -0,0 +1,409 @@
+//! Helpers for the base64 encoded `payload` fields used by several requests (e.g. `note.add` and
+//! `card.attn`).
+
+#[allow(unused_imports)]
+use crate::fmt::{debug, error, info, trace, warn};
+use super::binary::BinarySource;
+use super::NoteError;
+
+/// Length of `len` bytes when base64 encoded (with padding).
+pub const fn encoded_len(len: usize) -> usize {
+    len.div_ceil(3) * 4
+}
+
+/// Upper bound of the decoded length of a base64 string of length `len`.
+pub const fn decoded_len(len: usize) -> usize {
+    len.div_ceil(4) * 3
+}
+
+/// Encode `data` as base64 into `out`, returning the encoded string.
+pub fn encode<'a>(data: &[u8], out: &'a mut [u8]) -> Result<&'a str, NoteError> {
+    if out.len() < encoded_len(data.len()) {
+        return Err(NoteError::BufOverflow);
+    }
+
+    let sz = base64::encode_config_slice(data, base64::STANDARD, out);
+
+    // base64 only produces ascii.
+    core::str::from_utf8(&out[..sz]).map_err(|_| NoteError::InvalidPayload)
+}
+
+/// Decode the base64 string `payload` into `out`, returning the number of bytes written.
+pub fn decode(payload: &str, out: &mut [u8]) -> Result<usize, NoteError> {
+    if out.len() < decoded_len(payload.len()) {
+        return Err(NoteError::BufOverflow);
+    }
+
+    base64::decode_config_slice(payload, base64::STANDARD, out)
+        .map_err(|_| NoteError::InvalidPayload)
+}
+
+/// Encode the data from `source` as base64, passing the encoded string to `sink` in chunks, so
+/// that the encoded data does not have to be staged in a buffer of its own. Returns the length of
+/// the encoded string.
+pub fn encode_from(
+    source: &mut impl BinarySource,
+    mut sink: impl FnMut(&str) -> Result<(), NoteError>,
+) -> Result<usize, NoteError> {
+    let total = source.size();
+    let mut data = [0u8; decoded_len(CHUNK)];
+    let mut b64 = [0u8; CHUNK];
+    let mut offset = 0;
+    let mut encoded = 0;
+
+    while offset < total {
+        // Only the last chunk may be padded, so every other chunk is filled completely.
+        let want = data.len().min(total - offset);
+        let mut len = 0;
+        while len < want {
+            let sz = source.read(offset + len, &mut data[len..want])?;
+            if sz == 0 {
+                error!("payload: source ended at {} of {} bytes", offset + len, total);
+                return Err(NoteError::SourceEnded {
+                    read: offset + len,
+                    size: total,
+                });
+            }
+            len += sz.min(want - len);
+        }
+
+        let s = encode(&data[..len], &mut b64)?;
+        encoded += s.len();
+        sink(s)?;
+
+        offset += len;
+    }
+
+    Ok(encoded)
+}
+
+/// Encode `data` as a base64 string.
+#[cfg(feature = "alloc")]
+pub fn encode_string(data: &[u8]) -> alloc::string::String {
+    base64::encode_config(data, base64::STANDARD)
+}
+
+/// Decode the base64 string `payload`.
+#[cfg(feature = "alloc")]
+pub fn decode_vec(payload: &str) -> Result<alloc::vec::Vec<u8>, NoteError> {
+    base64::decode_config(payload, base64::STANDARD).map_err(|_| NoteError::InvalidPayload)
+}
+
+const KEY: &[u8] = b"payload";
+
+/// Characters of base64 decoded at a time, a multiple of 4.
+const CHUNK: usize = 128;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    /// Outside strings, at `depth` in objects and arrays.
+    Outside { depth: usize },
+
+    /// In a string, `matched` is the length of the prefix of `payload` it matches so far.
+    String { matched: Option<usize>, escaped: bool },
+
+    /// After the `payload` key, before the `:`.
+    Key,
+
+    /// After the `:` of the `payload` key, before the value.
+    Value,
+
+    /// In the `payload` string.
+    Payload { escaped: bool },
+
+    /// The whole payload has been decoded.
+    Done,
+}
+
+fn outside(depth: usize, c: u8) -> State {
+    match c {
+        b'"' => State::String {
+            matched: (depth == 1).then_some(0),
+            escaped: false,
+        },
+        b'{' | b'[' => State::Outside { depth: depth + 1 },
+        b'}' | b']' => State::Outside {
+            depth: depth.saturating_sub(1),
+        },
+        _ => State::Outside { depth },
+    }
+}
+
+/// Decodes the base64 `payload` field of a response that is passed to it in chunks, e.g. with
+/// [`FutureResponse::wait_streaming`](crate::FutureResponse::wait_streaming), and passes the
+/// decoded data to `sink` in chunks. Only the top-level `payload` field is decoded, the rest of
+/// the response is ignored.
+pub struct Decoder<F: FnMut(&[u8]) -> Result<(), NoteError>> {
+    sink: F,
+    state: State,
+    depth: usize,
+    chars: [u8; CHUNK],
+    len: usize,
+    decoded: usize,
+}
+
+impl<F: FnMut(&[u8]) -> Result<(), NoteError>> Decoder<F> {
+    pub fn new(sink: F) -> Decoder<F> {
+        Decoder {
+            sink,
+            state: State::Outside { depth: 0 },
+            depth: 0,
+            chars: [0u8; CHUNK],
+            len: 0,
+            decoded: 0,
+        }
+    }
+
+    /// Decode the next chunk of the response.
+    pub fn write(&mut self, chunk: &[u8]) -> Result<(), NoteError> {
+        for &c in chunk {
+            self.state = match self.state {
+                State::Outside { depth } => outside(depth, c),
+                State::String { escaped: true, .. } => State::String {
+                    matched: None,
+                    escaped: false,
+                },
+                State::String { matched, .. } => match c {
+                    b'\\' => State::String {
+                        matched: None,
+                        escaped: true,
+                    },
+                    b'"' if matched == Some(KEY.len()) => State::Key,
+                    b'"' => State::Outside { depth: self.depth },
+                    _ => State::String {
+                        matched: matched
+                            .filter(|&m| KEY.get(m) == Some(&c))
+                            .map(|m| m + 1),
+                        escaped: false,
+                    },
+                },
+                State::Key if c.is_ascii_whitespace() => State::Key,
+                State::Key if c == b':' => State::Value,
+                State::Value if c.is_ascii_whitespace() => State::Value,
+                State::Value if c == b'"' => State::Payload { escaped: false },
+                // A string value that happens to be `payload`.
+                State::Key => outside(self.depth, c),
+                State::Value => {
+                    error!("payload: `payload` is not a string");
+                    return Err(NoteError::InvalidPayload);
+                }
+                State::Payload { escaped: false } if c == b'\\' => {
+                    State::Payload { escaped: true }
+                }
+                State::Payload { escaped: false } if c == b'"' => {
+                    self.flush()?;
+                    State::Done
+                }
+                State::Payload { .. } => {
+                    self.chars[self.len] = c;
+                    self.len += 1;
+                    if self.len == CHUNK {
+                        self.flush()?;
+                    }
+                    State::Payload { escaped: false }
+                }
+                State::Done => State::Done,
+            };
+
+            if let State::Outside { depth } = self.state {
+                self.depth = depth;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), NoteError> {
+        let mut out = [0u8; decoded_len(CHUNK)];
+        let sz = decode(
+            core::str::from_utf8(&self.chars[..self.len]).map_err(|_| NoteError::InvalidPayload)?,
+            &mut out,
+        )?;
+        self.len = 0;
+        self.decoded += sz;
+
+        if sz > 0 {
+            (self.sink)(&out[..sz])?;
+        }
+
+        Ok(())
+    }
+
+    /// Finish decoding, returning the length of the decoded payload. A response without a
+    /// `payload` (e.g. an error response) decodes to nothing, a `payload` that was not terminated
+    /// fails with [`NoteError::InvalidPayload`].
+    pub fn finish(self) -> Result<usize, NoteError> {
+        match self.state {
+            State::Payload { .. } | State::Key | State::Value => {
+                error!("payload: response ended in `payload`");
+                Err(NoteError::InvalidPayload)
+            }
+            _ => Ok(self.decoded),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let data = b"hello notecard";
+        let mut b64 = [0u8; encoded_len(14)];
+        let s = encode(data, &mut b64).unwrap();
+        assert_eq!(s, "aGVsbG8gbm90ZWNhcmQ=");
+
+        let mut out = [0u8; decoded_len(20)];
+        let sz = decode(s, &mut out).unwrap();
+        assert_eq!(&out[..sz], data);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn round_trip_alloc() {
+        let data = [7u8; 2000];
+        let s = encode_string(&data);
+        assert_eq!(s.len(), encoded_len(data.len()));
+        assert_eq!(decode_vec(&s).unwrap(), data);
+        assert!(matches!(decode_vec("not base64!"), Err(NoteError::InvalidPayload)));
+    }
+
+    #[test]
+    fn too_small() {
+        let mut b64 = [0u8; 4];
+        assert!(matches!(
+            encode(b"hello", &mut b64),
+            Err(NoteError::BufOverflow)
+        ));
+
+        let mut out = [0u8; 2];
+        assert!(matches!(
+            decode("aGVsbG8=", &mut out),
+            Err(NoteError::BufOverflow)
+        ));
+    }
+
+    #[test]
+    fn invalid() {
+        let mut out = [0u8; 32];
+        assert!(matches!(
+            decode("not base64!", &mut out),
+            Err(NoteError::InvalidPayload)
+        ));
+    }
+
+    #[test]
+    fn decoder() {
+        let data: std::vec::Vec<u8> = (0..1000u32).map(|i| (i * 7) as u8).collect();
+        let mut b64 = [0u8; encoded_len(1000)];
+        let p = encode(&data, &mut b64).unwrap();
+        let body = std::format!(
+            r#"{{"body":{{"payload":"x"}},"note":"payload","payload":"{}","time":1}}{}"#,
+            p.replace('/', "\\/"),
+            "\r\n"
+        );
+
+        for chunk in [1, 3, 64, 200, 4096] {
+            let mut out = std::vec::Vec::new();
+            let mut dec = Decoder::new(|d| {
+                out.extend_from_slice(d);
+                Ok(())
+            });
+            body.as_bytes().chunks(chunk).try_for_each(|c| dec.write(c)).unwrap();
+            assert_eq!(dec.finish().unwrap(), 1000);
+            assert_eq!(out, data);
+        }
+
+        let mut dec = Decoder::new(|_| Ok(()));
+        dec.write(br#"{"err":"no payload"}"#).unwrap();
+        assert_eq!(dec.finish().unwrap(), 0);
+
+        let mut dec = Decoder::new(|_| Ok(()));
+        dec.write(br#"{"payload":"AQID"#).unwrap();
+        assert!(matches!(dec.finish(), Err(NoteError::InvalidPayload)));
+
+        let mut dec = Decoder::new(|_| Ok(()));
+        assert!(matches!(
+            dec.write(br#"{"payload":"not base64!"}"#),
+            Err(NoteError::InvalidPayload)
+        ));
+
+        let mut dec = Decoder::new(|_| Err(NoteError::BufOverflow));
+        assert!(matches!(
+            dec.write(br#"{"payload":"AQID"}"#),
+            Err(NoteError::BufOverflow)
+        ));
+    }
+
+    #[test]
+    fn encode_source() {
+        /// Reads at most 7 bytes at a time.
+        struct Trickle<'a>(&'a [u8]);
+
+        impl BinarySource for Trickle<'_> {
+            fn size(&self) -> usize {
+                self.0.len()
+            }
+
+            fn read(&mut self, offset: usize, buf: &mut [u8]) -> Result<usize, NoteError> {
+                let sz = buf.len().min(7).min(self.0.len() - offset);
+                buf[..sz].copy_from_slice(&self.0[offset..offset + sz]);
+                Ok(sz)
+            }
+        }
+
+        let data: std::vec::Vec<u8> = (0..1000u32).map(|i| (i * 7) as u8).collect();
+        let mut b64 = [0u8; encoded_len(1000)];
+        let expected = encode(&data, &mut b64).unwrap();
+
+        for len in [0, 1, 95, 96, 97, 1000] {
+            let mut out = std::string::String::new();
+            let sz = encode_from(&mut Trickle(&data[..len]), |s| {
+                out.push_str(s);
+                Ok(())
+            })
+            .unwrap();
+            assert_eq!(sz, encoded_len(len));
+
+            let mut b64 = [0u8; encoded_len(1000)];
+            assert_eq!(out, encode(&data[..len], &mut b64).unwrap());
+        }
+
+        let mut out = std::string::String::new();
+        encode_from(&mut &data[..], |s| {
+            out.push_str(s);
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(out, expected);
+
+        /// Reports more data than it has.
+        struct Short<'a>(&'a [u8]);
+
+        impl BinarySource for Short<'_> {
+            fn size(&self) -> usize {
+                self.0.len() + 100
+            }
+
+            fn read(&mut self, offset: usize, buf: &mut [u8]) -> Result<usize, NoteError> {
+                self.0.read(offset.min(self.0.len()), buf)
+            }
+        }
+
+        let mut out = std::string::String::new();
+        assert!(matches!(
+            encode_from(&mut Short(&data[..200]), |s| {
+                out.push_str(s);
+                Ok(())
+            }),
+            Err(NoteError::SourceEnded { read: 200, size: 300 })
+        ));
+        assert_eq!(out.len(), encoded_len(192));
+
+        assert!(matches!(
+            encode_from(&mut &data[..], |_| Err(NoteError::RequestTooLarge(0))),
+            Err(NoteError::RequestTooLarge(0))
+        ));
+    }
+}
@@ -0,0 +1,150 @@
+//! Transports to the Notecard.
+//!
+//! The [`Notecard`](crate::Notecard) driver handles requests and responses, while the transport
+//! handles the framing of the bus the Notecard is connected to.
+
+#[allow(unused_imports)]
+use crate::fmt::{debug, error, info, trace, warn};
+use embedded_hal::blocking::i2c::{Read, SevenBitAddress, Write};
+use heapless::Vec;
+
+use super::NoteError;
+
+pub mod record;
+pub mod shared;
+
+#[cfg(feature = "serial")]
+pub mod serial;
+
+#[cfg(feature = "std")]
+pub mod usb;
+
+/// A bus to the Notecard.
+pub trait NotecardTransport {
+    /// Check if the Notecard is connected and responding.
+    fn ping(&mut self) -> bool;
+
+    /// Send a chunk of a request.
+    fn write(&mut self, data: &[u8]) -> Result<(), NoteError>;
+
+    /// Number of response bytes ready to be read, `0` if no response is ready yet.
+    fn available(&mut self) -> Result<usize, NoteError>;
+
+    /// Read response bytes into `buf`, which is not larger than the number of bytes available.
+    /// Returns the number of bytes read and the number of bytes still available.
+    fn read(&mut self, buf: &mut [u8]) -> Result<(usize, usize), NoteError>;
+
+    /// Change the address of the Notecard on the bus, for buses where that applies.
+    fn set_address(&mut self, _addr: u8) {}
+
+    /// Reset the transport after a failed transfer, see [`Notecard::recover`](crate::Notecard::recover).
+    /// E.g. discard buffered data, or clock out a stuck bus.
+    fn recover(&mut self) {}
+}
+
+/// Largest chunk that can be read or written over I2C at once (excluding the header).
+const I2C_CHUNK_MAX: usize = 126;
+
+/// The [serial-over-I2C
+/// protocol](https://dev.blues.io/notecard/notecard-guides/serial-over-i2c-protocol/).
+pub struct I2cTransport<IOM: Write<SevenBitAddress> + Read<SevenBitAddress>> {
+    i2c: IOM,
+    addr: u8,
+}
+
+impl<IOM: Write<SevenBitAddress> + Read<SevenBitAddress>> I2cTransport<IOM> {
+    pub fn new(i2c: IOM, addr: u8) -> I2cTransport<IOM> {
+        I2cTransport { i2c, addr }
+    }
+
+    /// I2C address of the Notecard.
+    pub fn addr(&self) -> u8 {
+        self.addr
+    }
+
+    /// Release the I2C device.
+    pub fn free(self) -> IOM {
+        self.i2c
+    }
+}
+
+impl<IOM: Write<SevenBitAddress> + Read<SevenBitAddress>> NotecardTransport for I2cTransport<IOM> {
+    fn ping(&mut self) -> bool {
+        self.i2c.write(self.addr, &[]).is_ok()
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<(), NoteError> {
+        // Each chunk is prefixed with its length.
+        let mut buf = Vec::<u8, { I2C_CHUNK_MAX + 2 }>::new();
+        buf.push(data.len() as u8)
+            .map_err(|_| NoteError::BufOverflow)?;
+        buf.extend_from_slice(data)
+            .map_err(|_| NoteError::BufOverflow)?;
+
+        self.i2c
+            .write(self.addr, &buf)
+            .map_err(|_| NoteError::I2cWriteError)
+    }
+
+    fn available(&mut self) -> Result<usize, NoteError> {
+        // Ask for reading, but with zero bytes allocated.
+        self.i2c
+            .write(self.addr, &[0, 0])
+            .map_err(|_| NoteError::I2cWriteError)?;
+
+        let mut buf = [0u8; 2];
+
+        // Read available bytes to read
+        self.i2c
+            .read(self.addr, &mut buf)
+            .map_err(|_| NoteError::I2cReadError)?;
+
+        let available = buf[0] as usize;
+        let sent = buf[1] as usize;
+
+        trace!("avail = {}, sent = {}", available, sent);
+
+        if sent > 0 {
+            error!(
+                "data query: bytes sent when querying available bytes: {}",
+                sent
+            );
+            Err(NoteError::RemainingData)
+        } else {
+            Ok(available)
+        }
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> Result<(usize, usize), NoteError> {
+        // Chunk to read + notecard header (2 bytes)
+        let mut bytes = Vec::<u8, { I2C_CHUNK_MAX + 2 }>::new();
+
+        let sz = I2C_CHUNK_MAX.min(buf.len());
+        bytes
+            .resize(sz + 2, 0)
+            .map_err(|_| NoteError::BufOverflow)?;
+
+        // Ask for reading `sz` bytes
+        self.i2c
+            .write(self.addr, &[0, sz as u8])
+            .map_err(|_| NoteError::I2cWriteError)?;
+
+        // Read bytes
+        self.i2c
+            .read(self.addr, &mut bytes)
+            .map_err(|_| NoteError::I2cReadError)?;
+
+        let available = bytes[0] as usize;
+        let sent = bytes[1] as usize;
+
+        trace!("avail = {}, sent = {}", available, sent);
+
+        buf[..sz].copy_from_slice(&bytes[2..]);
+
+        Ok((sz, available))
+    }
+
+    fn set_address(&mut self, addr: u8) {
+        self.addr = addr;
+    }
+}
@@ -0,0 +1,300 @@
+//! Recording and replaying of the exchange with a Notecard.
+//!
+//! [`Recorder`] wraps a transport and writes every request and response to a [`RecordSink`].
+//! Requests are written on lines starting with `> `, and responses on lines starting with `< `:
+//!
+//! ```text
+//! > {"req":"card.time"}
+//! < {"time":1599769214,"zone":"CEST,Europe/Oslo"}
+//! ```
+//!
+//! A recorded session can be played back with [`ReplayTransport`], which checks that the same
+//! requests are made and responds with the recorded responses:
+//!
+//! ```ignore
+//! let replay = ReplayTransport::new(include_bytes!("session.log"));
+//! let mut note = Notecard::<_>::new_with_transport(replay, NotecardConfig::default())
+//!     .initialize(&mut delay)?;
+//! ```
+
+use super::NotecardTransport;
+use crate::fmt::error;
+use crate::NoteError;
+
+const REQUEST: &[u8] = b"> ";
+const RESPONSE: &[u8] = b"< ";
+
+/// Destination for a recorded session.
+pub trait RecordSink {
+    fn record(&mut self, data: &[u8]);
+}
+
+/// Records until the vector is full, the rest is discarded.
+impl<const N: usize> RecordSink for heapless::Vec<u8, N> {
+    fn record(&mut self, data: &[u8]) {
+        let n = data.len().min(self.capacity() - self.len());
+        self.extend_from_slice(&data[..n]).ok();
+    }
+}
+
+#[cfg(feature = "std")]
+impl RecordSink for std::vec::Vec<u8> {
+    fn record(&mut self, data: &[u8]) {
+        self.extend_from_slice(data);
+    }
+}
+
+/// Records the exchange with the Notecard through `T` to `S`.
+pub struct Recorder<T: NotecardTransport, S: RecordSink> {
+    inner: T,
+    sink: S,
+
+    /// The next request byte starts a new line.
+    tx_start: bool,
+
+    /// The next response byte starts a new line.
+    rx_start: bool,
+}
+
+impl<T: NotecardTransport, S: RecordSink> Recorder<T, S> {
+    pub fn new(inner: T, sink: S) -> Recorder<T, S> {
+        Recorder {
+            inner,
+            sink,
+            tx_start: true,
+            rx_start: true,
+        }
+    }
+
+    /// The recorded session.
+    pub fn sink(&self) -> &S {
+        &self.sink
+    }
+
+    /// The wrapped transport.
+    pub fn inner(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Release the wrapped transport and the sink.
+    pub fn free(self) -> (T, S) {
+        (self.inner, self.sink)
+    }
+
+    fn record(sink: &mut S, prefix: &[u8], start: &mut bool, data: &[u8]) {
+        for line in data.split_inclusive(|c| *c == b'\n') {
+            if *start {
+                sink.record(prefix);
+            }
+            sink.record(line);
+            *start = line.last() == Some(&b'\n');
+        }
+    }
+}
+
+impl<T: NotecardTransport, S: RecordSink> NotecardTransport for Recorder<T, S> {
+    fn ping(&mut self) -> bool {
+        self.inner.ping()
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<(), NoteError> {
+        // Terminate a response that did not end with a new-line.
+        if !self.rx_start {
+            self.sink.record(b"\n");
+            self.rx_start = true;
+        }
+
+        Self::record(&mut self.sink, REQUEST, &mut self.tx_start, data);
+        self.inner.write(data)
+    }
+
+    fn available(&mut self) -> Result<usize, NoteError> {
+        self.inner.available()
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> Result<(usize, usize), NoteError> {
+        let (n, available) = self.inner.read(buf)?;
+        let data = buf.get(..n).ok_or(NoteError::BufOverflow)?;
+        Self::record(&mut self.sink, RESPONSE, &mut self.rx_start, data);
+        Ok((n, available))
+    }
+
+    fn set_address(&mut self, addr: u8) {
+        self.inner.set_address(addr)
+    }
+
+    fn recover(&mut self) {
+        self.inner.recover()
+    }
+}
+
+/// Plays back a session recorded with [`Recorder`]. Requests that do not match the recorded
+/// requests fail with [`NoteError::InvalidRequest`].
+pub struct ReplayTransport<'a> {
+    log: &'a [u8],
+
+    /// Remaining part of the current line.
+    line: &'a [u8],
+
+    /// The current line is a response.
+    response: bool,
+}
+
+impl<'a> ReplayTransport<'a> {
+    pub fn new(log: &'a [u8]) -> ReplayTransport<'a> {
+        ReplayTransport {
+            log,
+            line: &[],
+            response: false,
+        }
+    }
+
+    /// The whole session has been played back.
+    pub fn done(&self) -> bool {
+        self.line.is_empty() && self.log.is_empty()
+    }
+
+    /// Move to the next line if it starts with `prefix`.
+    fn next_line(&mut self, prefix: &[u8]) -> bool {
+        if !self.log.starts_with(prefix) {
+            return false;
+        }
+
+        let end = self
+            .log
+            .iter()
+            .position(|c| *c == b'\n')
+            .map_or(self.log.len(), |p| p + 1);
+
+        self.line = &self.log[prefix.len()..end];
+        self.log = &self.log[end..];
+        self.response = prefix == RESPONSE;
+
+        true
+    }
+}
+
+impl NotecardTransport for ReplayTransport<'_> {
+    fn ping(&mut self) -> bool {
+        true
+    }
+
+    fn write(&mut self, mut data: &[u8]) -> Result<(), NoteError> {
+        while !data.is_empty() {
+            if self.line.is_empty() && !self.next_line(REQUEST) {
+                error!("replay: no more recorded requests.");
+                return Err(NoteError::InvalidRequest);
+            }
+
+            if self.response {
+                error!("replay: request made before the recorded response was read.");
+                return Err(NoteError::InvalidRequest);
+            }
+
+            let n = data.len().min(self.line.len());
+            if data[..n] != self.line[..n] {
+                error!("replay: request does not match recorded request.");
+                return Err(NoteError::InvalidRequest);
+            }
+
+            data = &data[n..];
+            self.line = &self.line[n..];
+        }
+
+        Ok(())
+    }
+
+    fn available(&mut self) -> Result<usize, NoteError> {
+        if self.line.is_empty() {
+            self.next_line(RESPONSE);
+        }
+
+        Ok(if self.response { self.line.len() } else { 0 })
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> Result<(usize, usize), NoteError> {
+        if !self.response {
+            return Err(NoteError::WrongState);
+        }
+
+        let n = buf.len().min(self.line.len());
+        buf[..n].copy_from_slice(&self.line[..n]);
+        self.line = &self.line[n..];
+
+        Ok((n, self.line.len()))
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use super::*;
+    use crate::mock::{MockTransport, NoDelay};
+    use crate::Notecard;
+
+    #[test]
+    fn record_and_replay() {
+        let mock = MockTransport::new()
+            .expect_req("card.time", r#"{"time":1599769214}"#)
+            .expect(r#"{"req":"hub.sync"}"#, "{}");
+
+        let mut note: Notecard<_, 1024> = Notecard::new_with_transport(
+            Recorder::new(mock, std::vec::Vec::new()),
+            crate::tests::config(),
+        )
+        .initialize(&mut NoDelay)
+        .unwrap();
+
+        note.card().time(&mut NoDelay).unwrap().wait(&mut NoDelay).unwrap();
+        note.hub().sync(&mut NoDelay, false).unwrap().wait(&mut NoDelay).unwrap();
+
+        let (recorder, _) = note.suspend();
+        let (_, log) = recorder.free();
+
+        assert_eq!(
+            log,
+            b"> {\"req\":\"card.time\"}\n< {\"time\":1599769214}\r\n> {\"req\":\"hub.sync\"}\n< {}\r\n"
+        );
+
+        let mut note: Notecard<_, 1024> =
+            Notecard::new_with_transport(ReplayTransport::new(&log), crate::tests::config())
+                .initialize(&mut NoDelay)
+                .unwrap();
+
+        let t = note.card().time(&mut NoDelay).unwrap().wait(&mut NoDelay).unwrap();
+        assert_eq!(t.time, Some(1599769214));
+
+        assert!(matches!(
+            note.card().time(&mut NoDelay),
+            Err(NoteError::InvalidRequest)
+        ));
+    }
+
+    /// Reports more bytes read than fit in the buffer.
+    struct Overreporting;
+
+    impl NotecardTransport for Overreporting {
+        fn ping(&mut self) -> bool {
+            true
+        }
+
+        fn write(&mut self, _data: &[u8]) -> Result<(), NoteError> {
+            Ok(())
+        }
+
+        fn available(&mut self) -> Result<usize, NoteError> {
+            Ok(8)
+        }
+
+        fn read(&mut self, buf: &mut [u8]) -> Result<(usize, usize), NoteError> {
+            Ok((buf.len() + 1, 0))
+        }
+    }
+
+    #[test]
+    fn record_overreported_read() {
+        let mut recorder = Recorder::new(Overreporting, std::vec::Vec::new());
+        let mut buf = [0u8; 4];
+
+        assert!(matches!(recorder.read(&mut buf), Err(NoteError::BufOverflow)));
+    }
+}
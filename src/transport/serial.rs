@@ -0,0 +1,213 @@
+//! Serial (UART) transport using `embedded-io`.
+//!
+//! Over serial the request is written as is, and the response is a single line terminated by a
+//! new-line.
+//!
+//! ```ignore
+//! let mut note = Notecard::<_>::new_with_transport(SerialTransport::new(uart), NotecardConfig::default())
+//!     .initialize(&mut delay)?;
+//! ```
+
+#[allow(unused_imports)]
+use crate::fmt::{debug, error, info, trace, warn};
+use embedded_io::{Read, ReadReady, Write};
+
+use super::NotecardTransport;
+use crate::NoteError;
+
+/// Number of bytes reported available while a response is being received, the actual length of
+/// the response is not known before the new-line is received.
+const SERIAL_CHUNK: usize = 128;
+
+/// A Notecard connected to a serial port.
+pub struct SerialTransport<S> {
+    serial: S,
+}
+
+impl<S> SerialTransport<S> {
+    pub fn new(serial: S) -> SerialTransport<S> {
+        SerialTransport { serial }
+    }
+
+    /// Release the serial port.
+    pub fn free(self) -> S {
+        self.serial
+    }
+}
+
+impl<S: Read + ReadReady + Write> NotecardTransport for SerialTransport<S> {
+    fn ping(&mut self) -> bool {
+        self.serial.flush().is_ok()
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<(), NoteError> {
+        self.serial
+            .write_all(data)
+            .map_err(|_| NoteError::SerialWriteError)?;
+        self.serial.flush().map_err(|_| NoteError::SerialWriteError)
+    }
+
+    fn available(&mut self) -> Result<usize, NoteError> {
+        let ready = self
+            .serial
+            .read_ready()
+            .map_err(|_| NoteError::SerialReadError)?;
+
+        Ok(if ready { SERIAL_CHUNK } else { 0 })
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> Result<(usize, usize), NoteError> {
+        let mut n = 0;
+
+        while n < buf.len() {
+            let sz = self
+                .serial
+                .read(&mut buf[n..])
+                .map_err(|_| NoteError::SerialReadError)?;
+
+            if sz == 0 {
+                return Err(NoteError::SerialReadError);
+            }
+
+            let end = buf
+                .get(n..n + sz)
+                .ok_or(NoteError::SerialReadError)?
+                .contains(&b'\n');
+            n += sz;
+
+            if end {
+                return Ok((n, 0));
+            }
+        }
+
+        Ok((n, SERIAL_CHUNK))
+    }
+}
+
+#[cfg(feature = "async")]
+mod asynch {
+    use embedded_io_async::{Read, ReadReady, Write};
+
+    use super::{SerialTransport, SERIAL_CHUNK};
+    use crate::asynch::AsyncNotecardTransport;
+    use crate::NoteError;
+
+    impl<S: Read + ReadReady + Write> AsyncNotecardTransport for SerialTransport<S> {
+        async fn ping(&mut self) -> bool {
+            self.serial.flush().await.is_ok()
+        }
+
+        async fn write(&mut self, data: &[u8]) -> Result<(), NoteError> {
+            self.serial
+                .write_all(data)
+                .await
+                .map_err(|_| NoteError::SerialWriteError)?;
+            self.serial
+                .flush()
+                .await
+                .map_err(|_| NoteError::SerialWriteError)
+        }
+
+        async fn available(&mut self) -> Result<usize, NoteError> {
+            let ready = self
+                .serial
+                .read_ready()
+                .map_err(|_| NoteError::SerialReadError)?;
+
+            Ok(if ready { SERIAL_CHUNK } else { 0 })
+        }
+
+        async fn read(&mut self, buf: &mut [u8]) -> Result<(usize, usize), NoteError> {
+            let mut n = 0;
+
+            while n < buf.len() {
+                let sz = self
+                    .serial
+                    .read(&mut buf[n..])
+                    .await
+                    .map_err(|_| NoteError::SerialReadError)?;
+
+                if sz == 0 {
+                    return Err(NoteError::SerialReadError);
+                }
+
+                let end = buf
+                    .get(n..n + sz)
+                    .ok_or(NoteError::SerialReadError)?
+                    .contains(&b'\n');
+                n += sz;
+
+                if end {
+                    return Ok((n, 0));
+                }
+            }
+
+            Ok((n, SERIAL_CHUNK))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A serial port with a canned response.
+    struct Port {
+        rx: &'static [u8],
+        tx: std::vec::Vec<u8>,
+    }
+
+    impl embedded_io::ErrorType for Port {
+        type Error = core::convert::Infallible;
+    }
+
+    impl Read for Port {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+            // Return at most 5 bytes at a time, like a UART FIFO.
+            let sz = buf.len().min(self.rx.len()).min(5);
+            buf[..sz].copy_from_slice(&self.rx[..sz]);
+            self.rx = &self.rx[sz..];
+            Ok(sz)
+        }
+    }
+
+    impl ReadReady for Port {
+        fn read_ready(&mut self) -> Result<bool, Self::Error> {
+            Ok(!self.rx.is_empty())
+        }
+    }
+
+    impl Write for Port {
+        fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+            self.tx.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> Result<(), Self::Error> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn read_line() {
+        let mut t = SerialTransport::new(Port {
+            rx: b"{\"connected\":true}\r\n",
+            tx: std::vec::Vec::new(),
+        });
+
+        t.write(b"{\"req\":\"hub.status\"}\n").unwrap();
+        assert_eq!(t.serial.tx, b"{\"req\":\"hub.status\"}\n");
+
+        assert_eq!(t.available().unwrap(), SERIAL_CHUNK);
+
+        let mut buf = [0u8; 8];
+        assert_eq!(t.read(&mut buf).unwrap(), (8, SERIAL_CHUNK));
+
+        let mut buf = [0u8; 64];
+        let (sz, avail) = t.read(&mut buf).unwrap();
+        assert_eq!(avail, 0);
+        assert_eq!(&buf[..sz], b"ted\":true}\r\n");
+
+        assert_eq!(t.available().unwrap(), 0);
+    }
+}
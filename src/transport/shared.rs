@@ -0,0 +1,148 @@
+//! Sharing the I2C bus with other devices.
+//!
+//! [`I2cTransport`](super::I2cTransport) works with any I2C device implementing the
+//! `embedded-hal` traits, so bus proxies from e.g. [shared-bus](https://docs.rs/shared-bus) can be
+//! used directly. Otherwise the bus can be borrowed for each transaction from a [`RefCell`] with
+//! [`RefCellI2c`]:
+//!
+//! ```ignore
+//! let bus = RefCell::new(i2c);
+//!
+//! let mut note = Notecard::<_>::new(RefCellI2c::new(&bus)).initialize(&mut delay)?;
+//! let mut sensor = Sensor::new(RefCellI2c::new(&bus));
+//! ```
+//!
+//! or from a mutable reference with [`BorrowedI2c`], using [`Notecard::suspend`](crate::Notecard::suspend) and
+//! [`Notecard::resume`](crate::Notecard::resume) to release the bus between requests.
+
+use core::cell::RefCell;
+
+use embedded_hal::blocking::i2c::{Read, SevenBitAddress, Write};
+
+/// An I2C bus borrowed from a [`RefCell`] for each transaction.
+pub struct RefCellI2c<'a, I2C> {
+    bus: &'a RefCell<I2C>,
+}
+
+impl<'a, I2C> RefCellI2c<'a, I2C> {
+    pub fn new(bus: &'a RefCell<I2C>) -> RefCellI2c<'a, I2C> {
+        RefCellI2c { bus }
+    }
+}
+
+impl<I2C: Write<SevenBitAddress>> Write<SevenBitAddress> for RefCellI2c<'_, I2C> {
+    type Error = I2C::Error;
+
+    fn write(&mut self, address: SevenBitAddress, bytes: &[u8]) -> Result<(), Self::Error> {
+        self.bus.borrow_mut().write(address, bytes)
+    }
+}
+
+impl<I2C: Read<SevenBitAddress>> Read<SevenBitAddress> for RefCellI2c<'_, I2C> {
+    type Error = I2C::Error;
+
+    fn read(&mut self, address: SevenBitAddress, buffer: &mut [u8]) -> Result<(), Self::Error> {
+        self.bus.borrow_mut().read(address, buffer)
+    }
+}
+
+/// An I2C bus borrowed through a mutable reference.
+pub struct BorrowedI2c<'a, I2C> {
+    bus: &'a mut I2C,
+}
+
+impl<'a, I2C> BorrowedI2c<'a, I2C> {
+    pub fn new(bus: &'a mut I2C) -> BorrowedI2c<'a, I2C> {
+        BorrowedI2c { bus }
+    }
+}
+
+impl<I2C: Write<SevenBitAddress>> Write<SevenBitAddress> for BorrowedI2c<'_, I2C> {
+    type Error = I2C::Error;
+
+    fn write(&mut self, address: SevenBitAddress, bytes: &[u8]) -> Result<(), Self::Error> {
+        self.bus.write(address, bytes)
+    }
+}
+
+impl<I2C: Read<SevenBitAddress>> Read<SevenBitAddress> for BorrowedI2c<'_, I2C> {
+    type Error = I2C::Error;
+
+    fn read(&mut self, address: SevenBitAddress, buffer: &mut [u8]) -> Result<(), Self::Error> {
+        self.bus.read(address, buffer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NotecardTransport;
+
+    /// Bus that logs the addresses written to.
+    #[derive(Default)]
+    struct Bus {
+        writes: std::vec::Vec<u8>,
+    }
+
+    impl Write<SevenBitAddress> for Bus {
+        type Error = ();
+
+        fn write(&mut self, address: SevenBitAddress, _bytes: &[u8]) -> Result<(), ()> {
+            self.writes.push(address);
+            Ok(())
+        }
+    }
+
+    impl Read<SevenBitAddress> for Bus {
+        type Error = ();
+
+        fn read(&mut self, _address: SevenBitAddress, buffer: &mut [u8]) -> Result<(), ()> {
+            buffer.fill(0);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn shared_refcell() {
+        let bus = RefCell::new(Bus::default());
+
+        let mut a = crate::I2cTransport::new(RefCellI2c::new(&bus), 0x17);
+        let mut b = crate::I2cTransport::new(RefCellI2c::new(&bus), 0x18);
+
+        assert!(a.ping());
+        assert_eq!(b.available().unwrap(), 0);
+        assert!(a.ping());
+
+        assert_eq!(bus.borrow().writes, [0x17, 0x18, 0x17]);
+    }
+
+    #[test]
+    fn two_notecards() {
+        let bus = RefCell::new(Bus::default());
+
+        let mut cell: crate::Notecard<_, 256, crate::Uninit> =
+            crate::Notecard::new(RefCellI2c::new(&bus));
+        let mut lora: crate::Notecard<_, 256, crate::Uninit> =
+            crate::Notecard::new_with_address(RefCellI2c::new(&bus), 0x18);
+
+        assert!(cell.ping());
+        assert!(lora.ping());
+        assert_eq!(cell.transport().addr(), crate::DEFAULT_I2C_ADDR);
+        assert_eq!(lora.transport().addr(), 0x18);
+
+        assert_eq!(bus.borrow().writes, [0x17, 0x18]);
+    }
+
+    #[test]
+    fn borrowed() {
+        let mut bus = Bus::default();
+
+        {
+            let mut t = crate::I2cTransport::new(BorrowedI2c::new(&mut bus), 0x17);
+            assert!(t.ping());
+        }
+
+        bus.write(0x42, &[]).unwrap();
+        assert_eq!(bus.writes, [0x17, 0x42]);
+    }
+}
@@ -0,0 +1,178 @@
+//! Transport for a Notecard connected to a host over USB (e.g. the USB port of a Notecarrier),
+//! or through a USB serial adapter (FTDI) connected to the UART of the Notecard.
+//!
+//! The port can be any `std::io::Read + std::io::Write`, e.g. from the
+//! [serialport](https://docs.rs/serialport) crate. Reads should time out so that the driver can
+//! poll for responses:
+//!
+//! ```ignore
+//! let port = serialport::new("/dev/ttyACM0", 9600)
+//!     .timeout(std::time::Duration::from_millis(10))
+//!     .open()?;
+//!
+//! let mut note = Notecard::<_>::new_with_transport(UsbSerialTransport::new(port), NotecardConfig::default())
+//!     .initialize(&mut delay)?;
+//! ```
+
+use std::io::{ErrorKind, Read, Write};
+use std::vec::Vec;
+
+use super::NotecardTransport;
+use crate::NoteError;
+
+/// Number of bytes reported available while a response is being received, the actual length of
+/// the response is not known before the new-line is received.
+const SERIAL_CHUNK: usize = 128;
+
+/// Number of consecutive reads without data before giving up on the rest of a response.
+const READ_RETRIES: usize = 100;
+
+/// A Notecard connected to a host serial port.
+pub struct UsbSerialTransport<S: Read + Write> {
+    port: S,
+
+    /// Bytes read from the port, but not yet returned.
+    rx: Vec<u8>,
+}
+
+impl<S: Read + Write> UsbSerialTransport<S> {
+    pub fn new(port: S) -> UsbSerialTransport<S> {
+        UsbSerialTransport {
+            port,
+            rx: Vec::new(),
+        }
+    }
+
+    /// Release the serial port.
+    pub fn free(self) -> S {
+        self.port
+    }
+
+    /// Read whatever is available from the port, returns the number of bytes read.
+    fn fill(&mut self) -> Result<usize, NoteError> {
+        let mut tmp = [0u8; 256];
+
+        match self.port.read(&mut tmp) {
+            Ok(n) => {
+                self.rx.extend_from_slice(&tmp[..n]);
+                Ok(n)
+            }
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    ErrorKind::TimedOut | ErrorKind::WouldBlock | ErrorKind::Interrupted
+                ) =>
+            {
+                Ok(0)
+            }
+            Err(_) => Err(NoteError::SerialReadError),
+        }
+    }
+}
+
+impl<S: Read + Write> NotecardTransport for UsbSerialTransport<S> {
+    fn ping(&mut self) -> bool {
+        self.port.flush().is_ok()
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<(), NoteError> {
+        self.port
+            .write_all(data)
+            .map_err(|_| NoteError::SerialWriteError)?;
+        self.port.flush().map_err(|_| NoteError::SerialWriteError)
+    }
+
+    fn available(&mut self) -> Result<usize, NoteError> {
+        if self.rx.is_empty() {
+            self.fill()?;
+        }
+
+        Ok(if self.rx.is_empty() { 0 } else { SERIAL_CHUNK })
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> Result<(usize, usize), NoteError> {
+        let mut retries = 0;
+
+        // Wait for the rest of the line, or enough data to fill `buf`.
+        while !self.rx.contains(&b'\n') && self.rx.len() < buf.len() {
+            if self.fill()? == 0 {
+                retries += 1;
+
+                if retries >= READ_RETRIES {
+                    return Err(NoteError::timeout(""));
+                }
+            }
+        }
+
+        let end = self.rx.iter().position(|c| *c == b'\n').map(|p| p + 1);
+        let n = buf.len().min(end.unwrap_or(self.rx.len()));
+
+        buf[..n].copy_from_slice(&self.rx[..n]);
+        self.rx.drain(..n);
+
+        Ok((n, if end == Some(n) { 0 } else { SERIAL_CHUNK }))
+    }
+
+    fn recover(&mut self) {
+        self.rx.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A port that returns the response a few bytes at a time, timing out in between.
+    struct Port {
+        rx: &'static [u8],
+        timeout: bool,
+        tx: Vec<u8>,
+    }
+
+    impl Read for Port {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.timeout = !self.timeout;
+            if self.timeout || self.rx.is_empty() {
+                return Err(ErrorKind::TimedOut.into());
+            }
+
+            let sz = buf.len().min(self.rx.len()).min(7);
+            buf[..sz].copy_from_slice(&self.rx[..sz]);
+            self.rx = &self.rx[sz..];
+            Ok(sz)
+        }
+    }
+
+    impl Write for Port {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.tx.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn read_response() {
+        let mut t = UsbSerialTransport::new(Port {
+            rx: b"{\"time\":1599769214}\r\n",
+            timeout: false,
+            tx: Vec::new(),
+        });
+
+        t.write(b"{\"req\":\"card.time\"}\n").unwrap();
+        assert_eq!(t.port.tx, b"{\"req\":\"card.time\"}\n");
+
+        assert_eq!(t.available().unwrap(), 0);
+        assert_eq!(t.available().unwrap(), SERIAL_CHUNK);
+
+        let mut buf = [0u8; 128];
+        let (sz, avail) = t.read(&mut buf).unwrap();
+        assert_eq!(avail, 0);
+        assert_eq!(&buf[..sz], b"{\"time\":1599769214}\r\n");
+
+        assert_eq!(t.available().unwrap(), 0);
+    }
+}
@@ -0,0 +1,269 @@
+//! <https://dev.blues.io/api-reference/notecard-api/var-requests/>
+//!
+//! Simple variables stored as notes in a DB Notefile (`vars.db` by default).
+
+#[allow(unused_imports)]
+use crate::fmt::{debug, error, info, trace, warn};
+use embedded_hal::blocking::delay::DelayMs;
+use serde::{Deserialize, Serialize};
+
+use super::notefile;
+use super::{FutureResponse, NoteError, Notecard, NotecardTransport};
+
+/// Value of a variable.
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "defmt-format", derive(defmt::Format))]
+pub enum Value<'a> {
+    Text(&'a str),
+    Value(f64),
+    Flag(bool),
+}
+
+pub struct Var<'a, IOM: NotecardTransport, const BS: usize> {
+    note: &'a mut Notecard<IOM, BS>,
+}
+
+impl<'a, IOM: NotecardTransport, const BS: usize> Var<'a, IOM, BS> {
+    pub fn from(note: &mut Notecard<IOM, BS>) -> Var<'_, IOM, BS> {
+        Var { note }
+    }
+
+    /// Retrieves the variable `name` from the DB Notefile `file` (`vars.db` if not specified).
+    pub fn get(
+        self,
+        delay: &mut impl DelayMs<u16>,
+        name: &str,
+        file: Option<&str>,
+    ) -> Result<FutureResponse<'a, res::Get, IOM, BS>, NoteError> {
+        self.note.request(
+            delay,
+            req::Get {
+                file: notefile::check(file)?,
+                ..req::Get::new(name)
+            },
+        )?;
+        Ok(FutureResponse::from(self.note))
+    }
+
+    /// Sets the variable `name` in the DB Notefile `file` (`vars.db` if not specified).
+    pub fn set(
+        self,
+        delay: &mut impl DelayMs<u16>,
+        name: &str,
+        file: Option<&str>,
+        value: Value,
+        sync: bool,
+    ) -> Result<FutureResponse<'a, res::Empty, IOM, BS>, NoteError> {
+        let (text, value, flag) = match value {
+            Value::Text(text) => (Some(text), None, None),
+            Value::Value(value) => (None, Some(value), None),
+            Value::Flag(flag) => (None, None, Some(flag)),
+        };
+
+        self.note.request(
+            delay,
+            req::Set {
+                file: notefile::check(file)?,
+                text,
+                value,
+                flag,
+                sync: sync.then_some(true),
+                ..req::Set::new(name)
+            },
+        )?;
+        Ok(FutureResponse::from(self.note))
+    }
+
+    /// Deletes the variable `name` from the DB Notefile `file` (`vars.db` if not specified).
+    pub fn delete(
+        self,
+        delay: &mut impl DelayMs<u16>,
+        name: &str,
+        file: Option<&str>,
+        sync: bool,
+    ) -> Result<FutureResponse<'a, res::Empty, IOM, BS>, NoteError> {
+        self.note.request(
+            delay,
+            req::Delete {
+                file: notefile::check(file)?,
+                sync: sync.then_some(true),
+                ..req::Delete::new(name)
+            },
+        )?;
+        Ok(FutureResponse::from(self.note))
+    }
+}
+
+pub mod req {
+    use super::*;
+
+    #[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "defmt-format", derive(defmt::Format))]
+    pub struct Get<'a> {
+        pub req: &'a str,
+
+        pub name: &'a str,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub file: Option<&'a str>,
+    }
+
+    impl<'a> Get<'a> {
+        /// A `var.get` request for the variable `name`.
+        pub fn new(name: &'a str) -> Get<'a> {
+            Get {
+                req: "var.get",
+                name,
+                ..Default::default()
+            }
+        }
+    }
+
+    #[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "defmt-format", derive(defmt::Format))]
+    pub struct Set<'a> {
+        pub req: &'a str,
+
+        pub name: &'a str,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub file: Option<&'a str>,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub text: Option<&'a str>,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub value: Option<f64>,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub flag: Option<bool>,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub sync: Option<bool>,
+    }
+
+    impl<'a> Set<'a> {
+        /// A `var.set` request for the variable `name`.
+        pub fn new(name: &'a str) -> Set<'a> {
+            Set {
+                req: "var.set",
+                name,
+                ..Default::default()
+            }
+        }
+    }
+
+    #[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "defmt-format", derive(defmt::Format))]
+    pub struct Delete<'a> {
+        pub req: &'a str,
+
+        pub name: &'a str,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub file: Option<&'a str>,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub sync: Option<bool>,
+    }
+
+    impl<'a> Delete<'a> {
+        /// A `var.delete` request for the variable `name`.
+        pub fn new(name: &'a str) -> Delete<'a> {
+            Delete {
+                req: "var.delete",
+                name,
+                ..Default::default()
+            }
+        }
+    }
+}
+
+pub mod res {
+    use super::*;
+
+    #[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "defmt-format", derive(defmt::Format))]
+    pub struct Empty {}
+
+    #[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "defmt-format", derive(defmt::Format))]
+    pub struct Get {
+        pub text: Option<heapless::String<256>>,
+        pub value: Option<f64>,
+        pub flag: Option<bool>,
+    }
+
+    impl Get {
+        /// The value of the variable, or `None` if it is not set.
+        pub fn var(&self) -> Option<Value<'_>> {
+            if let Some(text) = &self.text {
+                Some(Value::Text(text))
+            } else if let Some(value) = self.value {
+                Some(Value::Value(value))
+            } else {
+                self.flag.map(Value::Flag)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn get_variants() {
+        let (r, _) = serde_json_core::from_str::<res::Get>(r#"{"text":"blue"}"#).unwrap();
+        assert_eq!(r.var(), Some(Value::Text("blue")));
+
+        let (r, _) = serde_json_core::from_str::<res::Get>(r#"{"value":22.5}"#).unwrap();
+        assert_eq!(r.var(), Some(Value::Value(22.5)));
+
+        let (r, _) = serde_json_core::from_str::<res::Get>(r#"{"flag":false}"#).unwrap();
+        assert_eq!(r.var(), Some(Value::Flag(false)));
+
+        let (r, _) = serde_json_core::from_str::<res::Get>(r#"{}"#).unwrap();
+        assert_eq!(r.var(), None);
+    }
+
+    mod vectors {
+        use super::*;
+
+        vector_res!(get, "var.get.res.json", res::Get);
+        vector_res!(set, "var.set.res.json", res::Empty);
+        vector_res!(delete, "var.delete.res.json", res::Empty);
+
+        vector_req!(
+            get_req,
+            "var.get.req.json",
+            req::Get {
+                req: "var.get",
+                name: "status",
+                file: None,
+            }
+        );
+
+        vector_req!(
+            set_req,
+            "var.set.req.json",
+            req::Set {
+                req: "var.set",
+                name: "status",
+                text: Some("open"),
+                ..Default::default()
+            }
+        );
+
+        vector_req!(
+            delete_req,
+            "var.delete.req.json",
+            req::Delete {
+                req: "var.delete",
+                name: "status",
+                file: None,
+                sync: None,
+            }
+        );
+    }
+}
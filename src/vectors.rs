@@ -0,0 +1,207 @@
+//! Round-trip tests against the request and response examples from the Notecard API reference.
+//!
+//! The examples are bundled in `tests/vectors/` as `<request>.req.json` and `<request>.res.json`.
+//! Add new examples there when implementing a request, and register them in the test module of
+//! the request with [`vector_req!`] and [`vector_res!`]. The tests of this module fail if a request
+//! made in `src/` has no example, or if an example is not registered.
+
+/// Load an example from `tests/vectors/`.
+macro_rules! vector {
+    ($file:literal) => {
+        include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/vectors/",
+            $file
+        ))
+    };
+}
+
+/// Serialize the request and compare it against the example.
+macro_rules! vector_req {
+    ($name:ident, $file:literal, $req:expr) => {
+        #[test]
+        fn $name() {
+            let expected: serde_json::Value = serde_json::from_str(vector!($file)).unwrap();
+
+            let r = serde_json_core::to_string::<_, { crate::DEFAULT_BUF_SIZE }>(&$req).unwrap();
+            let r: serde_json::Value = serde_json::from_str(&r).unwrap();
+
+            assert_eq!(r, expected, "request does not match example in {}", $file);
+        }
+    };
+}
+
+/// Deserialize the example into the response type, and check that serializing the response
+/// gives back the example. Fields of the response type that are not in the example must be
+/// `None`, or `false` for flags that default to `false`.
+macro_rules! vector_res {
+    ($name:ident, $file:literal, $res:ty) => {
+        #[test]
+        fn $name() {
+            let (r, _) = serde_json_core::from_str::<$res>(vector!($file).trim())
+                .unwrap_or_else(|e| panic!("failed to deserialize {}: {:?}", $file, e));
+
+            let expected: serde_json::Value = serde_json::from_str(vector!($file)).unwrap();
+            let r = crate::vectors::without_unset(serde_json::to_value(&r).unwrap(), &expected);
+
+            assert_eq!(
+                crate::vectors::normalized(r),
+                crate::vectors::normalized(expected),
+                "response does not match example in {}",
+                $file
+            );
+        }
+    };
+}
+
+/// Remove the fields of `v` that are not in `expected` and are `null` or `false`, i.e. fields of
+/// the response type that were not set by the example.
+pub(crate) fn without_unset(
+    v: serde_json::Value,
+    expected: &serde_json::Value,
+) -> serde_json::Value {
+    use serde_json::Value;
+
+    match (v, expected) {
+        (Value::Object(m), Value::Object(e)) => Value::Object(
+            m.into_iter()
+                .filter_map(|(k, v)| match e.get(&k) {
+                    Some(e) => Some((k, without_unset(v, e))),
+                    None if v.is_null() || v == Value::Bool(false) => None,
+                    None => Some((k, v)),
+                })
+                .collect(),
+        ),
+        (Value::Array(a), Value::Array(e)) => Value::Array(
+            a.into_iter()
+                .zip(e.iter().chain(core::iter::repeat(&Value::Null)))
+                .map(|(v, e)| without_unset(v, e))
+                .collect(),
+        ),
+        (v, _) => v,
+    }
+}
+
+/// Compare all numbers as floats, e.g. `1` in an example is `1.0` in an `f32` field.
+pub(crate) fn normalized(v: serde_json::Value) -> serde_json::Value {
+    use serde_json::Value;
+
+    match v {
+        Value::Object(m) => Value::Object(m.into_iter().map(|(k, v)| (k, normalized(v))).collect()),
+        Value::Array(a) => Value::Array(a.into_iter().map(normalized).collect()),
+        Value::Number(n) => n
+            .as_f64()
+            .and_then(|f| serde_json::Number::from_f64(f as f32 as f64))
+            .map_or(Value::Number(n), Value::Number),
+        v => v,
+    }
+}
+
+mod tests {
+    use std::collections::BTreeSet;
+    use std::path::Path;
+    use std::string::{String, ToString};
+    use std::vec::Vec;
+
+    /// Contents of the source files in `src/`.
+    fn sources() -> Vec<String> {
+        let mut sources = Vec::new();
+        let mut dirs = vec![Path::new(env!("CARGO_MANIFEST_DIR")).join("src")];
+
+        while let Some(dir) = dirs.pop() {
+            for entry in std::fs::read_dir(dir).unwrap() {
+                let path = entry.unwrap().path();
+                if path.is_dir() {
+                    dirs.push(path);
+                } else {
+                    sources.push(std::fs::read_to_string(&path).unwrap());
+                }
+            }
+        }
+
+        sources
+    }
+
+    /// Request names made outside of tests in `src/`: `(name, with_arguments)`.
+    fn requests() -> BTreeSet<(String, bool)> {
+        let mut names = BTreeSet::new();
+
+        for source in sources() {
+            let source = source
+                .split("\n#[cfg(test)]")
+                .next()
+                .and_then(|s| s.split("\n#[cfg(all(test").next())
+                .unwrap();
+
+            for (pattern, args) in [("req: \"", true), ("{\\\"req\\\":\\\"", false)] {
+                for (i, _) in source.match_indices(pattern) {
+                    let name: String = source[i + pattern.len()..]
+                        .chars()
+                        .take_while(|c| c.is_ascii_alphanumeric() || *c == '.' || *c == '-')
+                        .collect();
+                    if name.contains('.') {
+                        names.insert((name, args));
+                    }
+                }
+            }
+        }
+
+        names
+    }
+
+    /// Files in `tests/vectors/`.
+    fn vectors() -> BTreeSet<String> {
+        std::fs::read_dir(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/vectors"))
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn every_request_has_a_vector() {
+        let vectors = vectors();
+        let requests = requests();
+
+        let missing: Vec<_> = requests
+            .iter()
+            .filter(|(name, args)| {
+                // Examples of a request in different modes, e.g. `card.aux.gpio.req.json`.
+                let req = vectors.iter().any(|v| {
+                    v.strip_prefix(name.as_str())
+                        .and_then(|v| v.strip_suffix(".req.json"))
+                        .is_some_and(|mode| {
+                            mode.is_empty()
+                                || (mode.starts_with('.')
+                                    && !requests.contains(&(format!("{}{}", name, mode), true)))
+                        })
+                });
+                let res = vectors.contains(&format!("{}.res.json", name));
+
+                // Requests without arguments only need an example of the response.
+                !(req || (res && !args))
+            })
+            .collect();
+
+        assert!(
+            missing.is_empty(),
+            "requests without examples: {:?}",
+            missing
+        );
+    }
+
+    #[test]
+    fn every_vector_is_tested() {
+        let sources = sources().concat();
+
+        let untested: Vec<_> = vectors()
+            .into_iter()
+            .filter(|v| !sources.contains(&format!("\"{}\"", v)))
+            .collect();
+
+        assert!(
+            untested.is_empty(),
+            "examples without tests: {:?}",
+            untested
+        );
+    }
+}
@@ -0,0 +1,697 @@
+//! <https://dev.blues.io/reference/notecard-api/web-requests/>
+//!
+//! Payloads that do not fit in a single request can be sent in fragments with [`WebTransfer`]:
+//!
+//! ```ignore
+//! let mut buf = [0u8; 1024];
+//! let mut transfer = WebTransfer::new(&mut buf).content("image/jpeg");
+//!
+//! let r = transfer.post(&mut note, &mut delay, "upload", Some("/image"), &mut &image[..], |sent, total| {
+//!     info!("web: {} / {}", sent, total);
+//! })?;
+//! ```
+
+#[allow(unused_imports)]
+use crate::fmt::{debug, error, info, trace, warn};
+use embedded_hal::blocking::delay::DelayMs;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use super::binary::BinarySource;
+use super::card::md5_hex;
+use super::hub;
+use super::{payload, FutureResponse, NoteError, Notecard, NotecardTransport};
+
+pub struct Web<'a, IOM: NotecardTransport, const BS: usize> {
+    note: &'a mut Notecard<IOM, BS>,
+}
+
+impl<'a, IOM: NotecardTransport, const BS: usize> Web<'a, IOM, BS> {
+    pub fn from(note: &mut Notecard<IOM, BS>) -> Web<'_, IOM, BS> {
+        Web { note }
+    }
+
+    /// Verify that the Notecard is in continuous mode and connected to Notehub, which is required
+    /// for web requests. Returns [`NoteError::NotConnected`] otherwise, rather than the error of the
+    /// Notecard when making the request:
+    ///
+    /// ```ignore
+    /// note.web().preflight(&mut delay)?.post(&mut delay, "route", ...)?.wait(&mut delay)?;
+    /// ```
+    pub fn preflight(self, delay: &mut impl DelayMs<u16>) -> Result<Self, NoteError> {
+        let mode = self.note.hub().get(delay)?.wait(delay)?.mode;
+        if !matches!(mode, Some(hub::req::HubMode::Continuous)) {
+            warn!("web: hub is not in continuous mode: {:?}", mode);
+            return Err(NoteError::NotConnected);
+        }
+
+        let status = self.note.hub().status(delay)?.wait(delay)?;
+        if !status.connected {
+            warn!("web: not connected to notehub: {:?}", status.status);
+            return Err(NoteError::NotConnected);
+        }
+
+        Ok(self)
+    }
+
+    /// Performs a HTTP GET request through the Notehub proxy `route`, with `name` appended to the
+    /// URL of the route. `content` is the MIME type of the request, `application/json` by default.
+    ///
+    /// The body of the HTTP response is deserialized into `B`, use [`res::Empty`] to ignore it.
+    pub fn get<B: DeserializeOwned>(
+        self,
+        delay: &mut impl DelayMs<u16>,
+        route: &str,
+        name: Option<&str>,
+        content: Option<&str>,
+    ) -> Result<FutureResponse<'a, res::Post<B>, IOM, BS>, NoteError> {
+        self.note.request(
+            delay,
+            req::Get {
+                name,
+                content,
+                ..req::Get::new(route)
+            },
+        )?;
+        Ok(FutureResponse::from(self.note))
+    }
+
+    /// Performs a HTTP DELETE request through the Notehub proxy `route`, with `name` appended to
+    /// the URL of the route.
+    ///
+    /// The body of the HTTP response is deserialized into `B`, use [`res::Empty`] to ignore it.
+    pub fn delete<B: DeserializeOwned>(
+        self,
+        delay: &mut impl DelayMs<u16>,
+        route: &str,
+        name: Option<&str>,
+        content: Option<&str>,
+    ) -> Result<FutureResponse<'a, res::Post<B>, IOM, BS>, NoteError> {
+        self.note.request(
+            delay,
+            req::Delete {
+                name,
+                content,
+                ..req::Delete::new(route)
+            },
+        )?;
+        Ok(FutureResponse::from(self.note))
+    }
+
+    /// Performs a HTTP POST request through the Notehub proxy `route`, with `name` appended to
+    /// the URL of the route. Either a JSON `body` or a base64 encoded `payload` can be sent, with
+    /// `content` as the MIME type of the payload.
+    ///
+    /// Payloads larger than what fits in one request are sent as fragments by specifying the
+    /// `total` size of the payload, the `offset` of this fragment and the MD5 `status` of the
+    /// fragment.
+    ///
+    /// The body of the HTTP response is deserialized into `B`, use [`res::Empty`] to ignore it.
+    #[allow(clippy::too_many_arguments)]
+    pub fn post<T: Serialize + Default, B: DeserializeOwned>(
+        self,
+        delay: &mut impl DelayMs<u16>,
+        route: &str,
+        name: Option<&str>,
+        body: Option<T>,
+        payload: Option<&str>,
+        content: Option<&str>,
+        total: Option<u32>,
+        offset: Option<u32>,
+        status: Option<&str>,
+    ) -> Result<FutureResponse<'a, res::Post<B>, IOM, BS>, NoteError> {
+        self.note.request(
+            delay,
+            req::Post {
+                name,
+                body,
+                payload,
+                content,
+                total,
+                offset,
+                status,
+                ..req::Post::<T>::new(route)
+            },
+        )?;
+        Ok(FutureResponse::from(self.note))
+    }
+
+    /// Performs a HTTP PUT request through the Notehub proxy `route`, with the same arguments as
+    /// [`Web::post`].
+    #[allow(clippy::too_many_arguments)]
+    pub fn put<T: Serialize + Default, B: DeserializeOwned>(
+        self,
+        delay: &mut impl DelayMs<u16>,
+        route: &str,
+        name: Option<&str>,
+        body: Option<T>,
+        payload: Option<&str>,
+        content: Option<&str>,
+        total: Option<u32>,
+        offset: Option<u32>,
+        status: Option<&str>,
+    ) -> Result<FutureResponse<'a, res::Post<B>, IOM, BS>, NoteError> {
+        self.note.request(
+            delay,
+            req::Post {
+                name,
+                body,
+                payload,
+                content,
+                total,
+                offset,
+                status,
+                ..req::Post::<T>::put(route)
+            },
+        )?;
+        Ok(FutureResponse::from(self.note))
+    }
+}
+
+/// Sends a payload larger than a single request to a Notehub route as fragments, each verified
+/// by its MD5. The Notecard performs the HTTP request once the last fragment has been received.
+pub struct WebTransfer<'b> {
+    buf: &'b mut [u8],
+    content: Option<&'b str>,
+    binary: bool,
+    preflight: bool,
+}
+
+impl<'b> WebTransfer<'b> {
+    /// Create a new transfer using `buf` for staging fragments. With base64 payloads three
+    /// sevenths of the buffer is used for the fragment, and the rest for encoding it. The encoded
+    /// fragment must fit in the buffer of the [`Notecard`].
+    pub fn new(buf: &'b mut [u8]) -> WebTransfer<'b> {
+        WebTransfer {
+            buf,
+            content: None,
+            binary: false,
+            preflight: false,
+        }
+    }
+
+    /// MIME type of the payload.
+    pub fn content(self, content: &'b str) -> WebTransfer<'b> {
+        WebTransfer {
+            content: Some(content),
+            ..self
+        }
+    }
+
+    /// Send the fragments through the binary storage area of the Notecard (`card.binary.put`)
+    /// instead of as base64 encoded payloads. The whole buffer is used for the fragment.
+    pub fn binary(self, binary: bool) -> WebTransfer<'b> {
+        WebTransfer { binary, ..self }
+    }
+
+    /// Check the connection with [`Web::preflight`] before sending the first fragment.
+    pub fn preflight(self, preflight: bool) -> WebTransfer<'b> {
+        WebTransfer { preflight, ..self }
+    }
+
+    /// Post the data from `source` to `route` using `web.post`. `progress` is called with the
+    /// number of bytes sent so far and the total size after each fragment. Returns the response to
+    /// the last fragment, with the body of the HTTP response deserialized into `B`.
+    pub fn post<B: DeserializeOwned, IOM: NotecardTransport, const BS: usize>(
+        &mut self,
+        note: &mut Notecard<IOM, BS>,
+        delay: &mut impl DelayMs<u16>,
+        route: &str,
+        name: Option<&str>,
+        source: &mut impl BinarySource,
+        progress: impl FnMut(usize, usize),
+    ) -> Result<res::Post<B>, NoteError> {
+        let request = req::Post {
+            name,
+            ..req::Post::<()>::new(route)
+        };
+        self.send(note, delay, request, source, progress)
+    }
+
+    /// Put the data from `source` to `route` using `web.put`, like [`WebTransfer::post`].
+    pub fn put<B: DeserializeOwned, IOM: NotecardTransport, const BS: usize>(
+        &mut self,
+        note: &mut Notecard<IOM, BS>,
+        delay: &mut impl DelayMs<u16>,
+        route: &str,
+        name: Option<&str>,
+        source: &mut impl BinarySource,
+        progress: impl FnMut(usize, usize),
+    ) -> Result<res::Post<B>, NoteError> {
+        let request = req::Post {
+            name,
+            ..req::Post::<()>::put(route)
+        };
+        self.send(note, delay, request, source, progress)
+    }
+
+    /// Send the data from `source` in fragments of `request`.
+    fn send<B: DeserializeOwned, IOM: NotecardTransport, const BS: usize>(
+        &mut self,
+        note: &mut Notecard<IOM, BS>,
+        delay: &mut impl DelayMs<u16>,
+        request: req::Post<'_, ()>,
+        source: &mut impl BinarySource,
+        mut progress: impl FnMut(usize, usize),
+    ) -> Result<res::Post<B>, NoteError> {
+        let total = source.size();
+        let mut offset = 0;
+
+        let chunk = if self.binary {
+            self.buf.len()
+        } else {
+            self.buf.len() / 7 * 3
+        };
+
+        if chunk == 0 {
+            return Err(NoteError::BufOverflow);
+        }
+
+        if self.preflight {
+            note.web().preflight(delay)?;
+        }
+
+        loop {
+            let (data, enc) = self.buf.split_at_mut(chunk);
+
+            let sz = source.read(offset, data)?;
+            if sz == 0 && offset < total {
+                error!("web: source ended after {} of {} bytes.", offset, total);
+                return Err(NoteError::SourceEnded {
+                    read: offset,
+                    size: total,
+                });
+            }
+            let data = &data[..sz];
+            let status = md5_hex(data);
+
+            let payload = if self.binary {
+                note.card().binary(delay, true)?.wait(delay)?;
+                note.card().binary_put(delay, data, None)?.wait(delay)?;
+                None
+            } else {
+                Some(payload::encode(data, enc)?)
+            };
+
+            trace!("web: sending fragment at {} ({} bytes) of {}", offset, sz, total);
+
+            note.request(
+                delay,
+                req::Post::<()> {
+                    payload,
+                    content: self.content,
+                    total: Some(total as u32),
+                    offset: Some(offset as u32),
+                    status: Some(&status),
+                    binary: self.binary.then_some(true),
+                    ..request.clone()
+                },
+            )?;
+            let r = FutureResponse::<res::Post<B>, IOM, BS>::from(note).wait(delay)?;
+
+            offset += sz;
+            progress(offset, total);
+
+            if offset >= total {
+                return Ok(r);
+            }
+        }
+    }
+}
+
+pub mod req {
+    use super::*;
+
+    #[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+    pub struct Post<'a, T: Serialize + Default> {
+        pub req: &'a str,
+
+        pub route: &'a str,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub name: Option<&'a str>,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub body: Option<T>,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub payload: Option<&'a str>,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub content: Option<&'a str>,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub total: Option<u32>,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub offset: Option<u32>,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub status: Option<&'a str>,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub binary: Option<bool>,
+    }
+
+    impl<'a, T: Serialize + Default> Post<'a, T> {
+        /// A `web.post` request through the Notehub proxy `route`.
+        pub fn new(route: &'a str) -> Post<'a, T> {
+            Post {
+                req: "web.post",
+                route,
+                ..Default::default()
+            }
+        }
+
+        /// A `web.put` request through the Notehub proxy `route`, which takes the same arguments
+        /// as `web.post`.
+        pub fn put(route: &'a str) -> Post<'a, T> {
+            Post {
+                req: "web.put",
+                route,
+                ..Default::default()
+            }
+        }
+    }
+
+    #[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+    pub struct Get<'a> {
+        pub req: &'a str,
+
+        pub route: &'a str,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub name: Option<&'a str>,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub content: Option<&'a str>,
+    }
+
+    impl<'a> Get<'a> {
+        /// A `web.get` request through the Notehub proxy `route`.
+        pub fn new(route: &'a str) -> Get<'a> {
+            Get {
+                req: "web.get",
+                route,
+                ..Default::default()
+            }
+        }
+    }
+
+    #[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+    pub struct Delete<'a> {
+        pub req: &'a str,
+
+        pub route: &'a str,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub name: Option<&'a str>,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub content: Option<&'a str>,
+    }
+
+    impl<'a> Delete<'a> {
+        /// A `web.delete` request through the Notehub proxy `route`.
+        pub fn new(route: &'a str) -> Delete<'a> {
+            Delete {
+                req: "web.delete",
+                route,
+                ..Default::default()
+            }
+        }
+    }
+}
+
+pub mod res {
+    use super::*;
+
+    #[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "defmt-format", derive(defmt::Format))]
+    pub struct Empty {}
+
+    /// Response to a web request (`web.get`, `web.post`, `web.put` or `web.delete`). The JSON body of the HTTP response is deserialized into `B`,
+    /// while other content types are returned base64 encoded in `payload`.
+    #[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "defmt-format", derive(defmt::Format))]
+    pub struct Post<B = Empty, S = heapless::String<1024>> {
+        /// HTTP status code of the response.
+        pub result: Option<u16>,
+
+        pub body: Option<B>,
+
+        pub payload: Option<S>,
+
+        /// Length of the payload.
+        pub length: Option<u32>,
+    }
+
+    impl<B, S: AsRef<str>> Post<B, S> {
+        /// Decode the payload into `out`. Returns an empty slice if there is no payload.
+        pub fn decode_payload<'b>(&self, out: &'b mut [u8]) -> Result<&'b [u8], NoteError> {
+            let sz = match &self.payload {
+                Some(p) => payload::decode(p.as_ref(), out)?,
+                None => 0,
+            };
+            Ok(&out[..sz])
+        }
+
+        /// Decode the payload. Returns an empty vector if there is no payload.
+        #[cfg(feature = "alloc")]
+        pub fn decode_payload_vec(&self) -> Result<alloc::vec::Vec<u8>, NoteError> {
+            match &self.payload {
+                Some(p) => payload::decode_vec(p.as_ref()),
+                None => Ok(alloc::vec::Vec::new()),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn post_fragment() {
+        let r = req::Post::<()> {
+            req: "web.post",
+            route: "upload",
+            payload: Some("aGVsbG8="),
+            content: Some("application/octet-stream"),
+            total: Some(10),
+            offset: Some(5),
+            status: Some("5d41402abc4b2a76b9719d911017c592"),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            &serde_json_core::to_string::<_, 256>(&r).unwrap(),
+            r#"{"req":"web.post","route":"upload","payload":"aGVsbG8=","content":"application/octet-stream","total":10,"offset":5,"status":"5d41402abc4b2a76b9719d911017c592"}"#
+        );
+    }
+
+    #[test]
+    fn post_response() {
+        let (r, _) = serde_json_core::from_str::<res::Post>(
+            r#"{"result":200,"payload":"aGVsbG8=","length":5,"body":{"ok":true}}"#,
+        )
+        .unwrap();
+        assert_eq!(r.result, Some(200));
+        assert_eq!(r.length, Some(5));
+
+        let mut buf = [0u8; 8];
+        assert_eq!(r.decode_payload(&mut buf).unwrap(), b"hello");
+    }
+
+    mod vectors {
+        use super::*;
+
+        #[derive(serde::Serialize, serde::Deserialize, Default)]
+        struct Reading {
+            temp: f32,
+            humidity: f32,
+        }
+
+        #[derive(serde::Serialize, serde::Deserialize)]
+        struct Status {
+            status: heapless::String<8>,
+        }
+
+        vector_res!(post, "web.post.res.json", res::Post<Status>);
+        vector_res!(put, "web.put.res.json", res::Post<Status>);
+        vector_res!(get, "web.get.res.json", res::Post<Reading>);
+        vector_res!(delete, "web.delete.res.json", res::Post);
+
+        vector_req!(
+            post_req,
+            "web.post.req.json",
+            req::Post::<Reading> {
+                req: "web.post",
+                route: "SensorService",
+                name: Some("/addReading"),
+                body: Some(Reading { temp: 72.32, humidity: 32.2 }),
+                ..req::Post::<Reading>::default()
+            }
+        );
+
+        vector_req!(
+            put_req,
+            "web.put.req.json",
+            req::Post::<Reading> {
+                name: Some("/updateReading"),
+                body: Some(Reading { temp: 72.32, humidity: 32.2 }),
+                ..req::Post::<Reading>::put("SensorService")
+            }
+        );
+
+        vector_req!(
+            get_req,
+            "web.get.req.json",
+            req::Get {
+                name: Some("/getLatest"),
+                ..req::Get::new("weatherInfo")
+            }
+        );
+
+        vector_req!(
+            delete_req,
+            "web.delete.req.json",
+            req::Delete {
+                name: Some("/deleteReading?id=1"),
+                ..req::Delete::new("SensorService")
+            }
+        );
+    }
+
+    #[cfg(feature = "mock")]
+    mod transfer {
+        use super::*;
+        use crate::mock::{MockTransport, NoDelay};
+        use std::vec::Vec;
+
+        #[derive(serde::Deserialize, Debug, PartialEq)]
+        struct Status {
+            status: heapless::String<8>,
+        }
+
+        #[test]
+        fn get_and_delete() {
+            let mut note: Notecard<_, 1024> = MockTransport::new()
+                .expect(
+                    r#"{"req":"web.get","route":"weatherInfo","name":"/getLatest"}"#,
+                    r#"{"result":200,"body":{"status":"ok"}}"#,
+                )
+                .expect(
+                    r#"{"req":"web.delete","route":"SensorService","name":"/deleteReading?id=1"}"#,
+                    r#"{"result":204}"#,
+                )
+                .notecard();
+
+            let r = note
+                .web()
+                .get::<Status>(&mut NoDelay, "weatherInfo", Some("/getLatest"), None)
+                .unwrap()
+                .wait(&mut NoDelay)
+                .unwrap();
+            assert_eq!(r.result, Some(200));
+            assert_eq!(r.body.unwrap().status, "ok");
+
+            let r = note
+                .web()
+                .delete::<res::Empty>(
+                    &mut NoDelay,
+                    "SensorService",
+                    Some("/deleteReading?id=1"),
+                    None,
+                )
+                .unwrap()
+                .wait(&mut NoDelay)
+                .unwrap();
+            assert_eq!(r.result, Some(204));
+            note.transport().done();
+        }
+
+        #[test]
+        fn post() {
+            let mut note: Notecard<_, 1024> = MockTransport::new()
+                .expect(
+                    r#"{"req":"web.post","route":"upload","name":"/text","payload":"aGVsbG8g","content":"text/plain","total":11,"offset":0,"status":"f814893777bcc2295fff05f00e508da6"}"#,
+                    "{}",
+                )
+                .expect(
+                    r#"{"req":"web.post","route":"upload","name":"/text","payload":"d29ybGQ=","content":"text/plain","total":11,"offset":6,"status":"7d793037a0760186574b0282f2f435e7"}"#,
+                    r#"{"result":200,"body":{"status":"ok"}}"#,
+                )
+                .notecard();
+            let mut buf = [0u8; 14];
+            let mut progress = Vec::new();
+
+            let r = WebTransfer::new(&mut buf)
+                .content("text/plain")
+                .post::<Status, _, 1024>(
+                    &mut note,
+                    &mut NoDelay,
+                    "upload",
+                    Some("/text"),
+                    &mut &b"hello world"[..],
+                    |s, t| progress.push((s, t)),
+                )
+                .unwrap();
+
+            assert_eq!(r.result, Some(200));
+            assert_eq!(progress, [(6, 11), (11, 11)]);
+            note.transport().done();
+        }
+
+        #[test]
+        fn put_binary() {
+            let mut note: Notecard<_, 1024> = MockTransport::new()
+                .expect_req("card.binary", "{}")
+                .expect_req("card.binary.put", "{}")
+                .expect(
+                    r#"{"req":"web.put","route":"upload","total":11,"offset":0,"status":"5eb63bbbe01eeed093cb22bb8f5acdc3","binary":true}"#,
+                    r#"{"result":200}"#,
+                )
+                .notecard();
+            let mut buf = [0u8; 16];
+
+            let r = WebTransfer::new(&mut buf)
+                .binary(true)
+                .put::<res::Empty, _, 1024>(
+                    &mut note,
+                    &mut NoDelay,
+                    "upload",
+                    None,
+                    &mut &b"hello world"[..],
+                    |_, _| (),
+                )
+                .unwrap();
+
+            assert_eq!(r.result, Some(200));
+            note.transport().done();
+        }
+
+        #[test]
+        fn preflight() {
+            let mut note: Notecard<_, 1024> = MockTransport::new()
+                .expect_req("hub.get", r#"{"mode":"periodic"}"#)
+                .notecard();
+            let mut buf = [0u8; 16];
+
+            // Nothing is sent when not in continuous mode.
+            assert!(matches!(
+                WebTransfer::new(&mut buf)
+                    .preflight(true)
+                    .put::<res::Empty, _, 1024>(
+                        &mut note,
+                        &mut NoDelay,
+                        "upload",
+                        None,
+                        &mut &b"hello world"[..],
+                        |_, _| (),
+                    ),
+                Err(NoteError::NotConnected)
+            ));
+            note.transport().done();
+        }
+    }
+}